@@ -9,6 +9,9 @@ crate=msp430-rt
 # remove existing blobs because otherwise this will append object files to the old blobs
 rm -f bin/*.a
 
+# The startup code is assembled for the base MSP430 ISA so that the same object runs unchanged on
+# MSP430X (CPUX) devices; it only sets up the stack pointer and branches to `Reset`, which is
+# written in Rust
 msp430-elf-as -mcpu=msp430 asm.s -o bin/$crate.o
 ar crs bin/msp430-none-elf.a bin/$crate.o
 
@@ -0,0 +1,1031 @@
+//! Build script helpers for `msp430-rt` applications
+//!
+//! # Converting TI linker command files
+//!
+//! TI's toolchains (CCS and MSP430-GCC) ship a linker command file per device, e.g.
+//! `lnk_msp430g2553.cmd`, whose `MEMORY` block describes the memory map of the device. `convert`
+//! derives the `memory.x` that `msp430-rt` expects from it, which eases the migration of existing
+//! C projects:
+//!
+//! - `RAM` becomes the `RAM` region.
+//! - `FLASH`, or `FRAM`, becomes the `ROM` region. The memory above `0x10000` (`FLASH2`, `FRAM2`)
+//!   is not used.
+//! - The vector table entries (`INT00` to `INTnn` and `RESET`) become the `VECTORS` region.
+//!
+//! The other regions, e.g. the information memory and the signatures, are left out.
+//!
+//! ``` ignore
+//! // build.rs
+//! use std::{env, path::PathBuf};
+//!
+//! fn main() {
+//!     let out = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+//!     msp430_rt_build::convert("lnk_msp430g2553.cmd", out.join("memory.x")).unwrap();
+//!
+//!     println!("cargo:rustc-link-search={}", out.display());
+//!     println!("cargo:rerun-if-changed=lnk_msp430g2553.cmd");
+//! }
+//! ```
+//!
+//! # Exporting the BSL password
+//!
+//! The password of the bootloader (BSL) is the contents of the interrupt vector table, the 32
+//! bytes from `0xFFE0` to `0xFFFF`, so it changes with every build that moves an interrupt
+//! handler. `bsl_password` extracts it from the linked ELF file of the firmware, and
+//! `export_bsl_password` writes it next to the firmware as `bsl_password.txt`, in the TI-TXT
+//! format that TI's BSL tools read, and as `bsl_password.rs`, which defines a
+//! `BSL_PASSWORD: [u8; 32]` constant for host tools written in Rust. Bytes that the image doesn't
+//! program read as `0xFF`, like erased flash.
+//!
+//! The password only exists once the firmware is linked, so this runs after its build, e.g. from
+//! the build script of the host tool or update server, which gets the path of the ELF file from
+//! the environment:
+//!
+//! ``` ignore
+//! // build.rs of the host tool
+//! use std::{env, path::PathBuf};
+//!
+//! fn main() {
+//!     let out = PathBuf::from(env::var_os("OUT_DIR").unwrap());
+//!     let elf = env::var("FIRMWARE_ELF").unwrap();
+//!     msp430_rt_build::export_bsl_password(&elf, &out).unwrap();
+//!
+//!     println!("cargo:rerun-if-env-changed=FIRMWARE_ELF");
+//!     println!("cargo:rerun-if-changed={}", elf);
+//! }
+//!
+//! // src/main.rs of the host tool
+//! include!(concat!(env!("OUT_DIR"), "/bsl_password.rs"));
+//! ```
+//!
+//! # Patching the image and vector table CRCs
+//!
+//! With the `image-crc` feature of `msp430-rt` the reset handler compares the CRC of the image
+//! against the word stored at `__IMAGE_CRC`, and with the `vector-crc` feature the CRC of the
+//! vector table against the word stored at `__VECTOR_CRC`. The linker fills both with a `0xFFFF`
+//! placeholder, so every boot of an unpatched image ends in `ImageCrcMismatch` or
+//! `VectorTableCorrupted`. `patch_crcs` computes the CRCs of the ranges `__image_crc_start` ..
+//! `__image_crc_end` and `__vector_table_start` .. `__vector_table_end` of the linked ELF file and
+//! stores those whose placeholder the image contains, and `patch_crcs_in_place` does the same to
+//! a file. Run it on the ELF file after
+//! every link and before flashing it or converting it to another format, e.g. from the cargo
+//! runner, which gets the path of the ELF file as its first argument:
+//!
+//! ``` ignore
+//! // xtask/src/main.rs, used as `runner = "cargo run -p xtask --"` in `.cargo/config.toml`
+//! fn main() {
+//!     let elf = std::env::args().nth(1).unwrap();
+//!     msp430_rt_build::patch_crcs_in_place(&elf).unwrap();
+//!
+//!     // then flash `elf`, e.g. with mspdebug
+//! }
+//! ```
+//!
+//! The ELF file must still have its symbol table, i.e. must not be stripped.
+
+#![deny(missing_docs)]
+
+use std::{error, fmt, fs, io, path::Path};
+
+/// Errors reported by the functions of this crate
+#[derive(Debug)]
+pub enum Error {
+    /// A file couldn't be read or written
+    Io(io::Error),
+    /// The linker command file has no `MEMORY` block
+    NoMemory,
+    /// A line of the `MEMORY` block couldn't be parsed
+    Parse(String),
+    /// The linker command file doesn't declare the given region
+    MissingRegion(&'static str),
+    /// The firmware isn't a little-endian 32-bit ELF file, or is truncated
+    InvalidElf(&'static str),
+    /// The firmware doesn't define the given symbol, or doesn't load anything at its address
+    MissingSymbol(&'static str),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::NoMemory => f.write_str("the linker command file has no MEMORY block"),
+            Error::Parse(line) => write!(f, "couldn't parse memory region `{}`", line),
+            Error::MissingRegion(region) => {
+                write!(f, "the linker command file has no {} region", region)
+            }
+            Error::InvalidElf(what) => write!(f, "invalid ELF file: {}", what),
+            Error::MissingSymbol(symbol) => {
+                write!(f, "the firmware doesn't define the `{}` symbol", symbol)
+            }
+        }
+    }
+}
+
+impl error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+/// A region of the `MEMORY` block
+struct Region {
+    name: String,
+    origin: u32,
+    length: u32,
+}
+
+/// Converts the TI linker command file `cmd` into the `memory.x` file `memory_x`
+pub fn convert<P: AsRef<Path>, Q: AsRef<Path>>(cmd: P, memory_x: Q) -> Result<(), Error> {
+    let cmd = fs::read_to_string(cmd)?;
+    fs::write(memory_x, self::memory_x(&cmd)?)?;
+
+    Ok(())
+}
+
+/// Returns the contents of the `memory.x` equivalent to the TI linker command file `cmd`
+pub fn memory_x(cmd: &str) -> Result<String, Error> {
+    let regions = regions(cmd)?;
+    let find = |names: &[&str]| {
+        regions
+            .iter()
+            .find(|r| names.contains(&&*r.name) && r.origin < 0x10000)
+    };
+
+    let ram = find(&["RAM"]).ok_or(Error::MissingRegion("RAM"))?;
+    let rom = find(&["FLASH", "FRAM"]).ok_or(Error::MissingRegion("FLASH or FRAM"))?;
+    let vectors = regions
+        .iter()
+        .filter(|r| r.name == "RESET" || is_vector(&r.name))
+        .collect::<Vec<_>>();
+    let start = vectors
+        .iter()
+        .map(|r| r.origin)
+        .min()
+        .ok_or(Error::MissingRegion("RESET"))?;
+    let end = vectors.iter().map(|r| r.origin + r.length).max().unwrap();
+
+    let mut memory_x = String::from("/* Generated from a TI linker command file */\nMEMORY\n{\n");
+    for (name, origin, length) in &[
+        ("RAM", ram.origin, ram.length),
+        ("ROM", rom.origin, rom.length),
+        ("VECTORS", start, end - start),
+    ] {
+        memory_x += &format!(
+            "  {} : ORIGIN = {:#06X}, LENGTH = {:#06X}\n",
+            name, origin, length
+        );
+    }
+    memory_x += "}\n";
+
+    Ok(memory_x)
+}
+
+/// Start of the interrupt vector table, whose contents are the BSL password
+const PASSWORD_START: u32 = 0xFFE0;
+
+/// Returns the BSL password of the firmware in the ELF file `elf`
+pub fn bsl_password(elf: &[u8]) -> Result<[u8; 32], Error> {
+    let elf = Elf::new(elf)?;
+
+    // Copy what the loadable segments put at `0xFFE0..0x10000`, at their load addresses
+    let mut password = [0xFF; 32];
+    for (address, byte) in (PASSWORD_START..).zip(&mut password) {
+        if let Some(value) = elf.load(address)? {
+            *byte = value;
+        }
+    }
+
+    Ok(password)
+}
+
+/// Extracts the BSL password of the ELF file `elf` and writes it to `bsl_password.txt` and
+/// `bsl_password.rs` in `dir`
+pub fn export_bsl_password<P: AsRef<Path>, Q: AsRef<Path>>(
+    elf: P,
+    dir: Q,
+) -> Result<[u8; 32], Error> {
+    let password = bsl_password(&fs::read(elf)?)?;
+    let hex = |bytes: &[u8]| {
+        bytes
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+
+    let txt = format!(
+        "@{:04X}\n{}\n{}\nq\n",
+        PASSWORD_START,
+        hex(&password[..16]),
+        hex(&password[16..])
+    );
+    fs::write(dir.as_ref().join("bsl_password.txt"), txt)?;
+
+    let bytes = password
+        .iter()
+        .map(|b| format!("{:#04X}", b))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let rs = format!(
+        "/// BSL password of the firmware, the contents of its vector table\n\
+         pub const BSL_PASSWORD: [u8; 32] = [{}];\n",
+        bytes
+    );
+    fs::write(dir.as_ref().join("bsl_password.rs"), rs)?;
+
+    Ok(password)
+}
+
+/// The CRCs stored by `patch_crcs`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Crcs {
+    /// The CRC of the image, when the `image-crc` feature is enabled
+    pub image: Option<u16>,
+    /// The CRC of the vector table, when the `vector-crc` feature is enabled
+    pub vector_table: Option<u16>,
+}
+
+/// Stores the CRCs of the image and of the vector table in the ELF file `elf`, at `__IMAGE_CRC`
+/// and `__VECTOR_CRC`
+///
+/// The CRCs cover the bytes the image loads at `__image_crc_start` .. `__image_crc_end` and at
+/// `__vector_table_start` .. `__vector_table_end`, the ranges the `image-crc` and `vector-crc`
+/// features of `msp430-rt` check at boot; bytes the image doesn't load read as `0xFF`, like erased
+/// flash. A CRC is only stored if its placeholder symbol exists, but at least one must.
+pub fn patch_crcs(elf: &mut [u8]) -> Result<Crcs, Error> {
+    let mut patches = vec![];
+    let crcs = {
+        let elf = Elf::new(elf)?;
+        let symbol = |name| elf.symbol(name)?.ok_or(Error::MissingSymbol(name));
+        let mut patch = |name, start, end| -> Result<Option<u16>, Error> {
+            let stored = match elf.symbol(name)? {
+                Some(stored) => stored,
+                None => return Ok(None),
+            };
+            let crc = elf.crc(symbol(start)?, symbol(end)?)?;
+            let at = elf.offset(stored)?.ok_or(Error::MissingSymbol(name))?;
+            patches.push((at, crc));
+
+            Ok(Some(crc))
+        };
+
+        Crcs {
+            image: patch("__IMAGE_CRC", "__image_crc_start", "__image_crc_end")?,
+            vector_table: patch("__VECTOR_CRC", "__vector_table_start", "__vector_table_end")?,
+        }
+    };
+    if patches.is_empty() {
+        return Err(Error::MissingSymbol("__IMAGE_CRC"));
+    }
+
+    for (at, crc) in patches {
+        elf.get_mut(at..at + 2)
+            .ok_or(Error::InvalidElf("truncated"))?
+            .copy_from_slice(&crc.to_le_bytes());
+    }
+
+    Ok(crcs)
+}
+
+/// Like `patch_crcs`, but patches the ELF file at the path `elf`
+pub fn patch_crcs_in_place<P: AsRef<Path>>(elf: P) -> Result<Crcs, Error> {
+    let mut bytes = fs::read(&elf)?;
+    let crcs = patch_crcs(&mut bytes)?;
+    fs::write(elf, bytes)?;
+
+    Ok(crcs)
+}
+
+/// The regions and the stack and heap sizes of a `memory.x`
+///
+/// Used by the build script of `msp430-rt` to generate its `memory` module.
+#[doc(hidden)]
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct MemoryMap {
+    /// The regions of the `MEMORY` command
+    pub regions: Vec<MemoryRegion>,
+    /// The value assigned to `_stack_size`
+    pub stack_size: Option<u32>,
+    /// The value assigned to `_heap_size`
+    pub heap_size: Option<u32>,
+}
+
+/// A region of the `MEMORY` command of a `memory.x`
+#[doc(hidden)]
+#[derive(Debug, Eq, PartialEq)]
+pub struct MemoryRegion {
+    /// Name of the region, in upper case
+    pub name: String,
+    /// Start address
+    pub origin: Option<u32>,
+    /// Size in bytes
+    pub length: Option<u32>,
+}
+
+/// Parses the `MEMORY` command of `memory_x` and the stack and heap sizes it assigns
+///
+/// Only values that are numbers, optionally with a `K` or `M` suffix, or sums of them are
+/// returned; the others, e.g. `ORIGIN(RAM) + 0x100`, are `None`.
+#[doc(hidden)]
+pub fn memory_map(memory_x: &str) -> MemoryMap {
+    // Without comments, as a list of tokens: words, numbers and single punctuation characters
+    let mut text = String::new();
+    let mut rest = memory_x;
+    while let Some(start) = rest.find("/*") {
+        text.push_str(&rest[..start]);
+        text.push(' ');
+        rest = rest[start..]
+            .find("*/")
+            .map_or("", |end| &rest[start + end + 2..]);
+    }
+    text.push_str(rest);
+    let mut tokens = vec![];
+    let mut chars = text.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if c.is_alphanumeric() || c == '_' || c == '.' {
+            let mut end = start + c.len_utf8();
+            while let Some(&(i, c)) = chars.peek() {
+                if !(c.is_alphanumeric() || c == '_' || c == '.') {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            tokens.push(&text[start..end]);
+        } else if !c.is_whitespace() {
+            tokens.push(&text[start..start + c.len_utf8()]);
+        }
+    }
+
+    let number = |token: &str| {
+        let (digits, scale) = match token.as_bytes().last() {
+            Some(b'K' | b'k') => (&token[..token.len() - 1], 1024),
+            Some(b'M' | b'm') => (&token[..token.len() - 1], 1024 * 1024),
+            _ => (token, 1),
+        };
+        match digits
+            .strip_prefix("0x")
+            .or_else(|| digits.strip_prefix("0X"))
+        {
+            Some(hex) => u32::from_str_radix(hex, 16).ok(),
+            None => digits.parse::<u32>().ok(),
+        }
+        .map(|n| n * scale)
+    };
+    // A sum of numbers, up to the token that ends it
+    let value = |tokens: &[&str], end: &[&str]| -> Option<u32> {
+        let len = tokens
+            .iter()
+            .position(|t| end.contains(t))
+            .unwrap_or(tokens.len());
+        let mut sum = 0;
+        for (i, token) in tokens[..len].iter().enumerate() {
+            match i % 2 {
+                0 => sum += number(token)?,
+                _ if *token == "+" => {}
+                _ => return None,
+            }
+        }
+        Some(sum).filter(|_| len % 2 == 1)
+    };
+
+    let mut map = MemoryMap::default();
+    let memory = tokens.iter().position(|t| *t == "MEMORY");
+    let regions = memory.map_or(&[][..], |memory| {
+        let body = &tokens[memory + 2..];
+        &body[..body.iter().position(|t| *t == "}").unwrap_or(body.len())]
+    });
+    let mut i = 0;
+    while let Some(colon) = regions[i..].iter().position(|t| *t == ":") {
+        // `NAME (attributes) : ORIGIN = origin, LENGTH = length`
+        let name = regions[i];
+        let entry = &regions[i + colon + 1..];
+        // The next entry starts with its name, before its attributes if it has any
+        let next = entry
+            .iter()
+            .position(|t| *t == ":")
+            .map_or(entry.len(), |colon| {
+                match entry[..colon].iter().rposition(|t| *t == "(") {
+                    Some(open) if entry[colon - 1] == ")" => open - 1,
+                    _ => colon - 1,
+                }
+            });
+        let entry = &entry[..next];
+        let field = |names: &[&str]| {
+            let at = entry.iter().position(|t| names.contains(t))?;
+            value(
+                entry.get(at + 2..)?,
+                &[",", "ORIGIN", "org", "o", "LENGTH", "len", "l"],
+            )
+        };
+        map.regions.push(MemoryRegion {
+            name: name.to_uppercase(),
+            origin: field(&["ORIGIN", "org", "o"]),
+            length: field(&["LENGTH", "len", "l"]),
+        });
+        i += colon + 1 + next;
+    }
+
+    // `symbol = value;`, outside of the commands
+    let assignment = |symbol: &str| {
+        tokens
+            .windows(2)
+            .position(|pair| pair[0] == symbol && pair[1] == "=")
+            .and_then(|at| value(&tokens[at + 2..], &[";"]))
+    };
+    map.stack_size = assignment("_stack_size");
+    map.heap_size = assignment("_heap_size");
+
+    map
+}
+
+/// A little-endian 32-bit ELF file
+struct Elf<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Elf<'a> {
+    fn new(data: &'a [u8]) -> Result<Self, Error> {
+        // `\x7FELF`, 32-bit, little-endian
+        if data.get(..6) != Some(&[0x7F, b'E', b'L', b'F', 1, 1]) {
+            return Err(Error::InvalidElf("not a little-endian 32-bit ELF file"));
+        }
+
+        Ok(Elf { data })
+    }
+
+    fn bytes(&self, at: usize, len: usize) -> Result<&'a [u8], Error> {
+        at.checked_add(len)
+            .and_then(|end| self.data.get(at..end))
+            .ok_or(Error::InvalidElf("truncated"))
+    }
+
+    fn u16_at(&self, at: usize) -> Result<u16, Error> {
+        let b = self.bytes(at, 2)?;
+        Ok(u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    fn u32_at(&self, at: usize) -> Result<u32, Error> {
+        let b = self.bytes(at, 4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    /// Returns the file offset of the byte that a loadable segment puts at `address`, if any
+    fn offset(&self, address: u32) -> Result<Option<usize>, Error> {
+        let phoff = self.u32_at(0x1C)? as usize;
+        let phentsize = usize::from(self.u16_at(0x2A)?);
+        let phnum = usize::from(self.u16_at(0x2C)?);
+
+        for i in 0..phnum {
+            let header = phoff + i * phentsize;
+            // `PT_LOAD`
+            if self.u32_at(header)? != 1 {
+                continue;
+            }
+            let offset = self.u32_at(header + 4)?;
+            let paddr = self.u32_at(header + 12)?;
+            let filesz = self.u32_at(header + 16)?;
+
+            if address >= paddr && address - paddr < filesz {
+                return Ok(Some((offset + (address - paddr)) as usize));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the byte that a loadable segment puts at `address`, if any
+    fn load(&self, address: u32) -> Result<Option<u8>, Error> {
+        match self.offset(address)? {
+            Some(at) => Ok(Some(self.bytes(at, 1)?[0])),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the value of the symbol `name`, if the symbol table defines it
+    fn symbol(&self, name: &str) -> Result<Option<u32>, Error> {
+        let shoff = self.u32_at(0x20)? as usize;
+        let shentsize = usize::from(self.u16_at(0x2E)?);
+        let shnum = usize::from(self.u16_at(0x30)?);
+        let section = |i: usize| shoff + i * shentsize;
+
+        for i in 0..shnum {
+            // `SHT_SYMTAB`
+            if self.u32_at(section(i) + 4)? != 2 {
+                continue;
+            }
+            let offset = self.u32_at(section(i) + 16)? as usize;
+            let size = self.u32_at(section(i) + 20)? as usize;
+            let strtab = self.u32_at(section(self.u32_at(section(i) + 24)? as usize) + 16)?;
+
+            for symbol in (offset..offset + size).step_by(16) {
+                let start = strtab as usize + self.u32_at(symbol)? as usize;
+                let len = self.data[start.min(self.data.len())..]
+                    .iter()
+                    .position(|b| *b == 0)
+                    .ok_or(Error::InvalidElf("truncated"))?;
+                if self.bytes(start, len)? == name.as_bytes() {
+                    return Ok(Some(self.u32_at(symbol + 4)?));
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Computes the CRC-16-CCITT of the bytes loaded at `start..end`, as `msp430_rt::crc` does
+    fn crc(&self, start: u32, end: u32) -> Result<u16, Error> {
+        let mut crc = 0xFFFF_u16;
+        for address in start..end {
+            crc ^= u16::from(self.load(address)?.unwrap_or(0xFF)) << 8;
+            for _ in 0..8 {
+                crc = if crc & 0x8000 != 0 {
+                    (crc << 1) ^ 0x1021
+                } else {
+                    crc << 1
+                };
+            }
+        }
+
+        Ok(crc)
+    }
+}
+
+/// `true` for the names of vector table entries, `INT00` to `INTnn`
+fn is_vector(name: &str) -> bool {
+    name.len() > 3 && name.starts_with("INT") && name[3..].bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Returns the regions of the `MEMORY` block of `cmd`
+fn regions(cmd: &str) -> Result<Vec<Region>, Error> {
+    let cmd = strip_comments(cmd);
+    let start = cmd.find("MEMORY").ok_or(Error::NoMemory)?;
+    let body = &cmd[start..];
+    let open = body.find('{').ok_or(Error::NoMemory)?;
+    let close = body.find('}').ok_or(Error::NoMemory)?;
+
+    body[open + 1..close]
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| region(line).ok_or_else(|| Error::Parse(line.to_string())))
+        .collect()
+}
+
+/// Parses a line like `RAM : origin = 0x0200, length = 0x0200`
+fn region(line: &str) -> Option<Region> {
+    let (name, attributes) = line.split_once(':')?;
+    let mut origin = None;
+    let mut length = None;
+    for attribute in attributes.split(',') {
+        let (key, value) = match attribute.split_once('=') {
+            Some((key, value)) => (key.trim().to_lowercase(), value.trim()),
+            None => continue,
+        };
+        match &*key {
+            "origin" | "org" | "o" => origin = Some(number(value)?),
+            "length" | "len" | "l" => length = Some(number(value)?),
+            // e.g. `fill = 0xFFFF` on the signature regions
+            _ => {}
+        }
+    }
+
+    Some(Region {
+        name: name.trim().to_uppercase(),
+        origin: origin?,
+        length: length?,
+    })
+}
+
+/// Parses a number in C (`0x1C00`) or TI assembler (`01C00h`) syntax
+fn number(value: &str) -> Option<u32> {
+    let value = value.trim();
+    if let Some(hex) = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        u32::from_str_radix(hex, 16).ok()
+    } else if let Some(hex) = value.strip_suffix('h').or_else(|| value.strip_suffix('H')) {
+        u32::from_str_radix(hex, 16).ok()
+    } else {
+        value.parse().ok()
+    }
+}
+
+/// Removes `/* */` and `//` comments
+fn strip_comments(cmd: &str) -> String {
+    let mut out = String::with_capacity(cmd.len());
+    let mut rest = cmd;
+    while let Some(start) = rest.find("/*") {
+        out += &rest[..start];
+        rest = match rest[start..].find("*/") {
+            Some(end) => &rest[start + end + 2..],
+            None => "",
+        };
+    }
+    out += rest;
+
+    out.lines()
+        .map(|line| line.split("//").next().unwrap_or(""))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns an ELF file that loads `segments`, `(paddr, bytes)`, and defines `symbols`
+    fn elf(segments: &[(u32, &[u8])], symbols: &[(&str, u32)]) -> Vec<u8> {
+        let phoff = 52;
+        let mut data = phoff + 32 * segments.len();
+
+        let mut file = vec![0; data];
+        file[..6].copy_from_slice(&[0x7F, b'E', b'L', b'F', 1, 1]);
+        file[0x1C..0x20].copy_from_slice(&(phoff as u32).to_le_bytes());
+        file[0x2A..0x2C].copy_from_slice(&32_u16.to_le_bytes());
+        file[0x2C..0x2E].copy_from_slice(&(segments.len() as u16).to_le_bytes());
+        for (i, (paddr, bytes)) in segments.iter().enumerate() {
+            let header = phoff + 32 * i;
+            for (at, value) in [
+                (0, 1),
+                (4, data as u32),
+                (12, *paddr),
+                (16, bytes.len() as u32),
+            ] {
+                file[header + at..header + at + 4].copy_from_slice(&value.to_le_bytes());
+            }
+            file.extend_from_slice(bytes);
+            data += bytes.len();
+        }
+
+        // `.strtab`, `.symtab` and their section headers, after the null section
+        let strtab = file.len();
+        file.push(0);
+        let mut names = vec![];
+        for (name, _) in symbols {
+            names.push(file.len() - strtab);
+            file.extend_from_slice(name.as_bytes());
+            file.push(0);
+        }
+        let symtab = file.len();
+        for (name, (_, value)) in names.iter().zip(symbols) {
+            file.extend_from_slice(&(*name as u32).to_le_bytes());
+            file.extend_from_slice(&value.to_le_bytes());
+            file.extend_from_slice(&[0; 8]);
+        }
+        let shoff = file.len();
+        file.extend_from_slice(&[0; 40]);
+        for (kind, offset, size, link) in [
+            (2_u32, symtab, 16 * symbols.len(), 2_u32),
+            (3, strtab, symtab - strtab, 0),
+        ] {
+            let mut header = [0; 40];
+            header[4..8].copy_from_slice(&kind.to_le_bytes());
+            header[16..20].copy_from_slice(&(offset as u32).to_le_bytes());
+            header[20..24].copy_from_slice(&(size as u32).to_le_bytes());
+            header[24..28].copy_from_slice(&link.to_le_bytes());
+            file.extend_from_slice(&header);
+        }
+        file[0x20..0x24].copy_from_slice(&(shoff as u32).to_le_bytes());
+        file[0x2E..0x30].copy_from_slice(&40_u16.to_le_bytes());
+        file[0x30..0x32].copy_from_slice(&3_u16.to_le_bytes());
+
+        file
+    }
+
+    /// The `MEMORY` block of a linker command file of an MSP430G2553, with 16 vectors
+    fn g2553_cmd() -> String {
+        let mut cmd = String::from(
+            "/* lnk_msp430g2553.cmd */
+-l rts430.lib
+
+MEMORY
+{
+    SFR              : origin = 0x0000, length = 0x0010
+    PERIPHERALS_8BIT : origin = 0x0010, length = 0x00F0
+    RAM              : origin = 0x0200, length = 0x0200 // 512 bytes
+    INFOA            : origin = 0x10C0, length = 0x0040
+    FLASH            : origin = 0xC000, length = 0x3FDE
+    BSLSIGNATURE     : origin = 0xFFDE, length = 0x0002, fill = 0xFFFF
+",
+        );
+        for i in 0..15 {
+            cmd += &format!(
+                "    INT{:02}            : origin = {:#06X}, length = 0x0002\n",
+                i,
+                0xFFE0 + 2 * i
+            );
+        }
+        cmd += "    RESET            : origin = 0xFFFE, length = 0x0002\n}\n";
+
+        cmd
+    }
+
+    #[test]
+    fn convert_memory_block() {
+        assert_eq!(
+            memory_x(&g2553_cmd()).unwrap(),
+            "/* Generated from a TI linker command file */
+MEMORY
+{
+  RAM : ORIGIN = 0x0200, LENGTH = 0x0200
+  ROM : ORIGIN = 0xC000, LENGTH = 0x3FDE
+  VECTORS : ORIGIN = 0xFFE0, LENGTH = 0x0020
+}
+"
+        );
+    }
+
+    #[test]
+    fn convert_fram_device() {
+        // TI assembler numbers and short attribute names; FRAM2 is above 0x10000
+        let cmd = "MEMORY
+{
+    RAM   : org = 01C00h, len = 0800h
+    FRAM  : org = 04400h, len = 0BB80h
+    FRAM2 : org = 010000h, len = 04000h
+    INT00 : org = 0FF90h, len = 2
+    RESET : org = 0FFFEh, len = 2
+}";
+
+        assert_eq!(
+            memory_x(cmd).unwrap(),
+            "/* Generated from a TI linker command file */
+MEMORY
+{
+  RAM : ORIGIN = 0x1C00, LENGTH = 0x0800
+  ROM : ORIGIN = 0x4400, LENGTH = 0xBB80
+  VECTORS : ORIGIN = 0xFF90, LENGTH = 0x0070
+}
+"
+        );
+    }
+
+    #[test]
+    fn convert_vectors() {
+        assert!(is_vector("INT00"));
+        assert!(is_vector("INT63"));
+        assert!(!is_vector("INT"));
+        assert!(!is_vector("INTERRUPTS"));
+        assert!(!is_vector("INFOA"));
+
+        // The vector table spans from the first INTnn entry to RESET
+        let cmd = "MEMORY { RAM : o = 0x200, l = 0x200
+            FLASH : o = 0xC000, l = 0x3FE0
+            INT10 : o = 0xFFF4, l = 2
+            INT11 : o = 0xFFF6, l = 2
+            RESET : o = 0xFFFE, l = 2 }";
+        assert!(memory_x(cmd)
+            .unwrap()
+            .contains("VECTORS : ORIGIN = 0xFFF4, LENGTH = 0x000C"));
+    }
+
+    #[test]
+    fn convert_missing_region() {
+        let cmd = g2553_cmd();
+
+        assert!(matches!(memory_x("-l rts430.lib"), Err(Error::NoMemory)));
+        assert!(matches!(
+            memory_x(&cmd.replace("RAM ", "SRAM")),
+            Err(Error::MissingRegion("RAM"))
+        ));
+        assert!(matches!(
+            memory_x(&cmd.replace("FLASH", "EPROM")),
+            Err(Error::MissingRegion("FLASH or FRAM"))
+        ));
+        let without_vectors = cmd
+            .lines()
+            .filter(|line| !line.contains("INT") && !line.contains("RESET"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(matches!(
+            memory_x(&without_vectors),
+            Err(Error::MissingRegion("RESET"))
+        ));
+        assert!(matches!(
+            memory_x(&cmd.replace("0x3FDE", "SIZE")),
+            Err(Error::Parse(line)) if line.starts_with("FLASH")
+        ));
+    }
+
+    #[test]
+    fn password_of_vector_table() {
+        let vectors = (0..32).collect::<Vec<u8>>();
+        let file = elf(&[(0xC000, &[0; 4]), (0xFFE0, &vectors)], &[]);
+
+        assert_eq!(bsl_password(&file).unwrap()[..], vectors[..]);
+    }
+
+    #[test]
+    fn password_of_partial_vector_table() {
+        // A segment that starts below the table and one that stops short of the end; the bytes no
+        // segment loads read as erased flash
+        let file = elf(&[(0xFFD0, &[0x11; 0x18]), (0xFFF0, &[0x22; 8])], &[]);
+        let password = bsl_password(&file).unwrap();
+
+        assert_eq!(password[..8], [0x11; 8]);
+        assert_eq!(password[8..16], [0xFF; 8]);
+        assert_eq!(password[16..24], [0x22; 8]);
+        assert_eq!(password[24..], [0xFF; 8]);
+    }
+
+    #[test]
+    fn password_without_vector_table() {
+        let file = elf(&[(0xC000, &[0; 4])], &[]);
+
+        assert_eq!(bsl_password(&file).unwrap(), [0xFF; 32]);
+    }
+
+    #[test]
+    fn password_of_invalid_elf() {
+        // The vector table is cut off in the middle
+        let mut file = elf(&[(0xFFE0, &[0; 32])], &[]);
+        file.truncate(52 + 32 + 16);
+
+        assert!(matches!(
+            bsl_password(&file),
+            Err(Error::InvalidElf("truncated"))
+        ));
+        assert!(matches!(
+            bsl_password(b":10C00000"),
+            Err(Error::InvalidElf("not a little-endian 32-bit ELF file"))
+        ));
+        assert!(matches!(
+            bsl_password(&[0x7F, b'E', b'L']),
+            Err(Error::InvalidElf("not a little-endian 32-bit ELF file"))
+        ));
+    }
+
+    #[test]
+    fn patch_image_crc() {
+        // The CRC-16-CCITT of `123456789` is 0x29B1
+        let mut file = elf(
+            &[(0xC000, b"123456789"), (0xC00A, &[0xFF, 0xFF])],
+            &[
+                ("__image_crc_start", 0xC000),
+                ("__image_crc_end", 0xC009),
+                ("__IMAGE_CRC", 0xC00A),
+            ],
+        );
+
+        assert_eq!(
+            patch_crcs(&mut file).unwrap(),
+            Crcs {
+                image: Some(0x29B1),
+                vector_table: None
+            }
+        );
+        let at = Elf::new(&file).unwrap().offset(0xC00A).unwrap().unwrap();
+        assert_eq!(file[at..at + 2], [0xB1, 0x29]);
+    }
+
+    #[test]
+    fn patch_image_crc_of_unloaded_bytes() {
+        // The gap at 0xC001 reads as erased flash
+        let mut file = elf(
+            &[
+                (0xC000, &[0x31]),
+                (0xC002, &[0x40]),
+                (0xC004, &[0xFF, 0xFF]),
+            ],
+            &[
+                ("__image_crc_start", 0xC000),
+                ("__image_crc_end", 0xC003),
+                ("__IMAGE_CRC", 0xC004),
+            ],
+        );
+        let expected = elf(&[(0xC000, &[0x31, 0xFF, 0x40])], &[]);
+        let expected = Elf::new(&expected).unwrap().crc(0xC000, 0xC003).unwrap();
+
+        assert_eq!(patch_crcs(&mut file).unwrap().image, Some(expected));
+    }
+
+    #[test]
+    fn patch_vector_table_crc() {
+        let mut file = elf(
+            &[
+                (0xFFFA, &[0x00, 0xC0, 0x00, 0xC0, 0x00, 0xC0]),
+                (0xC000, &[0xFF, 0xFF]),
+            ],
+            &[
+                ("__vector_table_start", 0xFFFA),
+                ("__vector_table_end", 0x10000),
+                ("__VECTOR_CRC", 0xC000),
+            ],
+        );
+        let vectors = Elf::new(&file).unwrap().crc(0xFFFA, 0x10000).unwrap();
+
+        assert_eq!(
+            patch_crcs(&mut file).unwrap(),
+            Crcs {
+                image: None,
+                vector_table: Some(vectors)
+            }
+        );
+        let at = Elf::new(&file).unwrap().offset(0xC000).unwrap().unwrap();
+        assert_eq!(file[at..at + 2], vectors.to_le_bytes());
+    }
+
+    #[test]
+    fn patch_without_placeholders() {
+        let mut file = elf(&[(0xC000, &[0; 4])], &[("__image_crc_start", 0xC000)]);
+
+        assert!(matches!(
+            patch_crcs(&mut file),
+            Err(Error::MissingSymbol("__IMAGE_CRC"))
+        ));
+    }
+
+    #[test]
+    fn patch_without_symbols() {
+        let mut file = elf(
+            &[(0xC000, &[0; 4])],
+            &[("__image_crc_start", 0xC000), ("__IMAGE_CRC", 0xC002)],
+        );
+
+        assert!(matches!(
+            patch_crcs(&mut file),
+            Err(Error::MissingSymbol("__image_crc_end"))
+        ));
+    }
+
+    /// A region of a `MemoryMap`
+    fn region(name: &str, origin: Option<u32>, length: Option<u32>) -> MemoryRegion {
+        MemoryRegion {
+            name: name.to_string(),
+            origin,
+            length,
+        }
+    }
+
+    #[test]
+    fn memory_map_suffixes() {
+        let map = memory_map(
+            "MEMORY\n{\n  RAM : ORIGIN = 0x2400, LENGTH = 8K\n  \
+             ROM : ORIGIN = 0x10000, LENGTH = 1M\n  INFO : ORIGIN = 6144, LENGTH = 0x0200\n}\n",
+        );
+
+        assert_eq!(
+            map.regions,
+            [
+                region("RAM", Some(0x2400), Some(0x2000)),
+                region("ROM", Some(0x10000), Some(0x100000)),
+                region("INFO", Some(0x1800), Some(0x0200)),
+            ]
+        );
+    }
+
+    #[test]
+    fn memory_map_sums() {
+        let map = memory_map(
+            "MEMORY\n{\n  ROM : ORIGIN = 0x4000 + 0x400, LENGTH = 48K - 0x80\n}\n\
+             _stack_size = 0x100 + 0x80;\n",
+        );
+
+        // Only sums are understood
+        assert_eq!(map.regions, [region("ROM", Some(0x4400), None)]);
+        assert_eq!(map.stack_size, Some(0x180));
+        assert_eq!(map.heap_size, None);
+    }
+
+    #[test]
+    fn memory_map_attributes() {
+        let map = memory_map(
+            "/* RAM : ORIGIN = 0, LENGTH = 0 */\nMEMORY\n{\n  \
+             RAM (rwx) : org = 0x0200, len = 0x0200\n  \
+             ROM (rx) : o = 0xC000, l = 0x3FE0 /* flash */\n  \
+             VECTORS : ORIGIN = 0xFFE0, LENGTH = 0x20\n}\n",
+        );
+
+        assert_eq!(
+            map.regions,
+            [
+                region("RAM", Some(0x0200), Some(0x0200)),
+                region("ROM", Some(0xC000), Some(0x3FE0)),
+                region("VECTORS", Some(0xFFE0), Some(0x0020)),
+            ]
+        );
+    }
+
+    #[test]
+    fn memory_map_skipped_expressions() {
+        let map = memory_map(
+            "MEMORY\n{\n  RAM : ORIGIN = 0x0200, LENGTH = 0x0200\n  \
+             INFOB : ORIGIN = ORIGIN(RAM) + 0x100, LENGTH = LENGTH(RAM)\n}\n\
+             _stack_size = _heap_size * 2;\n_heap_size = 0x40;\n",
+        );
+
+        assert_eq!(
+            map.regions,
+            [
+                region("RAM", Some(0x0200), Some(0x0200)),
+                region("INFOB", None, None),
+            ]
+        );
+        assert_eq!(map.stack_size, None);
+        assert_eq!(map.heap_size, Some(0x40));
+    }
+}
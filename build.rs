@@ -1,4 +1,4 @@
-use std::{env, fs, fs::File, io::Write, path::PathBuf};
+use std::{env, fs, fs::File, io::Write, path::PathBuf, process::Command};
 
 fn main() {
     let target = env::var("TARGET").unwrap();
@@ -7,10 +7,285 @@ fn main() {
     if target == "msp430-none-elf" {
         fs::copy(format!("bin/{}.a", target), out_dir.join("libmsp430-rt.a")).unwrap();
         println!("cargo:rustc-link-lib=static=msp430-rt");
+
+        check_linker();
+    }
+
+    if env::var_os("CARGO_FEATURE_JTAG_LOCK").is_some() {
+        println!(
+            "cargo:warning=msp430-rt: the `jtag-lock` feature is enabled. Flashing an image that \
+             uses `jtag_signature!` may permanently disable JTAG/SBW access to the device!"
+        );
     }
 
     // Put the linker script somewhere the linker can find it
     let out = &PathBuf::from(env::var_os("OUT_DIR").unwrap());
+
+    // C modules of the application call the runtime through the functions of the `ffi` module
+    if env::var_os("CARGO_FEATURE_C_API").is_some() {
+        let include = out.join("include");
+        fs::create_dir_all(&include).unwrap();
+        let mut f = File::create(include.join("msp430_rt.h")).unwrap();
+        writeln!(
+            f,
+            "/* Generated by the build script of msp430-rt */
+#ifndef MSP430_RT_H
+#define MSP430_RT_H
+
+#include <stdbool.h>
+#include <stdint.h>
+
+/* Resets the device */
+__attribute__((noreturn)) void msp430_rt_software_reset(void);
+
+/* Bounds of the heap, `_sheap` and `_eheap` */
+void *msp430_rt_heap_start(void);
+void *msp430_rt_heap_end(void);
+
+/* Ends the program with an exit status, through the `Exit` hook */
+__attribute__((noreturn)) void msp430_rt_exit(int16_t code);"
+        )
+        .unwrap();
+        if env::var_os("CARGO_FEATURE_NEWLIB_SYSCALLS").is_some() {
+            writeln!(
+                f,
+                "
+/* Calls `HeapCollision` if the stack has grown into the heap */
+void msp430_rt_check_heap_collision(void);"
+            )
+            .unwrap();
+        }
+        if env::var_os("CARGO_FEATURE_RESET_COUNTERS").is_some() {
+            writeln!(
+                f,
+                "
+/* Causes of the resets counted by the `reset-counters` feature */
+#define MSP430_RT_RESET_BROWNOUT 0
+#define MSP430_RT_RESET_PIN 1
+#define MSP430_RT_RESET_SVS 2
+#define MSP430_RT_RESET_WATCHDOG 3
+#define MSP430_RT_RESET_SOFTWARE 4
+#define MSP430_RT_RESET_SECURITY_VIOLATION 5
+#define MSP430_RT_RESET_OTHER 6
+
+/* Number of resets due to a cause; the cause of the last reset, or -1 if it wasn't recorded */
+uint16_t msp430_rt_reset_count(uint8_t cause);
+int8_t msp430_rt_reset_last_cause(void);
+void msp430_rt_reset_clear_counts(void);"
+            )
+            .unwrap();
+        }
+        if env::var_os("CARGO_FEATURE_BLACKBOX").is_some() {
+            writeln!(
+                f,
+                "
+/* Black-box event log; `msp430_rt_blackbox_events` copies up to `max` events, oldest first,
+   and returns how many it copied */
+struct msp430_rt_event {{
+    uint16_t timestamp;
+    uint16_t code;
+}};
+
+void msp430_rt_blackbox_record(uint16_t code);
+void msp430_rt_blackbox_seal(void);
+bool msp430_rt_blackbox_is_sealed(void);
+void msp430_rt_blackbox_clear(void);
+uint8_t msp430_rt_blackbox_events(struct msp430_rt_event *events, uint8_t max);"
+            )
+            .unwrap();
+        }
+        if env::var_os("CARGO_FEATURE_ISR_DEPTH").is_some() {
+            writeln!(
+                f,
+                "
+/* Nesting of the interrupt handlers defined with `#[interrupt]` */
+bool msp430_rt_in_isr(void);
+uint8_t msp430_rt_isr_depth(void);"
+            )
+            .unwrap();
+        }
+        writeln!(f, "\n#endif").unwrap();
+        println!("cargo:include={}", include.display());
+    }
+
+    // The part number selects the family defaults: number of vectors, information memory and
+    // peripheral addresses. Those are provided weakly, so `memory.x` can still override them.
+    println!(
+        "cargo:rustc-check-cfg=cfg(msp430_family, values(\"f1\", \"f2\", \"f4\", \"f5\", \"f6\", \
+         \"g2\", \"fr2\", \"fr4\", \"fr5\", \"fr6\"))"
+    );
+    println!("cargo:rustc-check-cfg=cfg(msp430x, msp430_fram)");
+    let mcu = mcu();
+    let family = mcu.as_ref().map(|mcu| {
+        let family = Family::of(mcu).unwrap_or_else(|| panic!("unknown MSP430 part `{}`", mcu));
+        println!("cargo:rustc-cfg=msp430_family=\"{}\"", family.name);
+        if family.cpux {
+            println!("cargo:rustc-cfg=msp430x");
+        }
+        if family.fram {
+            println!("cargo:rustc-cfg=msp430_fram");
+        }
+        family
+    });
+    let mut f = File::create(out.join("mcu.x")).unwrap();
+    match &family {
+        Some(family) => {
+            let (start, len, segment) = family.info;
+            writeln!(f, "/* Family {} */", family.name).unwrap();
+            writeln!(f, "PROVIDE(__info_start = {:#06X});", start).unwrap();
+            writeln!(f, "PROVIDE(__info_end = {:#06X});", start + len).unwrap();
+            writeln!(f, "PROVIDE(__info_segment_size = {:#06X});", segment).unwrap();
+            if let Some(crc16) = family.crc16 {
+                writeln!(f, "PROVIDE(__crc16 = {:#06X});", crc16).unwrap();
+            }
+            if let Some(syscfg0) = family.syscfg0 {
+                writeln!(f, "PROVIDE(__syscfg0 = {:#06X});", syscfg0).unwrap();
+            }
+            writeln!(f, "PROVIDE(__wdtctl = {:#06X});", family.wdtctl).unwrap();
+            if let Some(sysrstiv) = family.sysrstiv {
+                writeln!(f, "PROVIDE(__sysrstiv = {:#06X});", sysrstiv).unwrap();
+            }
+            let (out, dir) = family.port1;
+            writeln!(f, "PROVIDE(__boot_marker_out = {:#06X});", out).unwrap();
+            writeln!(f, "PROVIDE(__boot_marker_dir = {:#06X});", dir).unwrap();
+            writeln!(f, "PROVIDE(__timer_a0 = {:#06X});", family.timer_a0).unwrap();
+            if let Some((uart, brw, mctl)) = family.uart_a0 {
+                writeln!(f, "PROVIDE(__console_uart = {:#06X});", uart).unwrap();
+                writeln!(f, "PROVIDE(__console_brw = {});", brw).unwrap();
+                writeln!(f, "PROVIDE(__console_mctl = {:#06X});", mctl).unwrap();
+            }
+        }
+        None => writeln!(f, "/* No MCU selected */").unwrap(),
+    }
+    // The backchannel UART of the LaunchPad; eUSCI_A0 sits at a different address on FR2xx devices
+    if env::var_os("CARGO_FEATURE_BOARD_MSP_EXP430FR2433").is_some() {
+        writeln!(f, "/* MSP-EXP430FR2433 */").unwrap();
+        writeln!(f, "PROVIDE(__updater_uart = 0x0500);").unwrap();
+    }
+
+    // The layout named by `MSP430_RT_MEMORY_X` goes in the search path. This gives each image of
+    // a workspace its own `memory.x`: the linker runs from the root of the workspace, where it
+    // would otherwise look for a single `memory.x` shared by all the images
+    let given = env::var_os("MSP430_RT_MEMORY_X").map(|path| {
+        let path = PathBuf::from(path);
+        // The build script runs in the directory of this crate, not in the one of the image
+        if path.is_relative() {
+            panic!(
+                "`MSP430_RT_MEMORY_X` is set to the relative path `{}`; set it with \
+                 `relative = true` in `.cargo/config.toml`, or to an absolute path",
+                path.display()
+            );
+        }
+        println!("cargo:rerun-if-changed={}", path.display());
+        let memory_x = fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("could not read `{}`: {}", path.display(), e));
+        (path, memory_x)
+    });
+    // A `memory.x` in the root of the application, or of its workspace
+    let root = out
+        .ancestors()
+        .skip(1)
+        .map(|dir| dir.join("memory.x"))
+        .find(|path| path.is_file());
+
+    let data_in_info = env::var_os("CARGO_FEATURE_DATA_IN_INFO").is_some();
+    // Flash devices keep the counters of the `reset-counters` feature in the information memory
+    let counters_in_info = env::var_os("CARGO_FEATURE_RESET_COUNTERS").is_some()
+        && family
+            .as_ref()
+            .is_some_and(|family| !family.fram && family.sysrstiv.is_some());
+
+    // The `INFO` region of the information memory. On flash devices its last segment, INFOA,
+    // holds the calibration data of the clocks, so it's left out
+    let info = family.as_ref().map(|family| {
+        let (start, len, segment) = family.info;
+        let len = if segment == len { len } else { len - segment };
+        format!("INFO : ORIGIN = {:#06X}, LENGTH = {:#06X}", start, len)
+    });
+
+    // Known parts otherwise get a generated `memory.x`. The linker looks for it in the current
+    // directory before the search paths, so a `memory.x` in the root of the application still wins.
+    if let Some((_, memory_x)) = &given {
+        fs::write(out.join("memory.x"), memory_x).unwrap();
+    } else if let Some(part) = mcu.as_ref().and_then(|mcu| Part::of(mcu)) {
+        let mut f = File::create(out.join("memory.x")).unwrap();
+        writeln!(f, "/* Memory layout of the {} */", part.name.to_uppercase()).unwrap();
+        writeln!(f, "MEMORY\n{{").unwrap();
+        for (region, (origin, length)) in &[
+            ("RAM", part.ram),
+            ("ROM", part.rom),
+            ("VECTORS", part.vectors),
+        ] {
+            writeln!(
+                f,
+                "  {} : ORIGIN = {:#06X}, LENGTH = {:#06X}",
+                region, origin, length
+            )
+            .unwrap();
+        }
+        // The `.data` initializers and the reset counters can go in the information memory
+        if data_in_info || counters_in_info {
+            if let Some(info) = &info {
+                writeln!(f, "  {}", info).unwrap();
+            }
+        }
+        writeln!(f, "}}").unwrap();
+
+        // A `memory.x` in the root of the application, or of its workspace, is linked instead, and
+        // the constants of the `memory` module don't describe it
+        if let Some(root) = &root {
+            println!("cargo:rerun-if-changed={}", root.display());
+            println!(
+                "cargo:warning=`{}` is linked instead of the `memory.x` generated for the {}, \
+                 but the constants of `msp430_rt::memory` describe the generated one; set \
+                 `MSP430_RT_MEMORY_X` to its path",
+                root.display(),
+                part.name.to_uppercase()
+            );
+        }
+    } else {
+        let _ = fs::remove_file(out.join("memory.x"));
+    }
+
+    // The information memory must be declared by the `memory.x` that is linked: the one named by
+    // `MSP430_RT_MEMORY_X`, else the one in the root of the application, else the generated one
+    if data_in_info || counters_in_info {
+        let feature = if data_in_info {
+            "data-in-info"
+        } else {
+            "reset-counters"
+        };
+        let path = match (&given, &root) {
+            (Some((path, _)), _) => path.clone(),
+            (None, Some(path)) => {
+                println!("cargo:rerun-if-changed={}", path.display());
+                path.clone()
+            }
+            (None, None) => out.join("memory.x"),
+        };
+        if let Ok(memory_x) = fs::read_to_string(&path) {
+            let map = msp430_rt_build::memory_map(&memory_x);
+            if !map.regions.iter().any(|region| region.name == "INFO") {
+                panic!(
+                    "the `{}` feature needs an `INFO` region, the information memory, but `{}` \
+                     doesn't declare one; add it to the `MEMORY` block, e.g. `{}`",
+                    feature,
+                    path.display(),
+                    info.as_deref()
+                        .unwrap_or("INFO : ORIGIN = 0x1000, LENGTH = 0x00C0")
+                );
+            }
+        }
+    }
+
+    // The memory map, as typed constants of the `memory` module
+    let memory_x = fs::read_to_string(out.join("memory.x")).ok();
+    let mut f = File::create(out.join("memory.rs")).unwrap();
+    match &memory_x {
+        Some(memory_x) => memory_map(memory_x, &mut f),
+        None => writeln!(f, "// No `memory.x`; see the documentation of this module").unwrap(),
+    }
+
     let link_x = include_bytes!("link.x.in");
     if env::var_os("CARGO_FEATURE_DEVICE").is_some() {
         let mut f = File::create(out.join("link.x")).unwrap();
@@ -32,8 +307,724 @@ INCLUDE device.x"#
         f.write_all(link_x).unwrap();
     };
 
+    // The proxy vector table is a list of `br &vector` instructions, one per entry of the vector
+    // table, which follows it in ROM. A bootloader that owns the hardware vector table jumps to
+    // `ORIGIN(ROM) + 4 * n` to dispatch interrupt `n` (the reset vector being the last entry) to
+    // this application.
+    let mut f = File::create(out.join("proxy_vectors.x")).unwrap();
+    if env::var_os("CARGO_FEATURE_PROXY_VECTORS").is_some() {
+        let count = env::var("MSP430_RT_PROXY_VECTORS")
+            .ok()
+            .and_then(|n| n.parse::<u16>().ok())
+            .or_else(|| family.as_ref().map(|family| family.vectors))
+            .expect(
+                "the `proxy-vectors` feature requires `MSP430_RT_PROXY_VECTORS` to be set to the \
+                 number of vectors (including the reset vector) of the target device, or \
+                 `MSP430_MCU` to its part number",
+            );
+
+        writeln!(f, "/* {} proxy vectors: `mov &vector, pc` */", count).unwrap();
+        for i in 0..count {
+            writeln!(f, "SHORT(0x4210); SHORT(ADDR(.vector_table) + {});", 2 * i).unwrap();
+        }
+    } else {
+        writeln!(f, "/* The `proxy-vectors` feature is disabled */").unwrap();
+    }
+
+    // With the `ram-vectors` feature every hardware vector but the reset vector points to a stub
+    // in ROM that jumps through the corresponding entry of a vector table in RAM. The handlers
+    // linked into the vector table become the initial contents of the RAM vector table.
+    let mut defaults = File::create(out.join("vector_defaults.x")).unwrap();
+    let mut table = File::create(out.join("vector_table.x")).unwrap();
+    let mut dispatch = File::create(out.join("ram_dispatch.x")).unwrap();
+    if env::var_os("CARGO_FEATURE_RAM_VECTORS").is_some() {
+        let count = env::var("MSP430_RT_RAM_VECTORS")
+            .ok()
+            .and_then(|n| n.parse::<u16>().ok())
+            .or_else(|| family.as_ref().map(|family| family.vectors))
+            .filter(|n| *n > 0)
+            .expect(
+                "the `ram-vectors` feature requires `MSP430_RT_RAM_VECTORS` to be set to the \
+                 number of vectors (including the reset vector) of the target device, or \
+                 `MSP430_MCU` to its part number",
+            );
+
+        writeln!(defaults, "KEEP(*(.vector_table.interrupts));").unwrap();
+        writeln!(defaults, "KEEP(*(.__RESET_VECTOR));").unwrap();
+
+        writeln!(table, "/* {} vectors */", count).unwrap();
+        writeln!(dispatch, "/* {} stubs: `mov &ram_vector, pc` */", count - 1).unwrap();
+        for i in 0..count - 1 {
+            writeln!(table, "SHORT(ADDR(.ram_dispatch) + {});", 4 * i).unwrap();
+            writeln!(
+                dispatch,
+                "SHORT(0x4210); SHORT(__ram_vectors_start + {});",
+                2 * i
+            )
+            .unwrap();
+        }
+        // The RAM vector table is not initialized yet when the device resets
+        writeln!(table, "SHORT(ResetTrampoline);").unwrap();
+    } else if env::var_os("CARGO_FEATURE_C_VECTORS").is_none() {
+        for f in &mut [&mut defaults, &mut table, &mut dispatch] {
+            writeln!(f, "/* The `ram-vectors` feature is disabled */").unwrap();
+        }
+    }
+
+    // With the `c-vectors` feature the vector table is populated from both the Rust handlers and
+    // the C handlers declared with `__attribute__((interrupt(N)))`, which msp430-elf-gcc places
+    // in the `__interrupt_vector_N` sections (N starting at 1). One input section can't fill a
+    // slot of the other's table, so every hardware vector but the reset vector points to a stub
+    // that jumps through the C vector if there's one, or else through the Rust vector; the Rust
+    // vectors are stored in `.vector_defaults`.
+    let mut vectors = File::create(out.join("c_vectors.x")).unwrap();
+    let mut stubs = File::create(out.join("c_dispatch.x")).unwrap();
+    if env::var_os("CARGO_FEATURE_C_VECTORS").is_some() {
+        let count = env::var("MSP430_RT_C_VECTORS")
+            .ok()
+            .and_then(|n| n.parse::<u16>().ok())
+            .or_else(|| family.as_ref().map(|family| family.vectors))
+            .filter(|n| *n > 0)
+            .expect(
+                "the `c-vectors` feature requires `MSP430_RT_C_VECTORS` to be set to the number \
+                 of vectors (including the reset vector) of the target device, or `MSP430_MCU` \
+                 to its part number",
+            );
+
+        writeln!(defaults, "KEEP(*(.vector_table.interrupts));").unwrap();
+        writeln!(defaults, "KEEP(*(.__RESET_VECTOR));").unwrap();
+
+        writeln!(dispatch, "/* The `ram-vectors` feature is disabled */").unwrap();
+        writeln!(table, "/* {} vectors */", count).unwrap();
+        writeln!(stubs, "/* {} stubs: `mov &vector, pc` */", count - 1).unwrap();
+        for i in 0..count - 1 {
+            writeln!(
+                vectors,
+                "__c_vector_{0}_start = .; KEEP(*(__interrupt_vector_{1})); __c_vector_{0}_end = .;",
+                i,
+                i + 1
+            )
+            .unwrap();
+            writeln!(table, "SHORT(ADDR(.c_dispatch) + {});", 4 * i).unwrap();
+            writeln!(
+                stubs,
+                "SHORT(0x4210); SHORT(__c_vector_{0}_end != __c_vector_{0}_start ? \
+                 __c_vector_{0}_start : __vector_defaults_start + {1});",
+                i,
+                2 * i
+            )
+            .unwrap();
+        }
+        writeln!(table, "SHORT(ResetTrampoline);").unwrap();
+    } else {
+        for f in &mut [&mut vectors, &mut stubs] {
+            writeln!(f, "/* The `c-vectors` feature is disabled */").unwrap();
+        }
+    }
+
+    // IP Encapsulation: the bootcode reads the IPE signature after a BOR and protects the region
+    // described by the init structure it points to. The structure starts the region, so it's
+    // protected as well, and holds MPUIPC0, the upper and lower borders and a check code: the odd
+    // parity of the three words, which linker scripts can only express with `&`, `|` and `~`.
+    let mut f = File::create(out.join("ipe.x")).unwrap();
+    if env::var_os("CARGO_FEATURE_IPE").is_some() {
+        let (c, b2, b1) = ("__ipe_mpuipc0", "(__ipe_end >> 4)", "(__ipe_start >> 4)");
+        let check = format!(
+            "~(({c} & {b2} & {b1}) | ({c} & ~{b2} & ~{b1}) | (~{c} & {b2} & ~{b1}) | \
+             (~{c} & ~{b2} & {b1}))",
+            c = c,
+            b2 = b2,
+            b1 = b1
+        );
+        writeln!(f, ". = ALIGN(1024);").unwrap();
+        writeln!(f, "__ipe_start = .;").unwrap();
+        writeln!(f, "__ipe_init = .;").unwrap();
+        writeln!(f, "SHORT({});", c).unwrap();
+        writeln!(f, "SHORT({});", b2).unwrap();
+        writeln!(f, "SHORT({});", b1).unwrap();
+        writeln!(f, "SHORT({} & 0xFFFF);", check).unwrap();
+        writeln!(f, "KEEP(*(.ipe .ipe.*));").unwrap();
+        writeln!(f, ". = ALIGN(1024);").unwrap();
+        writeln!(f, "__ipe_end = .;").unwrap();
+        // The second word of the IPE signature; the structure is aligned, so nothing is lost
+        writeln!(f, "__ipe_init_shifted = ABSOLUTE(__ipe_init >> 4);").unwrap();
+    } else {
+        writeln!(f, "/* The `ipe` feature is disabled */").unwrap();
+    }
+
+    // Checkpoints: the snapshot of the RAM can take as much as the whole region
+    let mut f = File::create(out.join("checkpoint.x")).unwrap();
+    if env::var_os("CARGO_FEATURE_CHECKPOINT").is_some() {
+        writeln!(f, "__checkpoint_image = .;").unwrap();
+        writeln!(f, ". += LENGTH(RAM);").unwrap();
+    } else {
+        writeln!(f, "/* The `checkpoint` feature is disabled */").unwrap();
+    }
+
+    // Reset counters on flash devices: a segment of the information memory of their own, as
+    // updating them erases the whole segment
+    let mut f = File::create(out.join("reset_counts.x")).unwrap();
+    match family.as_ref().filter(|_| counters_in_info) {
+        Some(family) => {
+            let segment = family.info.2;
+            writeln!(f, ".reset_counts : ALIGN({:#06X})\n{{", segment).unwrap();
+            writeln!(f, "  KEEP(*(.reset_counts));").unwrap();
+            writeln!(f, "  . = ALIGN({:#06X});\n}} > INFO", segment).unwrap();
+        }
+        None => writeln!(f, "/* No reset counters in INFO */").unwrap(),
+    }
+
+    // The heap: a fixed size by default, or all the RAM left between the statics and the stack
+    let mut f = File::create(out.join("heap.x")).unwrap();
+    if env::var_os("CARGO_FEATURE_AUTO_HEAP").is_some() {
+        writeln!(f, "_eheap = (_stack_start - _stack_size) & ~1;").unwrap();
+        writeln!(f, "_heap_size = _eheap - _sheap;").unwrap();
+        writeln!(
+            f,
+            r#"ASSERT(_eheap >= _sheap, "
+ERROR(msp430-rt): .bss, .data and the stack size (_stack_size) leave no RAM for the heap
+of the `auto-heap` feature. Reduce them, or the RAM used by the program");"#
+        )
+        .unwrap();
+        writeln!(
+            f,
+            r#"ASSERT(SIZEOF(.runtime_config.heap_size) == 0, "
+ERROR(msp430-rt): The `auto-heap` feature sizes the heap; remove the `heap_size` setting
+of `runtime_config!`");"#
+        )
+        .unwrap();
+    } else {
+        writeln!(
+            f,
+            "PROVIDE(_heap_size = SIZEOF(.runtime_config.heap_size));"
+        )
+        .unwrap();
+        writeln!(f, "_eheap = _sheap + _heap_size;").unwrap();
+    }
+
+    // Fast boot: the reset handler skips the hooks and the constructors, which the link must then
+    // not contain, and the time spent initializing `.bss` and `.data` is kept within a budget. The
+    // estimate assumes the loops of `r0`, which take at most 8 cycles per word of `.bss` and 10 per
+    // word of `.data`, plus the fixed cost of the trampoline and of the calls
+    let mut f = File::create(out.join("fast_boot.x")).unwrap();
+    if env::var_os("CARGO_FEATURE_FAST_BOOT").is_some() {
+        writeln!(f, "PROVIDE(_boot_cycles_max = 1000);").unwrap();
+        writeln!(
+            f,
+            "__boot_cycles = 50 + SIZEOF(.bss) / 2 * 8 + SIZEOF(.data) / 2 * 10;"
+        )
+        .unwrap();
+        writeln!(
+            f,
+            r#"ASSERT(__boot_cycles <= _boot_cycles_max, "
+ERROR(msp430-rt): Initializing .bss and .data takes more than _boot_cycles_max cycles
+(1000 by default) with the `fast-boot` feature. Reduce the size of the `static`
+variables, e.g. by moving some to .user_ram, or raise the limit in memory.x");"#
+        )
+        .unwrap();
+        writeln!(
+            f,
+            r#"ASSERT(__init_array_end == __init_array_start && __ctors_end == __ctors_start, "
+ERROR(msp430-rt): The `fast-boot` feature doesn't run constructors, but the program
+contains some, e.g. functions marked with `#[ctor]`");"#
+        )
+        .unwrap();
+    } else {
+        writeln!(f, "/* The `fast-boot` feature is disabled */").unwrap();
+    }
+
+    // Unused interrupts are handled by the policy of `MSP430_RT_SPURIOUS_INTERRUPTS`: loop in
+    // `DefaultHandler_`, count and ignore them, reset the device, call a hook, or
+    // stop under the debugger in debug builds. Strict release builds don't get a default handler
+    // at all: the vectors that still refer to it make the link fail.
+    let mut f = File::create(out.join("default_handler.x")).unwrap();
+    let release = env::var("PROFILE").map(|p| p == "release").unwrap_or(false);
+    let strict = env::var_os("CARGO_FEATURE_STRICT_VECTORS").is_some() && release;
+    let reset_unused = env::var_os("CARGO_FEATURE_RESET_UNUSED_VECTORS").is_some();
+    let handler = match env::var("MSP430_RT_SPURIOUS_INTERRUPTS") {
+        Ok(policy) => match &*policy {
+            _ if reset_unused && policy != "reset" => panic!(
+                "the `reset-unused-vectors` feature selects the `reset` policy, but \
+                 `MSP430_RT_SPURIOUS_INTERRUPTS` is `{}`",
+                policy
+            ),
+            "loop" => "DefaultHandler_",
+            "ignore" => "DefaultHandlerIgnore",
+            "reset" => "DefaultHandlerReset",
+            "hook" => "DefaultHandlerHook",
+            "halt-in-debug" if release => "DefaultHandlerIgnore",
+            "halt-in-debug" => "DefaultHandlerHalt",
+            _ => panic!(
+                "`MSP430_RT_SPURIOUS_INTERRUPTS` must be one of `loop`, `ignore`, `reset`, `hook` \
+                 or `halt-in-debug`, found `{}`",
+                policy
+            ),
+        },
+        Err(_) if reset_unused => "DefaultHandlerReset",
+        Err(_) => "DefaultHandler_",
+    };
+    if strict {
+        writeln!(
+            f,
+            "/* `strict-vectors`: every interrupt must have a handler */"
+        )
+        .unwrap();
+        writeln!(
+            f,
+            "PROVIDE(DefaultHandler = ERROR_msp430_rt_strict_vectors_interrupt_without_handler);"
+        )
+        .unwrap();
+    } else {
+        // If this line is not here, all unused interrupt handlers will be zeroed out instead of
+        // going to the DefaultHandler!
+        writeln!(f, "EXTERN(DefaultHandler);").unwrap();
+        writeln!(f, "PROVIDE(DefaultHandler = {});", handler).unwrap();
+    }
+    // The MPU reports violations through the system NMI; this takes precedence over the default
+    // of `device.x`, which is included later
+    let mpu_family = matches!(family.as_ref().map(|f| f.name), Some("fr5" | "fr6"));
+    if env::var_os("CARGO_FEATURE_MPU").is_some()
+        || (env::var_os("CARGO_FEATURE_READONLY_TEXT").is_some() && mpu_family)
+    {
+        writeln!(f, "PROVIDE(SYSNMI = MpuNmi);").unwrap();
+    }
+
+    // When building one slot of an A/B (dual image) setup `memory.x` provides one `ROM_<slot>`
+    // region per slot instead of `ROM`; the selected slot is aliased to the region `link.x`
+    // expects. A RAM image has no `ROM` region at all; everything is placed in `RAM` instead.
+    let ram_image = env::var_os("CARGO_FEATURE_RAM_IMAGE").is_some();
+    let mut f = File::create(out.join("regions.x")).unwrap();
+    match env::var("MSP430_RT_SLOT") {
+        Ok(_) if ram_image => panic!("`MSP430_RT_SLOT` can't be used with the `ram-image` feature"),
+        Ok(slot) => {
+            let (slot, index) = match &*slot.to_uppercase() {
+                "A" => ("A", 0),
+                "B" => ("B", 1),
+                _ => panic!(
+                    "`MSP430_RT_SLOT` must be either `A` or `B`, found `{}`",
+                    slot
+                ),
+            };
+
+            writeln!(f, "/* Building slot {} */", slot).unwrap();
+            writeln!(f, "REGION_ALIAS(\"ROM\", ROM_{});", slot).unwrap();
+            writeln!(f, "_slot = {};", index).unwrap();
+        }
+        Err(_) if ram_image => {
+            writeln!(f, "/* Building a RAM image */").unwrap();
+            writeln!(f, "REGION_ALIAS(\"ROM\", RAM);").unwrap();
+        }
+        Err(_) => writeln!(f, "/* Not building a slot; `MSP430_RT_SLOT` is unset */").unwrap(),
+    }
+    // The initial values of `.data` are stored in ROM, or in the information memory, which the
+    // `INFO` region of `memory.x` describes
+    if data_in_info {
+        if ram_image {
+            panic!("the `data-in-info` feature can't be combined with `ram-image`");
+        }
+        // The image CRC and signature cover a single range of `ROM`
+        for feature in ["image-crc", "image-signature"] {
+            let var = format!("CARGO_FEATURE_{}", feature.to_uppercase().replace('-', "_"));
+            if env::var_os(var).is_some() {
+                panic!(
+                    "the `data-in-info` feature can't be combined with `{}`, which wouldn't cover \
+                     the initial values of `.data` in the information memory",
+                    feature
+                );
+            }
+        }
+        writeln!(f, "REGION_ALIAS(\"DATA_LOAD\", INFO);").unwrap();
+    } else {
+        writeln!(f, "REGION_ALIAS(\"DATA_LOAD\", ROM);").unwrap();
+    }
+    // An application behind a bootloader keeps its vector table in ROM, right after the proxy
+    // vector table; a RAM image keeps it in RAM, with the dispatch stubs of `ram-vectors`
+    if env::var_os("CARGO_FEATURE_PROXY_VECTORS").is_some() || ram_image {
+        writeln!(f, "REGION_ALIAS(\"VECTORS\", ROM);").unwrap();
+    }
+    // The boot selector needs to know where both slots are
+    if env::var_os("CARGO_FEATURE_BOOT_SELECTOR").is_some() {
+        for slot in &["a", "b"] {
+            let region = format!("ROM_{}", slot.to_uppercase());
+            writeln!(f, "__slot_{}_start = ORIGIN({});", slot, region).unwrap();
+            writeln!(
+                f,
+                "__slot_{0}_end = ORIGIN({1}) + LENGTH({1});",
+                slot, region
+            )
+            .unwrap();
+        }
+    }
+
     println!("cargo:rustc-link-search={}", out_dir.display());
 
     println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=bin/msp430-none-elf.a");
     println!("cargo:rerun-if-changed=link.x.in");
+    println!("cargo:rerun-if-env-changed=MSP430_MCU");
+    println!("cargo:rerun-if-env-changed=MSP430_RT_MEMORY_X");
+    println!("cargo:rerun-if-env-changed=MSP430_RT_C_VECTORS");
+    println!("cargo:rerun-if-env-changed=MSP430_RT_PROXY_VECTORS");
+    println!("cargo:rerun-if-env-changed=MSP430_RT_RAM_VECTORS");
+    println!("cargo:rerun-if-env-changed=MSP430_RT_SLOT");
+    println!("cargo:rerun-if-env-changed=MSP430_RT_SPURIOUS_INTERRUPTS");
+}
+
+/// Oldest `msp430-elf-gcc` accepted by `check_linker`, as (major, minor)
+const MIN_GCC_VERSION: (u32, u32) = (8, 3);
+
+/// Fails the build if the linker is the GCC driver and it can't be run, or is too old
+///
+/// A missing `msp430-elf-gcc` otherwise only shows up as a confusing error at the end of the build,
+/// when the binary is linked. Other linkers, e.g. `rust-lld`, aren't checked. Setting
+/// `MSP430_RT_SKIP_LINKER_CHECK` skips the check, e.g. for `cargo check` on a machine without the
+/// toolchain.
+fn check_linker() {
+    println!("cargo:rerun-if-env-changed=MSP430_RT_SKIP_LINKER_CHECK");
+    if env::var_os("MSP430_RT_SKIP_LINKER_CHECK").is_some() {
+        return;
+    }
+
+    // `-C linker` passed through `RUSTFLAGS` takes precedence over the Cargo configuration
+    let rustflags = env::var("CARGO_ENCODED_RUSTFLAGS").unwrap_or_default();
+    let mut flags = rustflags.split('\x1f');
+    let mut from_flags = None;
+    while let Some(flag) = flags.next() {
+        let value = match flag {
+            "-C" => flags.next().unwrap_or(""),
+            _ => flag.trim_start_matches("-C"),
+        };
+        if let Some(linker) = value.strip_prefix("linker=") {
+            from_flags = Some(linker.to_string());
+        }
+    }
+    // `msp430-elf-gcc` is the default linker of the `msp430-none-elf` target
+    let linker = from_flags
+        .or_else(|| env::var("RUSTC_LINKER").ok())
+        .unwrap_or_else(|| "msp430-elf-gcc".to_string());
+    if !linker.contains("gcc") {
+        return;
+    }
+
+    let output = match Command::new(&linker).arg("--version").output() {
+        Ok(output) if output.status.success() => output,
+        _ => panic!(
+            "the linker `{}` could not be run. Install the MSP430 GCC toolchain from TI \
+             (\"MSP430-GCC-OPENSOURCE\") and add its `bin` directory to `PATH`, or link with \
+             `rust-lld` instead (see the msp430-rt documentation on linking without \
+             `msp430-elf-gcc`). Set `MSP430_RT_SKIP_LINKER_CHECK` to build without a linker",
+            linker
+        ),
+    };
+    // e.g. `msp430-elf-gcc (Mitto Systems Limited - msp430-gcc 9.3.1.11) 9.3.1`; versions that
+    // can't be parsed aren't checked
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let version = stdout
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().last())
+        .map(|version| {
+            version
+                .split('.')
+                .map(|part| part.parse::<u32>().ok())
+                .collect::<Vec<_>>()
+        });
+    if let Some(&[Some(major), Some(minor), ..]) = version.as_deref() {
+        if (major, minor) < MIN_GCC_VERSION {
+            panic!(
+                "the linker `{}` is version {}.{}, but msp430-rt needs {}.{} or later. Install \
+                 a recent MSP430 GCC toolchain from TI (\"MSP430-GCC-OPENSOURCE\"), or link \
+                 with `rust-lld` instead",
+                linker, major, minor, MIN_GCC_VERSION.0, MIN_GCC_VERSION.1
+            );
+        }
+    }
+}
+
+/// Writes the regions of the `MEMORY` command of `memory_x`, and the stack and heap sizes it
+/// assigns, as Rust constants
+///
+/// Only regions and assignments whose values are numbers, optionally with a `K` or `M` suffix, or
+/// sums of them are written; the others, e.g. `ORIGIN(RAM) + 0x100`, are skipped.
+fn memory_map(memory_x: &str, f: &mut File) {
+    let map = msp430_rt_build::memory_map(memory_x);
+
+    writeln!(f, "// Generated by the build script of msp430-rt").unwrap();
+    let mut ram_end = None;
+    for region in &map.regions {
+        if let (Some(origin), Some(length)) = (region.origin, region.length) {
+            let name = &region.name;
+            writeln!(f, "/// Start address of the `{}` region", name).unwrap();
+            writeln!(f, "pub const {}_ORIGIN: u32 = {:#06X};", name, origin).unwrap();
+            writeln!(f, "/// Size of the `{}` region, in bytes", name).unwrap();
+            writeln!(f, "pub const {}_LENGTH: u32 = {:#06X};", name, length).unwrap();
+            writeln!(f, "/// The `{}` region, for `assert_in_region!`", name).unwrap();
+            writeln!(
+                f,
+                "pub const {0}: Region = Region {{ origin: {0}_ORIGIN, length: {0}_LENGTH }};",
+                name
+            )
+            .unwrap();
+            if name == "RAM" {
+                ram_end = Some(origin + length);
+            }
+        }
+    }
+
+    if let Some(ram_end) = ram_end {
+        writeln!(
+            f,
+            "/// Initial stack pointer, `_stack_start`: the end of `RAM`"
+        )
+        .unwrap();
+        writeln!(f, "pub const STACK_START: u32 = {:#06X};", ram_end).unwrap();
+    }
+
+    // With `auto-heap` the heap size is computed by the linker instead
+    let auto_heap = env::var_os("CARGO_FEATURE_AUTO_HEAP").is_some();
+    for (size, symbol, name, what) in &[
+        (
+            map.stack_size,
+            "_stack_size",
+            "STACK_SIZE",
+            "Bytes of RAM reserved for the stack",
+        ),
+        (
+            map.heap_size.filter(|_| !auto_heap),
+            "_heap_size",
+            "HEAP_SIZE",
+            "Bytes of RAM reserved for the heap",
+        ),
+    ] {
+        if let Some(size) = size {
+            writeln!(f, "/// {}, `{}`", what, symbol).unwrap();
+            writeln!(f, "pub const {}: u32 = {:#06X};", name, size).unwrap();
+        }
+    }
+}
+
+/// Returns the part number selected by a `mcu-*` feature or by the `MSP430_MCU` variable
+fn mcu() -> Option<String> {
+    let features = PARTS
+        .iter()
+        .map(|part| part.name)
+        .filter(|name| {
+            let feature = format!("CARGO_FEATURE_MCU_{}", name.to_uppercase());
+            env::var_os(feature).is_some()
+        })
+        .collect::<Vec<_>>();
+
+    match (&*features, env::var("MSP430_MCU")) {
+        ([], Ok(mcu)) => Some(mcu),
+        ([], Err(_)) => None,
+        ([feature], Ok(mcu)) if Part::of(&mcu).map(|part| part.name) != Some(feature) => panic!(
+            "`MSP430_MCU` is set to `{}` but the `mcu-{}` feature is enabled",
+            mcu, feature
+        ),
+        ([feature], _) => Some(feature.to_string()),
+        _ => panic!(
+            "only one `mcu-*` feature can be enabled, found {:?}",
+            features
+        ),
+    }
+}
+
+/// The memory layout of a MSP430 part
+struct Part {
+    /// Part number, e.g. `msp430g2553`
+    name: &'static str,
+    /// Origin and length of the `RAM`, `ROM` and `VECTORS` regions
+    ram: (u16, u16),
+    rom: (u16, u16),
+    vectors: (u16, u16),
+}
+
+/// Parts whose `memory.x` can be generated; each has a `mcu-<name>` feature. `ROM` only covers the
+/// program memory below the vector table; on devices with more than 64 KiB of program memory the
+/// upper part isn't used.
+const PARTS: &[Part] = &[
+    Part {
+        name: "msp430g2231",
+        ram: (0x0200, 0x0080),
+        rom: (0xF800, 0x07E0),
+        vectors: (0xFFE0, 0x0020),
+    },
+    Part {
+        name: "msp430g2452",
+        ram: (0x0200, 0x0100),
+        rom: (0xE000, 0x1FE0),
+        vectors: (0xFFE0, 0x0020),
+    },
+    Part {
+        name: "msp430g2553",
+        ram: (0x0200, 0x0200),
+        rom: (0xC000, 0x3FE0),
+        vectors: (0xFFE0, 0x0020),
+    },
+    Part {
+        name: "msp430f5529",
+        ram: (0x2400, 0x2000),
+        rom: (0x4400, 0xBB80),
+        vectors: (0xFF80, 0x0080),
+    },
+    Part {
+        name: "msp430fr2433",
+        ram: (0x2000, 0x1000),
+        rom: (0xC400, 0x3B80),
+        vectors: (0xFF88, 0x0078),
+    },
+    Part {
+        name: "msp430fr5969",
+        ram: (0x1C00, 0x0800),
+        rom: (0x4400, 0xBB80),
+        vectors: (0xFF90, 0x0070),
+    },
+    Part {
+        name: "msp430fr6989",
+        ram: (0x1C00, 0x0800),
+        rom: (0x4400, 0xBB80),
+        vectors: (0xFF90, 0x0070),
+    },
+];
+
+impl Part {
+    /// Returns the memory layout of part `mcu`, if it's known
+    fn of(mcu: &str) -> Option<&'static Self> {
+        let mcu = mcu.to_lowercase();
+        let part = mcu.trim_start_matches("msp430");
+        PARTS
+            .iter()
+            .find(|p| p.name.trim_start_matches("msp430") == part)
+    }
+}
+
+/// The defaults of a MSP430 device family
+struct Family {
+    /// Family, e.g. `g2` or `fr5`
+    name: &'static str,
+    /// The family implements the MSP430X (CPUX) instruction set
+    cpux: bool,
+    /// The program memory is FRAM rather than flash
+    fram: bool,
+    /// Number of vectors, including the reset vector, of the whole vector table area
+    vectors: u16,
+    /// Start, length and segment size of the information memory; FRAM has no segments
+    info: (u16, u16, u16),
+    /// Base address of the CRC16 peripheral, if any
+    crc16: Option<u16>,
+    /// Address of the `SYSCFG0` register, if the vector table is write protected by `PFWP`
+    syscfg0: Option<u16>,
+    /// Address of the `WDTCTL` register
+    wdtctl: u16,
+    /// Address of the `SYSRSTIV` register, if any
+    sysrstiv: Option<u16>,
+    /// Addresses of the `P1OUT` and `P1DIR` registers
+    port1: (u16, u16),
+    /// Base address of Timer_A0, i.e. of the `TA0CTL` register
+    timer_a0: u16,
+    /// Base address of the USCI_A0 (G2xx, F2xx) or eUSCI_A0 (FRAM families) module, and its
+    /// baud rate settings for 9600 baud from a 1 MHz SMCLK, if the family has either
+    uart_a0: Option<(u16, u16, u16)>,
+}
+
+impl Family {
+    /// Returns the family of part `mcu`, e.g. `msp430g2553` or just `g2553`
+    fn of(mcu: &str) -> Option<Self> {
+        let mcu = mcu.to_lowercase();
+        let part = mcu.trim_start_matches("msp430");
+        // Check the longer prefixes first: `fr2` is not `f2`
+        let prefixes = [
+            ("fr2", "fr2"),
+            ("fr4", "fr4"),
+            ("fr5", "fr5"),
+            ("fr6", "fr6"),
+            ("fg4", "f4"),
+            ("fg6", "f6"),
+            ("g2", "g2"),
+            ("f1", "f1"),
+            ("f2", "f2"),
+            ("f4", "f4"),
+            ("f5", "f5"),
+            ("f6", "f6"),
+        ];
+        let name = prefixes
+            .iter()
+            .find(|(prefix, _)| part.starts_with(prefix))
+            .map(|(_, name)| *name)?;
+
+        let family = match name {
+            "f1" | "f4" => Family {
+                name,
+                cpux: false,
+                fram: false,
+                vectors: 16,
+                info: (0x1000, 0x0100, 0x0080),
+                crc16: None,
+                syscfg0: None,
+                wdtctl: 0x0120,
+                sysrstiv: None,
+                port1: (0x0021, 0x0022),
+                timer_a0: 0x0160,
+                uart_a0: None,
+            },
+            "f2" | "g2" => Family {
+                name,
+                cpux: false,
+                fram: false,
+                vectors: if name == "f2" { 32 } else { 16 },
+                info: (0x1000, 0x0100, 0x0040),
+                crc16: None,
+                syscfg0: None,
+                wdtctl: 0x0120,
+                sysrstiv: None,
+                port1: (0x0021, 0x0022),
+                timer_a0: 0x0160,
+                uart_a0: Some((0x0060, 104, 0x02)),
+            },
+            "f5" | "f6" => Family {
+                name,
+                cpux: true,
+                fram: false,
+                vectors: 64,
+                info: (0x1800, 0x0200, 0x0080),
+                crc16: Some(0x0150),
+                syscfg0: None,
+                wdtctl: 0x015C,
+                sysrstiv: Some(0x019E),
+                port1: (0x0202, 0x0204),
+                timer_a0: 0x0340,
+                uart_a0: None,
+            },
+            "fr2" | "fr4" => Family {
+                name,
+                cpux: true,
+                fram: true,
+                vectors: 60,
+                info: (0x1800, 0x0200, 0x0200),
+                crc16: Some(0x01C0),
+                syscfg0: Some(0x0160),
+                wdtctl: 0x01CC,
+                sysrstiv: Some(0x015E),
+                port1: (0x0202, 0x0204),
+                timer_a0: 0x0380,
+                uart_a0: Some((0x0500, 6, 0x2081)),
+            },
+            _ => Family {
+                name,
+                cpux: true,
+                fram: true,
+                vectors: 56,
+                info: (0x1800, 0x0200, 0x0200),
+                crc16: Some(0x0150),
+                syscfg0: None,
+                wdtctl: 0x015C,
+                sysrstiv: Some(0x019E),
+                port1: (0x0202, 0x0204),
+                timer_a0: 0x0340,
+                uart_a0: Some((0x05C0, 6, 0x2081)),
+            },
+        };
+
+        Some(family)
+    }
 }
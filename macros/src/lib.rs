@@ -1,9 +1,3 @@
-extern crate proc_macro;
-extern crate proc_macro2;
-extern crate quote;
-extern crate rand;
-extern crate syn;
-
 use proc_macro::TokenStream;
 use std::{
     collections::HashSet,
@@ -15,15 +9,18 @@ use proc_macro2::Span;
 use quote::quote;
 use rand::{Rng, SeedableRng};
 use syn::{
-    parse, parse_macro_input, spanned::Spanned, Ident, Item, ItemFn, ItemStatic, ReturnType, Stmt,
-    Type, Visibility,
+    parse, parse_macro_input, spanned::Spanned, Ident, Item, ItemFn, ItemStatic, Lit,
+    MetaNameValue, ReturnType, Stmt, Type, Visibility,
 };
 
 /// Attribute to declare the entry point of the program
 ///
 /// The specified function will be called by the reset handler *after* RAM has been initialized.
 ///
-/// The type of the specified function must be `[unsafe] fn() -> !` (never ending function)
+/// The type of the specified function must be `[unsafe] fn() -> !` (never ending function), or
+/// `[unsafe] fn() [-> T]` where `T` implements `msp430_rt::Termination`. An entry point that
+/// returns passes the exit status of its value to the hook `OnExit` and then ends the program with
+/// `msp430_rt::exit`, e.g. to report the result of a test binary.
 ///
 /// # Properties
 ///
@@ -69,6 +66,18 @@ use syn::{
 ///     }
 /// }
 /// ```
+///
+/// - An entry point that returns, e.g. in a test binary.
+///
+/// ``` ignore
+/// #[entry]
+/// fn main() -> Result<(), Error> {
+///     run_tests()?;
+///
+///     // Exits with status 0
+///     Ok(())
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn entry(args: TokenStream, input: TokenStream) -> TokenStream {
     let f = parse_macro_input!(input as ItemFn);
@@ -80,19 +89,12 @@ pub fn entry(args: TokenStream, input: TokenStream) -> TokenStream {
         && f.decl.inputs.is_empty()
         && f.decl.generics.params.is_empty()
         && f.decl.generics.where_clause.is_none()
-        && f.decl.variadic.is_none()
-        && match f.decl.output {
-            ReturnType::Default => false,
-            ReturnType::Type(_, ref ty) => match **ty {
-                Type::Never(_) => true,
-                _ => false,
-            },
-        };
+        && f.decl.variadic.is_none();
 
     if !valid_signature {
         return parse::Error::new(
             f.span(),
-            "`#[entry]` function must have signature `[unsafe] fn() -> !`",
+            "`#[entry]` function must have signature `[unsafe] fn() [-> T]`",
         )
         .to_compile_error()
         .into();
@@ -133,8 +135,37 @@ pub fn entry(args: TokenStream, input: TokenStream) -> TokenStream {
         })
         .collect::<Vec<_>>();
 
+    // An entry point that returns reports its value through `Termination`
+    let output = match f.decl.output {
+        ReturnType::Type(_, ref ty) => match **ty {
+            Type::Never(_) => None,
+            _ => Some(quote!(#ty)),
+        },
+        ReturnType::Default => Some(quote!(())),
+    };
+
+    if let Some(output) = output {
+        let inner = random_ident();
+        return quote!(
+            #[cfg_attr(target_arch = "msp430", export_name = "main")]
+            #(#attrs)*
+            pub #unsafety fn #hash() -> ! {
+                #unsafety fn #inner() -> #output {
+                    #(#vars)*
+
+                    #(#stmts)*
+                }
+
+                #[allow(unused_unsafe)]
+                let value = unsafe { #inner() };
+                ::msp430_rt::main_returned(::msp430_rt::Termination::report(value))
+            }
+        )
+        .into();
+    }
+
     quote!(
-        #[export_name = "main"]
+        #[cfg_attr(target_arch = "msp430", export_name = "main")]
         #(#attrs)*
         pub #unsafety fn #hash() -> ! {
             #(#vars)*
@@ -158,8 +189,6 @@ pub fn entry(args: TokenStream, input: TokenStream) -> TokenStream {
 /// # Syntax
 ///
 /// ``` ignore
-/// extern crate device;
-///
 /// // the attribute comes from the device crate not from msp430-rt
 /// use device::interrupt;
 ///
@@ -213,15 +242,63 @@ pub fn entry(args: TokenStream, input: TokenStream) -> TokenStream {
 ///     println!("{}", COUNT);
 /// }
 /// ```
+///
+/// - Naked interrupt handlers
+///
+/// `#[interrupt(naked)]` makes the compiler emit the handler without a prologue or an epilogue,
+/// e.g. for time critical handlers written in assembly. The handler is still bound to the vector
+/// like any other handler. The body of a naked handler must consist of inline assembly only; it
+/// must save and restore the registers it uses and return with `reti`. Naked handlers can't
+/// declare `static mut` variables. The crate that defines them needs
+/// `#![feature(naked_functions)]`.
+///
+/// ``` ignore
+/// #[interrupt(naked)]
+/// unsafe fn PORT1() {
+///     asm!(
+///         "xor.b #1, &0x0021", // toggle P1.0
+///         "clr.b &0x0023",     // clear the P1 interrupt flags
+///         "reti",
+///         options(noreturn)
+///     );
+/// }
+/// ```
+///
+/// - Host tests
+///
+/// When the crate is built for a target other than MSP430, e.g. to run its unit tests on the host,
+/// the handler becomes a plain function and the name of the interrupt a
+/// `msp430_rt::vectors::Binding` constant: the name of the vector it's bound to and a function
+/// that runs the handler. This lets tests check the binding and exercise the handler, `static mut`
+/// state included; `msp430_rt::vector_table!` lists the bindings of all the vectors. The function
+/// of a naked handler panics, as its body only runs on MSP430.
+///
+/// ``` ignore
+/// #[test]
+/// fn timer_counts() {
+///     assert_eq!(TIMER0_A0.name, "TIMER0_A0");
+///     (TIMER0_A0.handler.unwrap())();
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn interrupt(args: TokenStream, input: TokenStream) -> TokenStream {
     let f: ItemFn = syn::parse(input).expect("`#[interrupt]` must be applied to a function");
 
-    if !args.is_empty() {
-        return parse::Error::new(Span::call_site(), "This attribute accepts no arguments")
-            .to_compile_error()
-            .into();
-    }
+    let naked = if args.is_empty() {
+        false
+    } else {
+        match syn::parse::<Ident>(args) {
+            Ok(ref arg) if arg == "naked" => true,
+            _ => {
+                return parse::Error::new(
+                    Span::call_site(),
+                    "This attribute accepts no arguments other than `naked`",
+                )
+                .to_compile_error()
+                .into();
+            }
+        }
+    };
 
     let fspan = f.span();
     let ident = f.ident;
@@ -276,6 +353,42 @@ pub fn interrupt(args: TokenStream, input: TokenStream) -> TokenStream {
         Ok(x) => x,
     };
 
+    let hash = random_ident();
+    if naked {
+        if let Some(var) = statics.first() {
+            return parse::Error::new(
+                var.span(),
+                "`#[interrupt(naked)]` handlers can't declare `static mut` variables",
+            )
+            .to_compile_error()
+            .into();
+        }
+
+        // The body of a naked function can only contain inline assembly, so check the name of
+        // the interrupt outside of it
+        return quote!(
+            const _: () = {
+                #check
+            };
+
+            #[cfg(target_arch = "msp430")]
+            #[export_name = #ident_s]
+            #[naked]
+            #(#attrs)*
+            #unsafety extern "msp430-interrupt" fn #hash() {
+                #(#stmts)*
+            }
+
+            #[cfg(not(target_arch = "msp430"))]
+            #[allow(dead_code, non_upper_case_globals)]
+            const #ident: ::msp430_rt::vectors::Binding = ::msp430_rt::vectors::Binding {
+                name: #ident_s,
+                handler: Some(|| panic!("naked interrupt handlers only run on MSP430")),
+            };
+        )
+        .into();
+    }
+
     let vars = statics
         .into_iter()
         .map(|var| {
@@ -296,17 +409,118 @@ pub fn interrupt(args: TokenStream, input: TokenStream) -> TokenStream {
         })
         .collect::<Vec<_>>();
 
-    let hash = random_ident();
-    quote!(
-        #[export_name = #ident_s]
-        #(#attrs)*
-        #unsafety extern "msp430-interrupt" fn #hash() {
-            #check
+    let depth = if cfg!(feature = "isr-depth") {
+        Some(quote!(
+            #[cfg(target_arch = "msp430")]
+            let _isr_depth = ::msp430_rt::isr::enter();
+        ))
+    } else {
+        None
+    };
 
-            #(#vars)*
+    let time = if cfg!(feature = "isr-time") {
+        let section = format!(".isr_time.1.{}", ident_s);
+        Some(quote!(
+            #[cfg(target_arch = "msp430")]
+            let _isr_time = {
+                static STATS: ::msp430_rt::isr_time::Stats = ::msp430_rt::isr_time::Stats::new();
+
+                #[link_section = #section]
+                #[used]
+                static ENTRY: ::msp430_rt::isr_time::Entry =
+                    ::msp430_rt::isr_time::Entry::new(#ident_s, &STATS);
+
+                STATS.start()
+            };
+        ))
+    } else {
+        None
+    };
 
-            #(#stmts)*
+    // Host builds, e.g. of unit tests, have no `msp430-interrupt` ABI and no vector table. The
+    // handler becomes a plain function there, and the name of the interrupt a constant that binds
+    // it to the vector, so that tests can inspect and call it
+    let body = quote!(
+        #check
+
+        #time
+
+        #depth
+
+        #(#vars)*
+
+        #(#stmts)*
+    );
+    let attrs = &attrs;
+    let handler = if cfg!(feature = "sleep") {
+        // The vector points to a stub that calls the handler and then clears the low-power mode
+        // bits requested by `sleep::wake` from the SR saved on the stack, so that `reti` leaves
+        // the device awake. The handler follows the C calling convention, so the stub saves the
+        // registers it may clobber, which puts the saved SR 10 bytes above the stack pointer
+        let section = format!(".pushsection .text.{},\"ax\",@progbits", ident_s);
+        let globl = format!(".globl {}", ident_s);
+        let type_ = format!(".type {},@function", ident_s);
+        let label = format!("{}:", ident_s);
+        quote!(
+            #[cfg(target_arch = "msp430")]
+            #(#attrs)*
+            #unsafety extern "C" fn #hash() {
+                #body
+            }
+
+            #[cfg(target_arch = "msp430")]
+            ::core::arch::global_asm!(
+                #section,
+                ".p2align 1",
+                #globl,
+                #type_,
+                #label,
+                "push r15",
+                "push r14",
+                "push r13",
+                "push r12",
+                "push r11",
+                "call #{handler}",
+                "bic &{wake}, 10(sp)",
+                "clr &{wake}",
+                "pop r11",
+                "pop r12",
+                "pop r13",
+                "pop r14",
+                "pop r15",
+                "reti",
+                ".popsection",
+                handler = sym #hash,
+                wake = sym ::msp430_rt::sleep::WAKE,
+            );
+        )
+    } else {
+        quote!(
+            #[cfg(target_arch = "msp430")]
+            #[export_name = #ident_s]
+            #(#attrs)*
+            #unsafety extern "msp430-interrupt" fn #hash() {
+                #body
+            }
+        )
+    };
+    quote!(
+        #handler
+
+        #[cfg(not(target_arch = "msp430"))]
+        #(#attrs)*
+        #unsafety fn #hash() {
+            #body
         }
+
+        #[cfg(not(target_arch = "msp430"))]
+        #[allow(dead_code, non_upper_case_globals, unused_unsafe)]
+        const #ident: ::msp430_rt::vectors::Binding = ::msp430_rt::vectors::Binding {
+            name: #ident_s,
+            handler: Some(|| {
+                unsafe { #hash() };
+            }),
+        };
     )
     .into()
 }
@@ -380,6 +594,254 @@ pub fn pre_init(args: TokenStream, input: TokenStream) -> TokenStream {
     .into()
 }
 
+/// Attribute to declare a function that runs before the entry point
+///
+/// The function must have the signature `[unsafe] fn()`.
+///
+/// The function is registered as a constructor, in the `.init_array` section, and is called by the
+/// reset handler *after* RAM has been initialized, together with the constructors of C code. Any
+/// number of functions can be declared with this attribute across the dependency graph.
+///
+/// `#[ctor(priority = N)]` sets the priority of the constructor, from 101 to 65535 like
+/// `__attribute__((constructor(N)))` in C: constructors with a lower priority run first, and
+/// constructors without a priority run last. The order of constructors with the same priority
+/// isn't specified.
+///
+/// # Examples
+///
+/// ``` ignore
+/// use msp430_rt::ctor;
+///
+/// #[ctor(priority = 101)]
+/// fn init_clocks() {
+///     // ..
+/// }
+///
+/// // runs after `init_clocks`
+/// #[ctor(priority = 200)]
+/// fn init_drivers() {
+///     // ..
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn ctor(args: TokenStream, input: TokenStream) -> TokenStream {
+    let f = parse_macro_input!(input as ItemFn);
+
+    // check the function signature
+    let valid_signature = f.constness.is_none()
+        && f.vis == Visibility::Inherited
+        && f.abi.is_none()
+        && f.decl.inputs.is_empty()
+        && f.decl.generics.params.is_empty()
+        && f.decl.generics.where_clause.is_none()
+        && f.decl.variadic.is_none()
+        && match f.decl.output {
+            ReturnType::Default => true,
+            ReturnType::Type(_, ref ty) => match **ty {
+                Type::Tuple(ref tuple) => tuple.elems.is_empty(),
+                _ => false,
+            },
+        };
+
+    if !valid_signature {
+        return parse::Error::new(
+            f.span(),
+            "`#[ctor]` function must have signature `[unsafe] fn()`",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    let section = if args.is_empty() {
+        ".init_array".to_string()
+    } else {
+        let priority = match syn::parse::<MetaNameValue>(args) {
+            Ok(MetaNameValue {
+                ref ident,
+                lit: Lit::Int(ref lit),
+                ..
+            }) if ident == "priority" => lit.value(),
+            _ => {
+                return parse::Error::new(
+                    Span::call_site(),
+                    "This attribute accepts no arguments other than `priority = N`",
+                )
+                .to_compile_error()
+                .into();
+            }
+        };
+
+        // 0 to 100 are reserved for the implementation, as in C
+        if !(101..=65535).contains(&priority) {
+            return parse::Error::new(
+                Span::call_site(),
+                "the priority of a `#[ctor]` function must be between 101 and 65535",
+            )
+            .to_compile_error()
+            .into();
+        }
+
+        format!(".init_array.{:05}", priority)
+    };
+
+    let attrs = f.attrs;
+    let unsafety = f.unsafety;
+    let ident = f.ident;
+    let block = f.block;
+    let hash = random_ident();
+
+    quote!(
+        #(#attrs)*
+        #unsafety fn #ident() #block
+
+        const _: () = {
+            unsafe extern "C" fn #hash() {
+                #ident()
+            }
+
+            #[link_section = #section]
+            #[used]
+            static CTOR: unsafe extern "C" fn() = #hash;
+        };
+    )
+    .into()
+}
+
+/// Attribute to place a function or a `static` variable in the IP Encapsulation region
+///
+/// Only code located in the region can read the region once the device has been reset with the
+/// region enabled, which protects proprietary code and data from being read out, e.g. through
+/// JTAG. Functions are never inlined, so their code stays in the region. `static` variables are
+/// stored in FRAM like `.persistent` ones: they are initialized when the device is programmed,
+/// not by the reset handler.
+///
+/// This attribute requires the `ipe` feature of `msp430-rt`.
+///
+/// # Examples
+///
+/// ``` ignore
+/// use msp430_rt::ipe;
+///
+/// #[ipe]
+/// static KEY: [u8; 16] = [/* .. */];
+///
+/// #[ipe]
+/// fn mac(data: &[u8]) -> u32 {
+///     // uses `KEY`
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn ipe(args: TokenStream, input: TokenStream) -> TokenStream {
+    if !args.is_empty() {
+        return parse::Error::new(Span::call_site(), "This attribute accepts no arguments")
+            .to_compile_error()
+            .into();
+    }
+
+    match parse_macro_input!(input as Item) {
+        Item::Fn(f) => {
+            let section = format!(".ipe.text.{}", f.ident);
+
+            quote!(
+                #[link_section = #section]
+                #[inline(never)]
+                #f
+            )
+            .into()
+        }
+        Item::Static(s) => {
+            let section = format!(".ipe.data.{}", s.ident);
+
+            quote!(
+                #[link_section = #section]
+                #s
+            )
+            .into()
+        }
+        item => parse::Error::new(
+            item.span(),
+            "`#[ipe]` can only be applied to functions and `static` variables",
+        )
+        .to_compile_error()
+        .into(),
+    }
+}
+
+/// Attribute to declare a `static` variable that the reset handler doesn't initialize
+///
+/// The variable is placed in `.user_ram`, which isn't zeroed, so that the reset handler doesn't
+/// spend time on it and it keeps its value across a watchdog or software reset. Its contents are
+/// garbage after a power-up, which the type system keeps track of: the type must be a
+/// `MaybeUninit` and the initializer `MaybeUninit::uninit()`.
+///
+/// # Examples
+///
+/// ``` ignore
+/// use core::mem::MaybeUninit;
+///
+/// use msp430_rt::uninit;
+///
+/// #[uninit]
+/// static mut SAMPLES: MaybeUninit<[u16; 512]> = MaybeUninit::uninit();
+/// ```
+#[proc_macro_attribute]
+pub fn uninit(args: TokenStream, input: TokenStream) -> TokenStream {
+    if !args.is_empty() {
+        return parse::Error::new(Span::call_site(), "This attribute accepts no arguments")
+            .to_compile_error()
+            .into();
+    }
+
+    let s = parse_macro_input!(input as ItemStatic);
+
+    let is_maybe_uninit = match *s.ty {
+        Type::Path(ref ty) => ty
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.value().ident == "MaybeUninit")
+            .unwrap_or(false),
+        _ => false,
+    };
+    if !is_maybe_uninit {
+        return parse::Error::new(
+            s.ty.span(),
+            "`#[uninit]` `static` variables must have type `MaybeUninit<T>`",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    let is_uninit = match *s.expr {
+        syn::Expr::Call(ref call) => match *call.func {
+            syn::Expr::Path(ref func) => func
+                .path
+                .segments
+                .last()
+                .map(|segment| segment.value().ident == "uninit")
+                .unwrap_or(false),
+            _ => false,
+        },
+        _ => false,
+    };
+    if !is_uninit {
+        return parse::Error::new(
+            s.expr.span(),
+            "`#[uninit]` `static` variables must be initialized with `MaybeUninit::uninit()`",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    let section = format!(".user_ram.{}", s.ident);
+
+    quote!(
+        #[link_section = #section]
+        #s
+    )
+    .into()
+}
+
 // Creates a random identifier
 fn random_ident() -> Ident {
     static CALL_COUNT: AtomicUsize = AtomicUsize::new(0);
@@ -0,0 +1,96 @@
+//! Jump tables of functions shared between images
+
+/// Exports a table of functions for other images to call
+///
+/// **IMPORTANT**: This macro can appear at most *once* in the dependency graph.
+///
+/// The table is placed in the `.api_table` section, located at the beginning of the `ROM` region
+/// (right after the vector related sections that the `proxy-vectors` and `ram-vectors` features
+/// place in `ROM`, if any), so its address stays the same across builds as long as `memory.x` and
+/// the number of vectors don't change. That address is available through the `__api_table` symbol.
+///
+/// The exported functions must use the `extern "C"` ABI. The order of the functions is part of the
+/// interface: only ever append new functions to the end of the table.
+///
+/// The consuming image declares the same functions, in the same order, using `api_import!`.
+///
+/// # Examples
+///
+/// ``` ignore
+/// // In the bootloader
+/// extern "C" fn write_word(addr: u16, value: u16) -> bool {
+///     // ..
+/// }
+///
+/// extern "C" fn version() -> u16 {
+///     3
+/// }
+///
+/// msp430_rt::api_export! {
+///     fn flash_write(addr: u16, value: u16) -> bool = write_word;
+///     fn bootloader_version() -> u16 = version;
+/// }
+/// ```
+#[macro_export]
+macro_rules! api_export {
+    ($(fn $name:ident($($arg:ident: $ty:ty),*) $(-> $ret:ty)? = $imp:path;)*) => {
+        #[repr(C)]
+        struct __ApiTable {
+            $($name: extern "C" fn($($ty),*) $(-> $ret)?,)*
+        }
+
+        #[link_section = ".api_table"]
+        #[no_mangle]
+        #[used]
+        static __API_TABLE: __ApiTable = __ApiTable {
+            $($name: $imp,)*
+        };
+    };
+}
+
+/// Declares typed wrappers around the functions exported by another image with `api_export!`
+///
+/// The functions must be declared in the same order and with the same signatures as in the
+/// exporting image. The address of the exported table must be given to the linker by defining the
+/// `__api_table` symbol in `memory.x`, e.g. `__api_table = 0xC000;`.
+///
+/// This macro defines a type named `__ApiTable` in the current module, so use it at most once per
+/// module.
+///
+/// # Examples
+///
+/// ``` ignore
+/// // In the application
+/// mod bootloader {
+///     msp430_rt::api_import! {
+///         /// Writes a word to flash
+///         pub fn flash_write(addr: u16, value: u16) -> bool;
+///         pub fn bootloader_version() -> u16;
+///     }
+/// }
+///
+/// if bootloader::bootloader_version() >= 3 {
+///     bootloader::flash_write(0xE000, 0x1234);
+/// }
+/// ```
+#[macro_export]
+macro_rules! api_import {
+    ($($(#[$attr:meta])* $vis:vis fn $name:ident($($arg:ident: $ty:ty),*) $(-> $ret:ty)?;)*) => {
+        #[repr(C)]
+        struct __ApiTable {
+            $($name: extern "C" fn($($ty),*) $(-> $ret)?,)*
+        }
+
+        $(
+            $(#[$attr])*
+            $vis fn $name($($arg: $ty),*) $(-> $ret)? {
+                extern "C" {
+                    // Provided by `memory.x`; the table lives in another image
+                    static __api_table: __ApiTable;
+                }
+
+                unsafe { (__api_table.$name)($($arg),*) }
+            }
+        )*
+    };
+}
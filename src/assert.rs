@@ -0,0 +1,138 @@
+//! Assertions that don't format
+//!
+//! `core::assert!` formats its message on failure, which pulls `core::fmt` into the image: a few
+//! KiB, more than a small device can spare. The macros of this module check the same conditions
+//! but report a failure as a 16-bit code, chosen by the program, and the location of the
+//! assertion, through the function `AssertFailed`:
+//!
+//! ``` ignore
+//! use msp430_rt::{rt_assert, rt_assert_eq};
+//!
+//! const BAD_LENGTH: u16 = 0x0101;
+//!
+//! fn send(frame: &[u8]) {
+//!     rt_assert!(frame.len() <= 64, BAD_LENGTH);
+//!     // The code defaults to 0
+//!     rt_assert_eq!(frame[0], SYNC);
+//!     // ..
+//! }
+//! ```
+//!
+//! `AssertFailed` never returns. The default, with the `blackbox` feature, records the code and
+//! the line of the assertion in the black box and seals it, and with the `uart-console` feature
+//! prints the failure; it then loops forever with the interrupts disabled. Define it to, e.g.,
+//! save the failure and reset the device:
+//!
+//! ``` ignore
+//! use core::panic::Location;
+//!
+//! #[allow(non_snake_case)]
+//! #[no_mangle]
+//! fn AssertFailed(code: u16, location: &'static Location<'static>) -> ! {
+//!     save_failure(code, location.line());
+//!     msp430_rt::reset::software_reset()
+//! }
+//! ```
+//!
+//! The location points to the file name, which is stored once per file. Build with
+//! `-Z location-detail=none` to leave the names out of the image entirely.
+
+use core::panic::Location;
+
+#[doc(hidden)]
+#[cold]
+#[inline(never)]
+#[track_caller]
+pub fn failed(code: u16) -> ! {
+    extern "Rust" {
+        fn AssertFailed(code: u16, location: &'static Location<'static>) -> !;
+    }
+
+    unsafe { AssertFailed(code, Location::caller()) }
+}
+
+#[allow(non_snake_case)]
+#[no_mangle]
+fn AssertFailed_(_code: u16, _location: &'static Location<'static>) -> ! {
+    msp430::interrupt::disable();
+
+    #[cfg(feature = "blackbox")]
+    {
+        crate::blackbox::record(_code);
+        crate::blackbox::record(_location.line() as u16);
+        crate::blackbox::seal();
+    }
+
+    #[cfg(feature = "uart-console")]
+    {
+        use crate::console;
+
+        console::init();
+        console::write_str("assertion failed: code ");
+        console::write_hex(_code);
+        console::write_str(" at ");
+        console::write_str(_location.file());
+        console::write_str(":");
+        console::write_decimal(_location.line());
+        console::write_str("\r\n");
+    }
+
+    crate::park()
+}
+
+/// Calls `AssertFailed` with `$code`, 0 by default, if `$condition` is false
+///
+/// See the `assert` module.
+#[macro_export]
+macro_rules! rt_assert {
+    ($condition:expr $(,)?) => {
+        $crate::rt_assert!($condition, 0)
+    };
+    ($condition:expr, $code:expr $(,)?) => {
+        if !$condition {
+            $crate::assert::failed($code)
+        }
+    };
+}
+
+/// Calls `AssertFailed` with `$code`, 0 by default, if `$left` and `$right` aren't equal
+///
+/// See the `assert` module.
+#[macro_export]
+macro_rules! rt_assert_eq {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::rt_assert_eq!($left, $right, 0)
+    };
+    ($left:expr, $right:expr, $code:expr $(,)?) => {
+        match (&$left, &$right) {
+            (left, right) => $crate::rt_assert!(*left == *right, $code),
+        }
+    };
+}
+
+/// Calls `AssertFailed` with `$code`, 0 by default, if `$left` and `$right` are equal
+///
+/// See the `assert` module.
+#[macro_export]
+macro_rules! rt_assert_ne {
+    ($left:expr, $right:expr $(,)?) => {
+        $crate::rt_assert_ne!($left, $right, 0)
+    };
+    ($left:expr, $right:expr, $code:expr $(,)?) => {
+        match (&$left, &$right) {
+            (left, right) => $crate::rt_assert!(*left != *right, $code),
+        }
+    };
+}
+
+/// Like `rt_assert!`, but only checked with `debug_assertions`
+///
+/// See the `assert` module.
+#[macro_export]
+macro_rules! rt_debug_assert {
+    ($($arg:tt)*) => {
+        if cfg!(debug_assertions) {
+            $crate::rt_assert!($($arg)*)
+        }
+    };
+}
@@ -0,0 +1,164 @@
+//! Black-box event recorder
+//!
+//! A small log of the last `CAPACITY` events, each a timestamp and a code chosen by the program,
+//! kept in `.persistent` FRAM so that it survives resets and power failures. A device that comes
+//! back from the field then tells what happened before it failed, not just the state it ended up
+//! in.
+//!
+//! `record` can be called from anywhere, including interrupt handlers. Once the log is full the
+//! oldest events are overwritten. `seal` freezes the log: events recorded afterwards are dropped,
+//! so that what led to a failure isn't overwritten by what the program does after it, e.g. after
+//! resetting. The default interrupt handler (`DefaultHandler`) seals the log before looping; a
+//! panic handler should call `seal` too, as this crate doesn't define one:
+//!
+//! ``` ignore
+//! use msp430_rt::blackbox;
+//!
+//! #[panic_handler]
+//! fn panic(_: &core::panic::PanicInfo) -> ! {
+//!     blackbox::record(0xDEAD);
+//!     blackbox::seal();
+//!     msp430_rt::reset::software_reset()
+//! }
+//!
+//! #[entry]
+//! fn main() -> ! {
+//!     if blackbox::is_sealed() {
+//!         for event in blackbox::events() {
+//!             // report `event.timestamp` and `event.code`
+//!         }
+//!         blackbox::clear();
+//!     }
+//!
+//!     blackbox::record(BOOTED);
+//!     // ..
+//! }
+//! ```
+//!
+//! Timestamps come from the function `BlackBoxTimestamp`, which the program can define, e.g. to
+//! read a free-running timer or an RTC; the default returns 0.
+//!
+//! ``` ignore
+//! #[no_mangle]
+//! extern "C" fn BlackBoxTimestamp() -> u16 {
+//!     // ..
+//! }
+//! ```
+//!
+//! The writes lift the write protection of the `fram` module; an MPU configured with
+//! `mpu_config!` must let the recorder write to `.persistent`.
+
+use core::ptr;
+
+use crate::fram;
+
+/// Number of events kept by the log
+pub const CAPACITY: usize = 32;
+
+/// A recorded event
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[repr(C)]
+pub struct Event {
+    /// Value returned by `BlackBoxTimestamp` when the event was recorded
+    pub timestamp: u16,
+    /// Code passed to `record`
+    pub code: u16,
+}
+
+/// Value of `Log.sealed` once the log has been sealed
+const SEALED: u16 = 0x5EA1;
+
+#[repr(C)]
+struct Log {
+    sealed: u16,
+    // Index of the next event to write, and number of valid events
+    next: u16,
+    len: u16,
+    events: [Event; CAPACITY],
+}
+
+#[link_section = ".persistent"]
+static mut LOG: Log = Log {
+    sealed: 0,
+    next: 0,
+    len: 0,
+    events: [Event {
+        timestamp: 0,
+        code: 0,
+    }; CAPACITY],
+};
+
+/// Appends an event with `code` to the log, unless the log is sealed
+pub fn record(code: u16) {
+    extern "C" {
+        fn BlackBoxTimestamp() -> u16;
+    }
+
+    fram::unprotected(|| unsafe {
+        let log = ptr::addr_of_mut!(LOG);
+        if ptr::read_volatile(ptr::addr_of!((*log).sealed)) == SEALED {
+            return;
+        }
+
+        // A corrupted index can't be trusted; start over
+        let mut next = usize::from(ptr::read_volatile(ptr::addr_of!((*log).next)));
+        if next >= CAPACITY {
+            next = 0;
+        }
+        let event = Event {
+            timestamp: BlackBoxTimestamp(),
+            code,
+        };
+        // The event is complete before the index moves past it, should power fail in between
+        ptr::write_volatile(ptr::addr_of_mut!((*log).events[next]), event);
+        ptr::write_volatile(
+            ptr::addr_of_mut!((*log).next),
+            ((next + 1) % CAPACITY) as u16,
+        );
+        let len = ptr::read_volatile(ptr::addr_of!((*log).len));
+        if usize::from(len) < CAPACITY {
+            ptr::write_volatile(ptr::addr_of_mut!((*log).len), len + 1);
+        }
+    });
+}
+
+/// Freezes the log; further events are dropped until `clear` is called
+pub fn seal() {
+    fram::unprotected(|| unsafe {
+        ptr::write_volatile(ptr::addr_of_mut!(LOG.sealed), SEALED);
+    });
+}
+
+/// Returns `true` if the log has been sealed
+pub fn is_sealed() -> bool {
+    unsafe { ptr::read_volatile(ptr::addr_of!(LOG.sealed)) == SEALED }
+}
+
+/// Empties the log and unseals it
+pub fn clear() {
+    fram::unprotected(|| unsafe {
+        ptr::write_volatile(ptr::addr_of_mut!(LOG.len), 0);
+        ptr::write_volatile(ptr::addr_of_mut!(LOG.next), 0);
+        ptr::write_volatile(ptr::addr_of_mut!(LOG.sealed), 0);
+    });
+}
+
+/// Returns the events of the log, from the oldest to the most recent
+pub fn events() -> impl Iterator<Item = Event> {
+    let (next, len) = unsafe {
+        (
+            usize::from(ptr::read_volatile(ptr::addr_of!(LOG.next))) % CAPACITY,
+            usize::from(ptr::read_volatile(ptr::addr_of!(LOG.len))).min(CAPACITY),
+        )
+    };
+
+    (0..len).map(move |i| {
+        let index = (next + CAPACITY - len + i) % CAPACITY;
+        unsafe { ptr::read_volatile(ptr::addr_of!(LOG.events[index])) }
+    })
+}
+
+#[no_mangle]
+extern "C" fn BlackBoxTimestamp_() -> u16 {
+    0
+}
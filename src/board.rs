@@ -0,0 +1,148 @@
+//! LaunchPad development boards
+//!
+//! Each `board-*` feature selects the MCU of a LaunchPad, which provides its `memory.x` (see the
+//! `mcu-*` features), and enables this module, which describes the parts of the board that most
+//! programs use: the two user LEDs and the pins of the backchannel UART, which the on-board
+//! debugger exposes to the host as a serial port (e.g. `/dev/ttyACM1`). On FRAM boards the UART is
+//! also the default port of the `uart-updater` feature.
+//!
+//! ``` ignore
+//! use msp430_rt::board;
+//!
+//! // e.g. before printing panic messages
+//! board::select_uart_pins();
+//! ```
+//!
+//! On FRAM devices, pin configuration only takes effect once the `LOCKLPM5` bit of `PM5CTL0` has
+//! been cleared.
+
+use core::ptr;
+
+/// A pin of a digital I/O port
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Pin {
+    /// Port number, e.g. 1 for `P1`
+    pub port: u8,
+    /// Bit of the pin in the port registers, e.g. `1 << 0` for `P1.0`
+    pub mask: u8,
+}
+
+/// MSP-EXP430G2ET, with a MSP430G2553
+#[cfg(feature = "board-msp-exp430g2et")]
+mod pins {
+    use super::Pin;
+
+    pub const LED1: Pin = Pin {
+        port: 1,
+        mask: 1 << 0,
+    };
+    pub const LED2: Pin = Pin {
+        port: 1,
+        mask: 1 << 6,
+    };
+    pub const UART: usize = 0x0060;
+    pub const UART_TX: Pin = Pin {
+        port: 1,
+        mask: 1 << 2,
+    };
+    pub const UART_RX: Pin = Pin {
+        port: 1,
+        mask: 1 << 1,
+    };
+
+    /// `P1SEL` and `P1SEL2` both set select the USCI_A0 function
+    pub unsafe fn select_uart_pins() {
+        super::set(0x0026, UART_TX.mask | UART_RX.mask);
+        super::set(0x0041, UART_TX.mask | UART_RX.mask);
+    }
+}
+
+/// MSP-EXP430FR5969, with a MSP430FR5969
+#[cfg(feature = "board-msp-exp430fr5969")]
+mod pins {
+    use super::Pin;
+
+    pub const LED1: Pin = Pin {
+        port: 4,
+        mask: 1 << 6,
+    };
+    pub const LED2: Pin = Pin {
+        port: 1,
+        mask: 1 << 0,
+    };
+    pub const UART: usize = 0x05C0;
+    pub const UART_TX: Pin = Pin {
+        port: 2,
+        mask: 1 << 0,
+    };
+    pub const UART_RX: Pin = Pin {
+        port: 2,
+        mask: 1 << 1,
+    };
+
+    /// `P2SEL1` set and `P2SEL0` cleared select the eUSCI_A0 function
+    pub unsafe fn select_uart_pins() {
+        super::clear(0x020B, UART_TX.mask | UART_RX.mask);
+        super::set(0x020D, UART_TX.mask | UART_RX.mask);
+    }
+}
+
+/// MSP-EXP430FR2433, with a MSP430FR2433
+#[cfg(feature = "board-msp-exp430fr2433")]
+mod pins {
+    use super::Pin;
+
+    pub const LED1: Pin = Pin {
+        port: 1,
+        mask: 1 << 0,
+    };
+    pub const LED2: Pin = Pin {
+        port: 1,
+        mask: 1 << 1,
+    };
+    pub const UART: usize = 0x0500;
+    pub const UART_TX: Pin = Pin {
+        port: 1,
+        mask: 1 << 4,
+    };
+    pub const UART_RX: Pin = Pin {
+        port: 1,
+        mask: 1 << 5,
+    };
+
+    /// `P1SEL0` set selects the eUSCI_A0 function
+    pub unsafe fn select_uart_pins() {
+        super::set(0x020A, UART_TX.mask | UART_RX.mask);
+    }
+}
+
+/// The first user LED
+pub const LED1: Pin = pins::LED1;
+
+/// The second user LED
+pub const LED2: Pin = pins::LED2;
+
+/// Base address of the registers of the serial module wired to the backchannel UART
+pub const UART: usize = pins::UART;
+
+/// Transmit pin of the backchannel UART
+pub const UART_TX: Pin = pins::UART_TX;
+
+/// Receive pin of the backchannel UART
+pub const UART_RX: Pin = pins::UART_RX;
+
+/// Selects the UART function of the backchannel UART pins
+pub fn select_uart_pins() {
+    msp430::interrupt::free(|_| unsafe { pins::select_uart_pins() });
+}
+
+unsafe fn set(register: usize, mask: u8) {
+    let register = register as *mut u8;
+    ptr::write_volatile(register, ptr::read_volatile(register) | mask);
+}
+
+#[allow(dead_code)]
+unsafe fn clear(register: usize, mask: u8) {
+    let register = register as *mut u8;
+    ptr::write_volatile(register, ptr::read_volatile(register) & !mask);
+}
@@ -0,0 +1,421 @@
+//! A/B boot selection
+//!
+//! This module implements the pieces needed to boot one of two firmware slots (see the A/B slots
+//! section of the crate documentation):
+//!
+//! - Each slot image carries a `FirmwareHeader`, emitted with the `firmware_header!` macro, which
+//! describes the image and how to verify it.
+//!
+//! - A `BootState` record, stored in non-volatile memory at the address of the `__boot_state`
+//! symbol, tracks which slot is *active* (known good) and which one is *pending* (freshly updated
+//! and not yet confirmed).
+//!
+//! - The bootloader calls `select` (requires the `boot-selector` feature) to branch into the
+//! preferred valid slot.
+//!
+//! - The application calls `confirm` once it has booted successfully, and `request_update` after
+//! writing a new image into the other slot.
+//!
+//! A pending slot gets a single trial boot. If the application doesn't call `confirm` during that
+//! boot, the next boot reverts to the active slot.
+//!
+//! `__boot_state` must be defined in the `memory.x` of both the bootloader and the application and
+//! point to 8 bytes of memory that keep their contents across resets and power cycles, and that
+//! are writable without erase cycles, like FRAM or info memory on FRAM devices. E.g.
+//!
+//! ``` text
+//! /* Information memory A of a FR5xx device */
+//! __boot_state = 0x1980;
+//! ```
+
+use core::ptr;
+
+/// Value of `FirmwareHeader.magic` in a valid header
+pub const FIRMWARE_MAGIC: u16 = 0xF1A5;
+
+/// Value of `BootState.magic` in an initialized boot state
+pub const BOOT_STATE_MAGIC: u16 = 0xB007;
+
+/// Value of `BootState.pending` when no slot is pending confirmation
+pub const NO_SLOT: u16 = 0xFFFF;
+
+/// A firmware slot
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Slot {
+    /// Slot `A`, located in the `ROM_A` region
+    A = 0,
+    /// Slot `B`, located in the `ROM_B` region
+    B = 1,
+}
+
+impl Slot {
+    /// Returns the other slot
+    pub fn other(self) -> Slot {
+        match self {
+            Slot::A => Slot::B,
+            Slot::B => Slot::A,
+        }
+    }
+
+    #[cfg(feature = "boot-selector")]
+    fn from_index(index: u16) -> Option<Slot> {
+        match index {
+            0 => Some(Slot::A),
+            1 => Some(Slot::B),
+            _ => None,
+        }
+    }
+}
+
+/// Header describing the image of a slot
+///
+/// The header is placed in the `.firmware_header` section, which occupies the last 16 bytes of the
+/// slot's `ROM` region. Use the `firmware_header!` macro to emit it.
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub struct FirmwareHeader {
+    /// Always `FIRMWARE_MAGIC`
+    pub magic: u16,
+    /// Version of the image. Higher is newer
+    pub version: u16,
+    /// Start of the range covered by the image CRC
+    pub start: &'static u8,
+    /// End of the range covered by the image CRC
+    pub end: &'static u8,
+    /// Expected CRC of the image (see the `image-crc` feature)
+    pub crc: &'static u16,
+    /// Reset handler of the image
+    pub entry: unsafe extern "C" fn() -> !,
+    /// Start of the image signature (see the `image-signature` feature)
+    pub signature_start: &'static u8,
+    /// End of the image signature. Equal to `signature_start` if the image isn't signed
+    pub signature_end: &'static u8,
+}
+
+/// The reset handler of this image, as `FirmwareHeader::entry`
+///
+/// Both ABIs call a function without arguments that doesn't return the same way.
+#[cfg(all(feature = "image-crc", target_arch = "msp430"))]
+#[doc(hidden)]
+pub const ENTRY: unsafe extern "C" fn() -> ! = unsafe {
+    core::mem::transmute::<unsafe extern "msp430-interrupt" fn() -> !, unsafe extern "C" fn() -> !>(
+        crate::ResetTrampoline,
+    )
+};
+
+#[cfg(all(feature = "image-crc", not(target_arch = "msp430")))]
+#[doc(hidden)]
+pub const ENTRY: unsafe extern "C" fn() -> ! = {
+    unsafe extern "C" fn entry() -> ! {
+        panic!("the reset handler only runs on MSP430")
+    }
+    entry
+};
+
+/// Emits the `FirmwareHeader` of the slot being built
+///
+/// **IMPORTANT**: This macro can appear at most *once* in the dependency graph.
+///
+/// The header describes the range verified by the `image-crc` feature, so this macro is only
+/// available when that feature is enabled.
+///
+/// # Examples
+///
+/// ``` ignore
+/// use msp430_rt::firmware_header;
+///
+/// firmware_header!(3);
+/// ```
+#[cfg(feature = "image-crc")]
+#[macro_export]
+macro_rules! firmware_header {
+    ($version:expr) => {
+        #[link_section = ".firmware_header"]
+        #[no_mangle]
+        #[used]
+        static __FIRMWARE_HEADER: $crate::boot::FirmwareHeader = {
+            extern "C" {
+                static __image_crc_start: u8;
+                static __image_crc_end: u8;
+                static __IMAGE_CRC: u16;
+                static __image_signature_start: u8;
+                static __image_signature_end: u8;
+            }
+
+            $crate::boot::FirmwareHeader {
+                magic: $crate::boot::FIRMWARE_MAGIC,
+                version: $version,
+                start: unsafe { &__image_crc_start },
+                end: unsafe { &__image_crc_end },
+                crc: unsafe { &__IMAGE_CRC },
+                entry: $crate::boot::ENTRY,
+                signature_start: unsafe { &__image_signature_start },
+                signature_end: unsafe { &__image_signature_end },
+            }
+        };
+    };
+}
+
+/// Emits the `FirmwareHeader` of the slot being built
+///
+/// This macro requires the `image-crc` feature.
+#[cfg(not(feature = "image-crc"))]
+#[macro_export]
+macro_rules! firmware_header {
+    ($($tt:tt)*) => {
+        compile_error!("`firmware_header!` requires the `image-crc` feature of msp430-rt");
+    };
+}
+
+/// Boot state shared by the bootloader and the application
+#[derive(Clone, Copy, Debug)]
+#[repr(C)]
+pub struct BootState {
+    /// `BOOT_STATE_MAGIC` once the boot state has been initialized
+    pub magic: u16,
+    /// Index of the known good slot
+    pub active: u16,
+    /// Index of the slot awaiting confirmation, or `NO_SLOT`
+    pub pending: u16,
+    /// Non-zero once the pending slot has been given its trial boot
+    pub trial: u16,
+}
+
+extern "C" {
+    static mut __boot_state: BootState;
+}
+
+/// Reads the boot state
+pub fn state() -> BootState {
+    unsafe { ptr::read_volatile(&__boot_state) }
+}
+
+fn set_state(state: BootState) {
+    msp430::interrupt::free(|_| unsafe { ptr::write_volatile(&mut __boot_state, state) })
+}
+
+/// Returns the slot the running image was built for
+///
+/// Linking fails if this function is used in an image that wasn't built with `MSP430_RT_SLOT` set.
+pub fn current_slot() -> Slot {
+    extern "C" {
+        // Absolute symbol; its address is the index of the slot
+        static _slot: u8;
+    }
+
+    // The compiler assumes that the address of a symbol is never 0; hide it behind a volatile read
+    if unsafe { ptr::read_volatile(&(&_slot as *const u8 as usize)) } == 0 {
+        Slot::A
+    } else {
+        Slot::B
+    }
+}
+
+/// Marks the running image as known good
+///
+/// Call this once the application is confident the update works. Otherwise the bootloader reverts
+/// to the previously active slot on the next boot.
+pub fn confirm() {
+    let slot = current_slot() as u16;
+    let mut state = state();
+
+    if state.magic != BOOT_STATE_MAGIC {
+        state = BootState {
+            magic: BOOT_STATE_MAGIC,
+            active: slot,
+            pending: NO_SLOT,
+            trial: 0,
+        };
+    } else if state.pending == slot {
+        state.active = slot;
+        state.pending = NO_SLOT;
+        state.trial = 0;
+    } else {
+        return;
+    }
+
+    set_state(state);
+}
+
+/// Requests a trial boot of `slot` on the next boot
+///
+/// Call this after a new image has been completely written into `slot`.
+pub fn request_update(slot: Slot) {
+    let mut state = state();
+
+    if state.magic != BOOT_STATE_MAGIC {
+        state.magic = BOOT_STATE_MAGIC;
+        state.active = slot.other() as u16;
+    }
+    state.pending = slot as u16;
+    state.trial = 0;
+
+    set_state(state);
+}
+
+/// Returns the address range, `start..end`, occupied by `slot`
+#[cfg(feature = "boot-selector")]
+pub(crate) fn bounds(slot: Slot) -> (usize, usize) {
+    extern "C" {
+        static __slot_a_start: u8;
+        static __slot_a_end: u8;
+        static __slot_b_start: u8;
+        static __slot_b_end: u8;
+    }
+
+    let (start, end) = unsafe {
+        match slot {
+            Slot::A => (&__slot_a_start as *const u8, &__slot_a_end as *const u8),
+            Slot::B => (&__slot_b_start as *const u8, &__slot_b_end as *const u8),
+        }
+    };
+
+    (start as usize, end as usize)
+}
+
+/// Returns the slot a firmware update should be written to
+///
+/// That is the slot that is not active. Without a valid boot state, this is the slot that doesn't
+/// contain a valid image, or `Slot::B` if both are valid.
+#[cfg(feature = "boot-selector")]
+pub fn inactive_slot() -> Slot {
+    let state = state();
+
+    match Slot::from_index(state.active) {
+        Some(active) if state.magic == BOOT_STATE_MAGIC => active.other(),
+        _ => {
+            if validate(Slot::A).is_some() {
+                Slot::B
+            } else {
+                Slot::A
+            }
+        }
+    }
+}
+
+/// Returns the header of `slot` if the slot contains a valid image
+///
+/// The image is valid if its header is intact, its CRC range lies within the slot and its CRC
+/// matches. With the `image-signature` feature its signature must also lie within the slot and be
+/// accepted by `VerifySignature`.
+#[cfg(feature = "boot-selector")]
+pub fn validate(slot: Slot) -> Option<&'static FirmwareHeader> {
+    let (start, end) = bounds(slot);
+
+    // The header occupies the last 16 bytes of the slot. Don't create a reference to it until its
+    // contents have been checked; an erased slot contains garbage
+    let header = (end - 16) as *const FirmwareHeader;
+    let raw = unsafe { ptr::read_volatile(header as *const [u16; 8]) };
+    if raw[0] != FIRMWARE_MAGIC {
+        return None;
+    }
+
+    let (image_start, image_end, crc, entry) = (
+        usize::from(raw[2]),
+        usize::from(raw[3]),
+        usize::from(raw[4]),
+        usize::from(raw[5]),
+    );
+    let within = |addr: usize| addr >= start && addr < end;
+    if !(within(image_start) && image_start < image_end && image_end <= end)
+        || !within(crc)
+        || !within(entry)
+    {
+        return None;
+    }
+
+    let image =
+        unsafe { core::slice::from_raw_parts(image_start as *const u8, image_end - image_start) };
+    if crate::crc::crc16(image) != unsafe { ptr::read_volatile(crc as *const u16) } {
+        return None;
+    }
+
+    #[cfg(feature = "image-signature")]
+    {
+        let (signature_start, signature_end) = (usize::from(raw[6]), usize::from(raw[7]));
+        if !(within(signature_start) && signature_start <= signature_end && signature_end <= end)
+            || (signature_start < image_end && signature_end > image_start)
+        {
+            return None;
+        }
+
+        let signature = unsafe {
+            core::slice::from_raw_parts(
+                signature_start as *const u8,
+                signature_end - signature_start,
+            )
+        };
+        if !crate::image::check(image, signature) {
+            return None;
+        }
+    }
+
+    Some(unsafe { &*header })
+}
+
+/// Boots the preferred valid slot
+///
+/// A pending slot is booted once on trial; an unconfirmed trial reverts to the active slot. If the
+/// active slot is invalid the other slot is booted and becomes active. Without a valid boot state
+/// the slot with the higher version is booted. If neither slot is valid `NoValidImage` is called;
+/// it defaults to an infinite loop.
+///
+/// This function is meant to be called by the bootloader, usually from its entry point, with
+/// interrupts disabled.
+#[cfg(feature = "boot-selector")]
+pub fn select() -> ! {
+    extern "C" {
+        fn NoValidImage() -> !;
+    }
+
+    let mut state = state();
+
+    if state.magic != BOOT_STATE_MAGIC {
+        let active = match (validate(Slot::A), validate(Slot::B)) {
+            (Some(a), Some(b)) if b.version > a.version => Slot::B,
+            (None, Some(_)) => Slot::B,
+            _ => Slot::A,
+        };
+        state = BootState {
+            magic: BOOT_STATE_MAGIC,
+            active: active as u16,
+            pending: NO_SLOT,
+            trial: 0,
+        };
+        set_state(state);
+    }
+
+    if let Some(pending) = Slot::from_index(state.pending) {
+        if state.trial == 0 {
+            if let Some(header) = validate(pending) {
+                state.trial = 1;
+                set_state(state);
+                unsafe { (header.entry)() }
+            }
+        }
+
+        // Either the trial boot wasn't confirmed or the pending image is invalid: revert
+        state.pending = NO_SLOT;
+        state.trial = 0;
+        set_state(state);
+    }
+
+    let active = Slot::from_index(state.active).unwrap_or(Slot::A);
+    if let Some(header) = validate(active) {
+        unsafe { (header.entry)() }
+    }
+
+    if let Some(header) = validate(active.other()) {
+        state.active = active.other() as u16;
+        set_state(state);
+        unsafe { (header.entry)() }
+    }
+
+    unsafe { NoValidImage() }
+}
+
+#[cfg(feature = "boot-selector")]
+#[no_mangle]
+extern "C" fn NoValidImage_() -> ! {
+    crate::park()
+}
@@ -0,0 +1,22 @@
+//! Programmatic debugger halts
+
+#[cfg(target_arch = "msp430")]
+use core::arch::asm;
+
+/// Halts the program under a debugger
+///
+/// The CPU has no breakpoint instruction, so this emits `0x4343`, the instruction that GDB and
+/// mspdebug insert for their software breakpoints: a debugger that watches for it, e.g. GDB
+/// connected to mspdebug's GDB server, or a simulator, stops here as if it had hit a breakpoint.
+/// The instruction, `mov.b #0, r3`, has no effect otherwise, so the program just carries on when
+/// no debugger is attached.
+///
+/// Only builds of this crate with `debug_assertions` emit the instruction; in release builds, and
+/// on other targets, this does nothing.
+#[inline(always)]
+pub fn breakpoint() {
+    #[cfg(target_arch = "msp430")]
+    if cfg!(debug_assertions) {
+        unsafe { asm!(".word 0x4343", options(nomem, nostack, preserves_flags)) };
+    }
+}
@@ -0,0 +1,61 @@
+//! Interrupts shared by several handlers
+
+/// Defines the handler of interrupt `$vector` as a dispatcher that calls every handler registered
+/// for it with `shared_handler!`
+///
+/// **IMPORTANT**: This macro can appear at most *once* per interrupt in the dependency graph, and
+/// the interrupt must not also be handled with `#[interrupt]`.
+///
+/// The registered handlers are called in link order, i.e. in the order in which the object files
+/// that contain them are passed to the linker. Each of them is a plain `fn()`.
+///
+/// Like `#[interrupt]` this macro defines an `extern "msp430-interrupt"` function, so the crate
+/// that uses it needs `#![feature(abi_msp430_interrupt)]`. On other targets than MSP430 it
+/// doesn't define anything.
+///
+/// # Examples
+///
+/// ``` ignore
+/// // In the application
+/// msp430_rt::shared_interrupt!(PORT1);
+///
+/// // In a button driver
+/// fn on_port1() {
+///     // check and clear the flag of the button pin
+/// }
+///
+/// msp430_rt::shared_handler!(PORT1, on_port1);
+///
+/// // In a radio driver
+/// msp430_rt::shared_handler!(PORT1, radio::on_irq);
+/// ```
+#[macro_export]
+macro_rules! shared_interrupt {
+    ($vector:ident) => {
+        #[cfg(target_arch = "msp430")]
+        #[allow(non_snake_case)]
+        #[no_mangle]
+        extern "msp430-interrupt" fn $vector() {
+            let handlers =
+                $crate::linker_slice!(concat!(".interrupt_chain.", stringify!($vector)), fn());
+            for handler in handlers {
+                handler();
+            }
+        }
+    };
+}
+
+/// Registers `$handler` as one of the handlers of interrupt `$vector`
+///
+/// The interrupt must be dispatched with `shared_interrupt!`, usually by the application. See that
+/// macro for an example.
+#[macro_export]
+macro_rules! shared_handler {
+    ($vector:ident, $handler:expr) => {
+        const _: () = {
+            #[link_section = concat!(".interrupt_chain.", stringify!($vector), ".1")]
+            #[used]
+            static HANDLER: fn() = $handler;
+        };
+    };
+}
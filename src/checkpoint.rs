@@ -0,0 +1,228 @@
+//! Checkpoints for intermittent computing
+//!
+//! An energy harvesting device loses the contents of its RAM and registers whenever its supply
+//! runs out. With the `checkpoint` feature the program can save its state to FRAM when it's about
+//! to lose power, e.g. from the interrupt handler of a comparator that watches the supply, and the
+//! reset handler resumes execution from that state once power is back, instead of starting over.
+//!
+//! `save` copies the registers, `.bss`, the RAM vector table of `ram-vectors`, `.data`, the heap
+//! and the used part of the stack to FRAM. It returns `Resume::Saved` right away, and
+//! `Resume::Restored` a second time, after the next reset, when the reset handler has restored the
+//! snapshot:
+//!
+//! ``` ignore
+//! use msp430_rt::checkpoint::{self, Resume};
+//!
+//! #[interrupt]
+//! fn COMP_E() {
+//!     match checkpoint::save() {
+//!         // Wait for power to run out
+//!         Resume::Saved => loop {},
+//!         Resume::Restored => {
+//!             // Peripherals, including the clocks, are in their reset state
+//!             init_peripherals();
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! Normal boots are unaffected: the reset handler only restores a snapshot if `save` completed
+//! since the last restore, so a power failure during `save` loses that checkpoint but nothing
+//! else. If power comes back before it runs out, call `discard`, or the next reset, e.g. by the
+//! watchdog, goes back to the checkpoint. The snapshot takes as much FRAM as there is RAM.
+//!
+//! The peripherals, as well as `.user_ram` and the RAM above `_eheap` that isn't part of the
+//! stack, aren't saved.
+
+#[cfg(target_arch = "msp430")]
+use core::arch::asm;
+use core::ptr;
+
+use crate::fram;
+
+/// How `save` returned
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Resume {
+    /// The state of the program has been saved
+    Saved,
+    /// The state of the program has been restored after a reset
+    Restored,
+}
+
+// Password, hold and counter clear bits of `WDTCTL`
+const WDTPW: u16 = 0x5A << 8;
+const WDTHOLD: u16 = 1 << 7;
+const WDTCNTCL: u16 = 1 << 3;
+
+/// Value of `Context.state` when the snapshot is complete
+const VALID: u16 = 0xC4EC;
+
+/// Registers saved by `save`; the other ones are clobbered by calls anyway
+#[repr(C)]
+struct Context {
+    state: u16,
+    sp: u16,
+    pc: u16,
+    // R4 to R10
+    registers: [u16; 7],
+}
+
+#[link_section = ".persistent"]
+static mut CONTEXT: Context = Context {
+    state: 0,
+    sp: 0,
+    pc: 0,
+    registers: [0; 7],
+};
+
+/// Saves the state of the program to FRAM
+///
+/// The interrupts are disabled while the state is saved.
+pub fn save() -> Resume {
+    #[cfg(target_arch = "msp430")]
+    let restored = fram::unprotected(|| unsafe {
+        let restored: u16;
+        // `.bss`, then the RAM vector table, `.data` and the heap, which are contiguous, skipping
+        // `.user_ram` in between; the stack goes from SP to `_stack_start`. The image is only
+        // marked as valid once it's complete
+        asm!(
+            "mov r4, 6(r12)",
+            "mov r5, 8(r12)",
+            "mov r6, 10(r12)",
+            "mov r7, 12(r12)",
+            "mov r8, 14(r12)",
+            "mov r9, 16(r12)",
+            "mov r10, 18(r12)",
+            "mov #2f, 4(r12)",
+            "mov sp, 2(r12)",
+            "mov #__checkpoint_image, r13",
+            "mov #_sbss, r14",
+            "3:",
+            "cmp #_ebss, r14",
+            "jhs 4f",
+            "mov 0(r14), 0(r13)",
+            "add #2, r14",
+            "add #2, r13",
+            "jmp 3b",
+            "4:",
+            "mov #__ram_vectors_start, r14",
+            "5:",
+            "cmp #_eheap, r14",
+            "jhs 6f",
+            "mov 0(r14), 0(r13)",
+            "add #2, r14",
+            "add #2, r13",
+            "jmp 5b",
+            "6:",
+            "mov sp, r14",
+            "8:",
+            "cmp #_stack_start, r14",
+            "jhs 9f",
+            "mov 0(r14), 0(r13)",
+            "add #2, r14",
+            "add #2, r13",
+            "jmp 8b",
+            "9:",
+            "mov #{valid}, 0(r12)",
+            "mov #0, r15",
+            "jmp 7f",
+            // `restore` jumps here with the registers and the stack of the call
+            "2:",
+            "mov #1, r15",
+            "7:",
+            // `restore` resumes with the registers it uses, other than R4 to R10, overwritten
+            out("r11") _,
+            inout("r12") ptr::addr_of_mut!(CONTEXT) => _,
+            valid = const VALID,
+            out("r13") _,
+            out("r14") _,
+            out("r15") restored,
+        );
+        restored
+    });
+    // Nothing to save on other targets
+    #[cfg(not(target_arch = "msp430"))]
+    let restored = 0;
+
+    if restored == 0 {
+        Resume::Saved
+    } else {
+        Resume::Restored
+    }
+}
+
+/// Discards the saved state, so that the next reset starts the program over
+pub fn discard() {
+    fram::unprotected(|| unsafe { ptr::write_volatile(ptr::addr_of_mut!(CONTEXT.state), 0) });
+}
+
+/// Returns `true` if a complete snapshot is waiting to be restored
+pub(crate) fn pending() -> bool {
+    unsafe { ptr::read_volatile(ptr::addr_of!(CONTEXT.state)) == VALID }
+}
+
+/// Restores the snapshot and resumes execution in `save`
+///
+/// This overwrites the whole stack, that of the caller included, so it must run from the reset
+/// handler with the FRAM writable. The watchdog is held during the copy, which can take longer
+/// than its default interval, and restarted right before resuming.
+#[cfg(target_arch = "msp430")]
+pub(crate) unsafe fn restore() -> ! {
+    extern "C" {
+        // Absolute symbol; its address is the address of the `WDTCTL` register
+        static __wdtctl: u8;
+    }
+
+    let wdtctl = ptr::addr_of!(__wdtctl) as usize as *mut u16;
+    // The upper byte reads back as a different value than the password
+    let watchdog = ptr::read_volatile(wdtctl) & 0xFF;
+    ptr::write_volatile(wdtctl, WDTPW | watchdog | WDTHOLD);
+
+    // Same layout as in `save`. The snapshot is only discarded once it's been restored, right
+    // before resuming. `save` declares every register used here but R4 to R10 as an output
+    asm!(
+        "mov #__checkpoint_image, r13",
+        "mov #_sbss, r14",
+        "2:",
+        "cmp #_ebss, r14",
+        "jhs 3f",
+        "mov 0(r13), 0(r14)",
+        "add #2, r13",
+        "add #2, r14",
+        "jmp 2b",
+        "3:",
+        "mov #__ram_vectors_start, r14",
+        "4:",
+        "cmp #_eheap, r14",
+        "jhs 5f",
+        "mov 0(r13), 0(r14)",
+        "add #2, r13",
+        "add #2, r14",
+        "jmp 4b",
+        "5:",
+        "mov 2(r12), r14",
+        "6:",
+        "cmp #_stack_start, r14",
+        "jhs 7f",
+        "mov 0(r13), 0(r14)",
+        "add #2, r13",
+        "add #2, r14",
+        "jmp 6b",
+        "7:",
+        "mov 2(r12), sp",
+        "mov 6(r12), r4",
+        "mov 8(r12), r5",
+        "mov 10(r12), r6",
+        "mov 12(r12), r7",
+        "mov 14(r12), r8",
+        "mov 16(r12), r9",
+        "mov 18(r12), r10",
+        "mov #0, 0(r12)",
+        "mov r15, 0(r11)",
+        "mov 4(r12), pc",
+        in("r11") wdtctl,
+        in("r12") ptr::addr_of!(CONTEXT),
+        in("r15") WDTPW | watchdog | WDTCNTCL,
+        options(noreturn),
+    );
+}
@@ -0,0 +1,70 @@
+//! MCLK frequency and busy-wait delays
+//!
+//! The frequency of MCLK is declared once, either with `runtime_config!` or in `memory.x`, and the
+//! delays below derive their loop counts from it:
+//!
+//! ``` ignore
+//! msp430_rt::runtime_config! {
+//!     mclk_khz: 8000,
+//! }
+//! ```
+//!
+//! ``` text
+//! /* memory.x */
+//! __mclk_khz = 8000;
+//! ```
+//!
+//! The default, 1000 kHz, is the frequency of MCLK after a reset. The declaration doesn't set up
+//! the clock system; that's still up to the program.
+
+#[cfg(target_arch = "msp430")]
+use core::arch::asm;
+
+/// Cycles taken by an iteration of the delay loop: `dec` and `jnz`
+const CYCLES_PER_LOOP: u32 = 3;
+
+/// Returns the frequency of MCLK in kHz, as declared with `runtime_config!` or `__mclk_khz`
+pub fn mclk_khz() -> u32 {
+    extern "C" {
+        // Absolute symbol; its address is the frequency in kHz
+        static __mclk_khz: u8;
+    }
+
+    core::ptr::addr_of!(__mclk_khz) as usize as u32
+}
+
+/// Returns the frequency of MCLK in Hz
+pub fn mclk_hz() -> u32 {
+    mclk_khz() * 1000
+}
+
+/// Busy-waits for at least `cycles` cycles of MCLK
+///
+/// The wait can be longer if interrupts fire, or if the code runs from FRAM with wait states.
+pub fn delay_cycles(cycles: u32) {
+    let mut loops = cycles / CYCLES_PER_LOOP;
+    while loops != 0 {
+        let chunk = loops.min(u32::from(u16::MAX)) as u16;
+        #[cfg(target_arch = "msp430")]
+        unsafe {
+            asm!(
+                "2:",
+                "dec r12",
+                "jnz 2b",
+                inout("r12") chunk => _,
+                options(nomem, nostack),
+            );
+        }
+        loops -= u32::from(chunk);
+    }
+}
+
+/// Busy-waits for at least `us` microseconds, according to the declared MCLK frequency
+pub fn delay_us(us: u32) {
+    delay_cycles((u64::from(us) * u64::from(mclk_khz()) / 1000) as u32);
+}
+
+/// Busy-waits for at least `ms` milliseconds, according to the declared MCLK frequency
+pub fn delay_ms(ms: u32) {
+    delay_cycles(ms.saturating_mul(mclk_khz()));
+}
@@ -0,0 +1,57 @@
+//! Link-time configuration from Rust source
+
+/// Sets link-time settings of the runtime from Rust source, instead of `memory.x`
+///
+/// The settings are:
+///
+/// - `stack_size`: bytes of RAM reserved for the stack. Linking fails if `.bss`, `.data`, the heap
+/// and the stack don't fit in RAM together. Defaults to 0, i.e. no reservation.
+/// - `heap_size`: bytes of RAM reserved for the heap, between `_sheap` and `_eheap`. Defaults to 0.
+/// - `image_size_max`: maximum size of the image (see "Image size budget" in the crate
+/// documentation). Defaults to the size of `ROM`.
+/// - `mclk_khz`: frequency of MCLK in kHz, used by the delays of the `clock` module. Defaults to
+/// 1000, the frequency after a reset.
+///
+/// Each setting can also be given as a symbol in `memory.x` (`_stack_size`, `_heap_size`,
+/// `__image_size_max` and `__mclk_khz`), which takes precedence. Use this macro at most once per
+/// setting in the dependency graph; the values are added up otherwise.
+///
+/// The linker learns the values from the sizes of placeholder arrays placed in sections that are
+/// not loaded into the device, so they take no space in the image.
+///
+/// # Examples
+///
+/// ``` ignore
+/// msp430_rt::runtime_config! {
+///     stack_size: 256,
+///     heap_size: 128,
+/// }
+/// ```
+#[macro_export]
+macro_rules! runtime_config {
+    ($($setting:ident: $value:expr),* $(,)?) => {
+        $($crate::runtime_config!(@setting $setting, $value);)*
+    };
+    (@setting stack_size, $value:expr) => {
+        $crate::runtime_config!(@section ".runtime_config.stack_size", $value);
+    };
+    (@setting heap_size, $value:expr) => {
+        $crate::runtime_config!(@section ".runtime_config.heap_size", $value);
+    };
+    (@setting image_size_max, $value:expr) => {
+        $crate::runtime_config!(@section ".runtime_config.image_size_max", $value);
+    };
+    (@setting mclk_khz, $value:expr) => {
+        $crate::runtime_config!(@section ".runtime_config.mclk_khz", $value);
+    };
+    (@setting $setting:ident, $value:expr) => {
+        compile_error!(concat!("unknown `runtime_config!` setting `", stringify!($setting), "`"));
+    };
+    (@section $section:literal, $value:expr) => {
+        const _: () = {
+            #[link_section = $section]
+            #[used]
+            static SIZE: [u8; $value] = [0; $value];
+        };
+    };
+}
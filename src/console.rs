@@ -0,0 +1,203 @@
+//! Polled UART console
+//!
+//! With the `uart-console` feature this module drives the UART of the device, so that small
+//! programs get readable output, e.g. of crashes, without a driver of their own. It only
+//! transmits, by polling, and uses the USCI_A0 module on G2xx and F2xx devices or the eUSCI_A0
+//! module on FRAM devices:
+//!
+//! ``` ignore
+//! use core::fmt::Write;
+//! use msp430_rt::console::{self, Console};
+//!
+//! #[panic_handler]
+//! fn panic(info: &core::panic::PanicInfo) -> ! {
+//!     console::init();
+//!     let _ = write!(Console, "{}\r\n", info);
+//!     loop {}
+//! }
+//! ```
+//!
+//! `Console` implements `core::fmt::Write`; `write_str` and `write_bytes` print without pulling in
+//! `core::fmt`. The default `AssertFailed` prints failed assertions of the `rt_assert!` family
+//! too, without formatting, e.g. `assertion failed: code 0x0101 at src/main.rs:12`.
+//!
+//! `init` configures the module to run from SMCLK at the baud rate given by the `__console_brw`
+//! and `__console_mctl` symbols, the values of the `UCAxBRW` (or `UCAxBR0` and `UCAxBR1`) and
+//! `UCAxMCTLW` (or `UCAxMCTL`) registers. Their defaults give 9600 baud from the 1 MHz SMCLK after
+//! a reset. The registers of the module start at the address of the `__console_uart` symbol,
+//! which defaults to USCI_A0 or eUSCI_A0 of the family selected with `MSP430_MCU`; override these
+//! in `memory.x` if needed:
+//!
+//! ``` text
+//! /* eUSCI_A1 of a FR5xx device at 115200 baud from an 8 MHz SMCLK */
+//! __console_uart = 0x05E0;
+//! __console_brw = 4;
+//! __console_mctl = 0x5551;
+//! ```
+//!
+//! With a `board-*` feature `init` also selects the UART function of the backchannel UART pins;
+//! otherwise the program has to select the function of the pins it uses. On FRAM devices the pins
+//! only take that function once the `LOCKLPM5` bit of `PM5CTL0` has been cleared.
+
+use core::fmt;
+
+use regs::{configure, send};
+
+#[cfg(any(
+    msp430_family = "f1",
+    msp430_family = "f4",
+    msp430_family = "f5",
+    msp430_family = "f6"
+))]
+compile_error!(
+    "the `uart-console` feature of msp430-rt requires a device with a USCI_A (G2xx, F2xx) or \
+     eUSCI_A (FRAM) module; see `MSP430_MCU`"
+);
+
+/// The UART console, for use with `write!`
+#[derive(Clone, Copy, Debug)]
+pub struct Console;
+
+impl fmt::Write for Console {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        write_str(s);
+        Ok(())
+    }
+}
+
+fn value(symbol: &u8) -> usize {
+    symbol as *const u8 as usize
+}
+
+fn base() -> usize {
+    extern "C" {
+        // Absolute symbol; its address is the base address of the module
+        static __console_uart: u8;
+    }
+
+    value(unsafe { &__console_uart })
+}
+
+/// Configures the UART; see the module documentation
+pub fn init() {
+    extern "C" {
+        // Absolute symbols; their addresses are the baud rate settings
+        static __console_brw: u8;
+        static __console_mctl: u8;
+    }
+
+    let (brw, mctl) = unsafe { (value(&__console_brw) as u16, value(&__console_mctl) as u16) };
+    unsafe { configure(base(), brw, mctl) };
+
+    #[cfg(any(
+        feature = "board-msp-exp430fr2433",
+        feature = "board-msp-exp430fr5969",
+        feature = "board-msp-exp430g2et"
+    ))]
+    crate::board::select_uart_pins();
+}
+
+/// Sends `s`, waiting for each byte to be queued for transmission
+pub fn write_str(s: &str) {
+    write_bytes(s.as_bytes());
+}
+
+/// Sends `bytes`, waiting for each byte to be queued for transmission
+pub fn write_bytes(bytes: &[u8]) {
+    let base = base();
+    for &byte in bytes {
+        unsafe { send(base, byte) };
+    }
+}
+
+/// Sends `n` in decimal
+pub(crate) fn write_decimal(mut n: u32) {
+    let mut digits = [0; 10];
+    let mut len = 0;
+    loop {
+        digits[digits.len() - 1 - len] = b'0' + (n % 10) as u8;
+        len += 1;
+        n /= 10;
+        if n == 0 {
+            break;
+        }
+    }
+    write_bytes(&digits[digits.len() - len..]);
+}
+
+/// Sends `n` as 4 hexadecimal digits, prefixed by `0x`
+pub(crate) fn write_hex(n: u16) {
+    let mut digits = *b"0x0000";
+    for (i, digit) in digits[2..].iter_mut().enumerate() {
+        let nibble = (n >> (12 - 4 * i)) as u8 & 0xF;
+        *digit = if nibble < 10 {
+            b'0' + nibble
+        } else {
+            b'A' + nibble - 10
+        };
+    }
+    write_bytes(&digits);
+}
+
+// USCI_A0 of G2xx and F2xx devices: byte registers, with the interrupt flags in `IFG2`
+#[cfg(any(msp430_family = "g2", msp430_family = "f2"))]
+mod regs {
+    const UCAXCTL1: usize = 0x01;
+    const UCAXBR0: usize = 0x02;
+    const UCAXBR1: usize = 0x03;
+    const UCAXMCTL: usize = 0x04;
+    const UCAXTXBUF: usize = 0x07;
+    const IFG2: usize = 0x0003;
+
+    const UCSWRST: u8 = 1 << 0;
+    const UCSSEL_SMCLK: u8 = 0b10 << 6;
+    const UCA0TXIFG: u8 = 1 << 1;
+
+    unsafe fn write(address: usize, value: u8) {
+        core::ptr::write_volatile(address as *mut u8, value);
+    }
+
+    pub unsafe fn configure(base: usize, brw: u16, mctl: u16) {
+        write(base + UCAXCTL1, UCSWRST | UCSSEL_SMCLK);
+        write(base + UCAXBR0, brw as u8);
+        write(base + UCAXBR1, (brw >> 8) as u8);
+        write(base + UCAXMCTL, mctl as u8);
+        write(base + UCAXCTL1, UCSSEL_SMCLK);
+    }
+
+    pub unsafe fn send(base: usize, byte: u8) {
+        while core::ptr::read_volatile(IFG2 as *const u8) & UCA0TXIFG == 0 {}
+        write(base + UCAXTXBUF, byte);
+    }
+}
+
+// eUSCI_A of the FRAM families: word registers
+#[cfg(not(any(msp430_family = "g2", msp430_family = "f2")))]
+mod regs {
+    const UCAXCTLW0: usize = 0x00;
+    const UCAXBRW: usize = 0x06;
+    const UCAXMCTLW: usize = 0x08;
+    const UCAXTXBUF: usize = 0x0E;
+    const UCAXIFG: usize = 0x1C;
+
+    const UCSWRST: u16 = 1 << 0;
+    const UCSSEL_SMCLK: u16 = 0b10 << 6;
+    const UCTXIFG: u16 = 1 << 1;
+
+    unsafe fn write(address: usize, value: u16) {
+        core::ptr::write_volatile(address as *mut u16, value);
+    }
+
+    pub unsafe fn configure(base: usize, brw: u16, mctl: u16) {
+        write(base + UCAXCTLW0, UCSWRST);
+        write(base + UCAXCTLW0, UCSWRST | UCSSEL_SMCLK);
+        write(base + UCAXBRW, brw);
+        write(base + UCAXMCTLW, mctl);
+        write(base + UCAXCTLW0, UCSSEL_SMCLK);
+    }
+
+    pub unsafe fn send(base: usize, byte: u8) {
+        while core::ptr::read_volatile((base + UCAXIFG) as *const u16) & UCTXIFG == 0 {}
+        write(base + UCAXTXBUF, u16::from(byte));
+    }
+}
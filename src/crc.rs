@@ -0,0 +1,177 @@
+//! CRC computation and boot-time image verification
+//!
+//! The CRC used throughout this crate is CRC-16-CCITT (polynomial `0x1021`, initial value
+//! `0xFFFF`, no bit reflection and no final XOR). This is the same algorithm implemented by the
+//! CRC16 peripheral found on many MSP430 devices, so values computed on the host, in software and
+//! by the hardware all agree.
+//!
+//! The `vector-crc` feature adds `verify_vector_table`, which checks the vector table against a
+//! stored CRC.
+//!
+//! When the `crc-hw` feature is enabled the computation is offloaded to the CRC16 peripheral. This
+//! speeds up the boot-time image verification by one to two orders of magnitude.
+
+/// Initial value of the CRC-16-CCITT computation
+pub const CRC16_INIT: u16 = 0xFFFF;
+
+/// Updates a running CRC-16-CCITT with the given `data`
+///
+/// Start the computation with `CRC16_INIT`. Feeding data in several chunks gives the same result
+/// as feeding all of it at once.
+///
+/// With the `crc-hw` feature the CRC16 peripheral is used instead, and interrupts are masked for
+/// the duration of the call so that interrupt handlers can use this function too.
+#[cfg(not(feature = "crc-hw"))]
+pub fn crc16_update(mut crc: u16, data: &[u8]) -> u16 {
+    for byte in data {
+        crc ^= u16::from(*byte) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+
+    crc
+}
+
+/// Updates a running CRC-16-CCITT with the given `data`
+///
+/// Start the computation with `CRC16_INIT`. Feeding data in several chunks gives the same result
+/// as feeding all of it at once.
+///
+/// With the `crc-hw` feature the CRC16 peripheral is used instead, and interrupts are masked for
+/// the duration of the call so that interrupt handlers can use this function too.
+#[cfg(feature = "crc-hw")]
+pub fn crc16_update(crc: u16, data: &[u8]) -> u16 {
+    // Register block of the CRC16 peripheral
+    #[repr(C)]
+    struct Crc16 {
+        crcdi: u16,
+        crcdirb: u16,
+        crcinires: u16,
+        crcresr: u16,
+    }
+
+    extern "C" {
+        // Provided by the linker script; see `__crc16` in `link.x`
+        static mut __crc16: Crc16;
+    }
+
+    msp430::interrupt::free(|_| unsafe {
+        let regs = &mut __crc16 as *mut Crc16;
+
+        core::ptr::write_volatile(&mut (*regs).crcinires, crc);
+        // Feeding the data in reverse bit order is what makes the peripheral compute the standard
+        // (non reflected) CRC-16-CCITT
+        let crcdirb_l = &mut (*regs).crcdirb as *mut u16 as *mut u8;
+        for byte in data {
+            core::ptr::write_volatile(crcdirb_l, *byte);
+        }
+
+        core::ptr::read_volatile(&(*regs).crcinires)
+    })
+}
+
+/// Computes the CRC-16-CCITT of `data`
+pub fn crc16(data: &[u8]) -> u16 {
+    crc16_update(CRC16_INIT, data)
+}
+
+// Expected CRC of the image. The placeholder value is patched after linking by
+// `msp430_rt_build::patch_crcs`.
+#[cfg(feature = "image-crc")]
+#[link_section = ".image_crc"]
+#[no_mangle]
+static __IMAGE_CRC: u16 = 0xFFFF;
+
+/// Computes the CRC of the image range and compares it against the stored value
+///
+/// The image range is given by the `__image_crc_start` and `__image_crc_end` symbols. On mismatch
+/// `ImageCrcMismatch` is called, which never returns.
+#[cfg(feature = "image-crc")]
+pub(crate) unsafe fn verify_image() {
+    extern "C" {
+        static __image_crc_start: u8;
+        static __image_crc_end: u8;
+
+        fn ImageCrcMismatch(expected: u16, computed: u16) -> !;
+    }
+
+    let start = &__image_crc_start as *const u8;
+    let len = &__image_crc_end as *const u8 as usize - start as usize;
+
+    let computed = crc16(core::slice::from_raw_parts(start, len));
+    // The stored value is patched after compilation; don't let the compiler constant fold it
+    let expected = core::ptr::read_volatile(&__IMAGE_CRC);
+
+    if computed != expected {
+        ImageCrcMismatch(expected, computed)
+    }
+}
+
+#[cfg(feature = "image-crc")]
+#[no_mangle]
+extern "C" fn ImageCrcMismatch_(_expected: u16, _computed: u16) -> ! {
+    crate::park()
+}
+
+// Expected CRC of the vector table. Like `__IMAGE_CRC` it's patched after linking, and
+// `vectors::install` updates it.
+#[cfg(feature = "vector-crc")]
+#[link_section = ".image_crc"]
+#[no_mangle]
+static mut __VECTOR_CRC: u16 = 0xFFFF;
+
+/// Computes the CRC of the vector table
+#[cfg(feature = "vector-crc")]
+fn vector_table_crc() -> u16 {
+    extern "C" {
+        static __vector_table_start: u8;
+        static __vector_table_end: u8;
+    }
+
+    unsafe {
+        let start = &__vector_table_start as *const u8;
+        let len = &__vector_table_end as *const u8 as usize - start as usize;
+
+        crc16(core::slice::from_raw_parts(start, len))
+    }
+}
+
+/// Stores the CRC of the vector table as it is now
+///
+/// The FRAM must be writable, i.e. this must run inside `fram::unprotected`.
+#[cfg(all(feature = "vector-crc", feature = "fram-vectors"))]
+pub(crate) unsafe fn store_vector_table_crc() {
+    core::ptr::write_volatile(core::ptr::addr_of_mut!(__VECTOR_CRC), vector_table_crc());
+}
+
+/// Computes the CRC of the vector table and compares it against the stored value
+///
+/// On mismatch `VectorTableCorrupted` is called, which never returns. The reset handler calls this
+/// function before calling the entry point; it can also be called periodically, e.g. from a timer
+/// interrupt, to detect corruption at runtime.
+#[cfg(feature = "vector-crc")]
+pub fn verify_vector_table() {
+    extern "C" {
+        fn VectorTableCorrupted(expected: u16, computed: u16) -> !;
+    }
+
+    unsafe {
+        let computed = vector_table_crc();
+        let expected = core::ptr::read_volatile(core::ptr::addr_of!(__VECTOR_CRC));
+
+        if computed != expected {
+            VectorTableCorrupted(expected, computed)
+        }
+    }
+}
+
+#[cfg(feature = "vector-crc")]
+#[no_mangle]
+extern "C" fn VectorTableCorrupted_(_expected: u16, _computed: u16) -> ! {
+    crate::park()
+}
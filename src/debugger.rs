@@ -0,0 +1,90 @@
+//! Boot delay for attaching a debugger
+//!
+//! A program that reconfigures the clocks or enters a low-power mode right away leaves little time
+//! to attach a debugger, which can't connect to a sleeping MSP430. With the `debugger-delay`
+//! feature the reset handler spins for a while, before the `#[pre_init]` function, when either:
+//!
+//! - the program called `request_delay` before the last reset, e.g. on a command received over a
+//! serial port, followed by `reset::software_reset`; or
+//! - the pin given by the `__debugger_delay_in` symbol, the address of its `PxIN` register, and by
+//! the `__debugger_delay_mask` symbol, its bit, reads high. The default mask, 0, selects no pin.
+//! On devices with LPMx.5, e.g. FRAM devices, the pins are locked after a reset, so only the
+//! request works there.
+//!
+//! The window lasts about `__debugger_delay_ms` milliseconds at the default MCLK of 1 MHz, 500 by
+//! default. The watchdog is held during the window, and then gets its previous configuration
+//! back. A debugger can end the window early by setting `__debugger_delay_skip` to a non-zero
+//! value, e.g. with `set var __debugger_delay_skip = 1` in GDB.
+//!
+//! ``` text
+//! /* memory.x: wait while P1.1 (P1IN = 0x0020 on G2xx devices) is high */
+//! __debugger_delay_in = 0x0020;
+//! __debugger_delay_mask = 0x02;
+//! ```
+
+use core::ptr;
+
+/// Value of `REQUEST` when a delay was requested
+const REQUESTED: u16 = 0xDE1A;
+
+// Password and hold bit of `WDTCTL`
+const WDTPW: u16 = 0x5A << 8;
+const WDTHOLD: u16 = 1 << 7;
+const WDTCNTCL: u16 = 1 << 3;
+
+// In `.user_ram`, so that it survives the reset
+#[link_section = ".user_ram.debugger_delay"]
+static mut REQUEST: u16 = 0;
+
+/// Loop iterations that take about a millisecond at 1 MHz
+const LOOPS_PER_MS: u16 = 125;
+
+// Set by a debugger to end the window early
+#[export_name = "__debugger_delay_skip"]
+static mut SKIP: u16 = 0;
+
+/// Makes the next reset wait for a debugger
+///
+/// The request is kept in `.user_ram`, which the reset handler doesn't initialize, and cleared
+/// when the delay starts. After a power-up the RAM holds random values, which can match the
+/// request by chance, so the first boot may also wait.
+pub fn request_delay() {
+    unsafe { ptr::write_volatile(ptr::addr_of_mut!(REQUEST), REQUESTED) };
+}
+
+/// Spins for the delay window if it's been requested
+pub(crate) unsafe fn wait() {
+    extern "C" {
+        // Absolute symbols; see the module documentation
+        static __debugger_delay_in: u8;
+        static __debugger_delay_mask: u8;
+        static __debugger_delay_ms: u8;
+        static __wdtctl: u8;
+    }
+
+    let mask = ptr::addr_of!(__debugger_delay_mask) as usize as u8;
+    let pin = mask != 0
+        && ptr::read_volatile(ptr::addr_of!(__debugger_delay_in) as usize as *const u8) & mask != 0;
+    let requested = ptr::read_volatile(ptr::addr_of!(REQUEST)) == REQUESTED;
+    if !(pin || requested) {
+        return;
+    }
+    ptr::write_volatile(ptr::addr_of_mut!(REQUEST), 0);
+
+    let wdtctl = ptr::addr_of!(__wdtctl) as usize as *mut u16;
+    // The upper byte reads back as a different value than the password
+    let watchdog = ptr::read_volatile(wdtctl) & 0xFF;
+    ptr::write_volatile(wdtctl, WDTPW | watchdog | WDTHOLD);
+
+    ptr::write_volatile(ptr::addr_of_mut!(SKIP), 0);
+    let ms = ptr::addr_of!(__debugger_delay_ms) as usize as u16;
+    'window: for _ in 0..ms {
+        for _ in 0..LOOPS_PER_MS {
+            if ptr::read_volatile(ptr::addr_of!(SKIP)) != 0 {
+                break 'window;
+            }
+        }
+    }
+
+    ptr::write_volatile(wdtctl, WDTPW | watchdog | WDTCNTCL);
+}
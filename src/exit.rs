@@ -0,0 +1,100 @@
+//! Ending the program
+
+// In `.user_ram`, so that it survives a reset
+#[export_name = "__exit_code"]
+#[link_section = ".user_ram.exit_code"]
+static mut CODE: i16 = 0;
+
+/// Ends the program with exit status `code`
+///
+/// This calls the function `Exit`, which never returns. The default stores `code` in the
+/// `__exit_code` variable, which lives in `.user_ram` and so survives a reset, then disables the
+/// interrupts and loops forever. A test runner or debugger script can set a breakpoint on `Exit`
+/// and read the code from `r12`, or read `__exit_code` once the device is halted.
+///
+/// Under a simulator define `Exit` to end the simulation instead, e.g. by calling the `_exit` of
+/// the simulator's C library; on hardware it can, e.g., report the code and reset the device:
+///
+/// ``` ignore
+/// #[no_mangle]
+/// extern "C" fn Exit(code: i16) -> ! {
+///     report(code);
+///     msp430_rt::reset::software_reset()
+/// }
+/// ```
+///
+/// With the `newlib-syscalls` feature the default `_exit` of C code goes through `Exit` too.
+pub fn exit(code: i16) -> ! {
+    extern "C" {
+        fn Exit(code: i16) -> !;
+    }
+
+    unsafe { Exit(code) }
+}
+
+/// A value that `main` can return, and the exit status it reports
+///
+/// An `#[entry]` function that returns instead of diverging passes the status of its return value
+/// to the function `OnExit`, then ends the program with `exit`. The default `OnExit` does nothing;
+/// define it to, e.g., log the result of a test binary before `Exit` runs:
+///
+/// ``` ignore
+/// #[entry]
+/// fn main() -> Result<(), Error> {
+///     self_test()?;
+///     Ok(())
+/// }
+///
+/// #[no_mangle]
+/// extern "C" fn OnExit(code: i16) {
+///     // ..
+/// }
+/// ```
+pub trait Termination {
+    /// Returns the exit status
+    fn report(self) -> i16;
+}
+
+/// Status 0
+impl Termination for () {
+    fn report(self) -> i16 {
+        0
+    }
+}
+
+/// The value itself
+impl Termination for i16 {
+    fn report(self) -> i16 {
+        self
+    }
+}
+
+/// The status of the value for `Ok`, and 1 for `Err`
+impl<T: Termination, E> Termination for Result<T, E> {
+    fn report(self) -> i16 {
+        match self {
+            Ok(value) => value.report(),
+            Err(_) => 1,
+        }
+    }
+}
+
+/// Called when the entry point returns; see `Termination`
+#[doc(hidden)]
+pub fn main_returned(code: i16) -> ! {
+    extern "C" {
+        fn OnExit(code: i16);
+    }
+
+    unsafe { OnExit(code) };
+    exit(code)
+}
+
+#[no_mangle]
+extern "C" fn OnExit_(_code: i16) {}
+
+#[no_mangle]
+extern "C" fn Exit_(code: i16) -> ! {
+    unsafe { core::ptr::write_volatile(core::ptr::addr_of_mut!(CODE), code) };
+    crate::park()
+}
@@ -0,0 +1,160 @@
+//! Runtime services for C code
+//!
+//! With the `c-api` feature the services of the runtime are also exported as `extern "C"`
+//! functions, prefixed with `msp430_rt_`, so that the C modules of a mixed program can use them.
+//! The build script generates the matching header, `msp430_rt.h`, and passes the directory that
+//! contains it to the build scripts of the crates that depend on `msp430-rt` through the
+//! `DEP_MSP430_RT_INCLUDE` environment variable, e.g. for the `cc` crate:
+//!
+//! ``` ignore
+//! // build.rs
+//! cc::Build::new()
+//!     .include(std::env::var("DEP_MSP430_RT_INCLUDE").unwrap())
+//!     .file("src/driver.c")
+//!     .compile("driver");
+//! ```
+
+use core::ptr;
+
+/// Resets the device; see `reset::software_reset`
+#[no_mangle]
+pub extern "C" fn msp430_rt_software_reset() -> ! {
+    crate::reset::software_reset()
+}
+
+/// Returns the start of the heap, `_sheap`
+#[no_mangle]
+pub extern "C" fn msp430_rt_heap_start() -> *mut u8 {
+    extern "C" {
+        static mut _sheap: u8;
+    }
+
+    ptr::addr_of_mut!(_sheap)
+}
+
+/// Returns the end of the heap, `_eheap`; the heap is empty unless its size is set
+#[no_mangle]
+pub extern "C" fn msp430_rt_heap_end() -> *mut u8 {
+    extern "C" {
+        static mut _eheap: u8;
+    }
+
+    ptr::addr_of_mut!(_eheap)
+}
+
+/// Returns `true` if called from an interrupt handler; see `isr::in_isr`
+#[cfg(feature = "isr-depth")]
+#[no_mangle]
+pub extern "C" fn msp430_rt_in_isr() -> bool {
+    crate::isr::in_isr()
+}
+
+/// Returns the nesting depth of interrupt handlers; see `isr::isr_depth`
+#[cfg(feature = "isr-depth")]
+#[no_mangle]
+pub extern "C" fn msp430_rt_isr_depth() -> u8 {
+    crate::isr::isr_depth()
+}
+
+/// Ends the program with exit status `code`; see `exit`
+#[no_mangle]
+pub extern "C" fn msp430_rt_exit(code: i16) -> ! {
+    crate::exit(code)
+}
+
+/// Calls `HeapCollision` if the stack has grown into the heap; see `check_heap_collision`
+#[cfg(feature = "newlib-syscalls")]
+#[no_mangle]
+pub extern "C" fn msp430_rt_check_heap_collision() {
+    crate::check_heap_collision()
+}
+
+/// Causes of `reset::Cause`, in the order of the `MSP430_RT_RESET_*` constants of the header
+#[cfg(feature = "reset-counters")]
+const CAUSES: [crate::reset::Cause; 7] = {
+    use crate::reset::Cause;
+
+    [
+        Cause::Brownout,
+        Cause::Pin,
+        Cause::Svs,
+        Cause::Watchdog,
+        Cause::Software,
+        Cause::SecurityViolation,
+        Cause::Other,
+    ]
+};
+
+/// Returns the number of resets due to `cause`, or 0 if it isn't a valid cause; see `reset::count`
+#[cfg(feature = "reset-counters")]
+#[no_mangle]
+pub extern "C" fn msp430_rt_reset_count(cause: u8) -> u16 {
+    CAUSES
+        .get(usize::from(cause))
+        .map_or(0, |&cause| crate::reset::count(cause))
+}
+
+/// Returns the cause of the last reset, or -1 if it wasn't recorded; see `reset::last_cause`
+#[cfg(feature = "reset-counters")]
+#[no_mangle]
+pub extern "C" fn msp430_rt_reset_last_cause() -> i8 {
+    crate::reset::last_cause()
+        .and_then(|cause| CAUSES.iter().position(|&c| c == cause))
+        .map_or(-1, |i| i as i8)
+}
+
+/// Sets the reset counters back to 0; see `reset::clear_counts`
+#[cfg(feature = "reset-counters")]
+#[no_mangle]
+pub extern "C" fn msp430_rt_reset_clear_counts() {
+    crate::reset::clear_counts()
+}
+
+/// Records the event `code`; see `blackbox::record`
+#[cfg(feature = "blackbox")]
+#[no_mangle]
+pub extern "C" fn msp430_rt_blackbox_record(code: u16) {
+    crate::blackbox::record(code)
+}
+
+/// Freezes the event log; see `blackbox::seal`
+#[cfg(feature = "blackbox")]
+#[no_mangle]
+pub extern "C" fn msp430_rt_blackbox_seal() {
+    crate::blackbox::seal()
+}
+
+/// Returns `true` if the event log is sealed; see `blackbox::is_sealed`
+#[cfg(feature = "blackbox")]
+#[no_mangle]
+pub extern "C" fn msp430_rt_blackbox_is_sealed() -> bool {
+    crate::blackbox::is_sealed()
+}
+
+/// Empties and unseals the event log; see `blackbox::clear`
+#[cfg(feature = "blackbox")]
+#[no_mangle]
+pub extern "C" fn msp430_rt_blackbox_clear() {
+    crate::blackbox::clear()
+}
+
+/// Copies up to `max` events of the log to `events`, oldest first, and returns how many it copied;
+/// see `blackbox::events`
+///
+/// # Safety
+///
+/// `events` must point to `max` writable events.
+#[cfg(feature = "blackbox")]
+#[no_mangle]
+pub unsafe extern "C" fn msp430_rt_blackbox_events(
+    events: *mut crate::blackbox::Event,
+    max: u8,
+) -> u8 {
+    let mut copied = 0;
+    for event in crate::blackbox::events().take(usize::from(max)) {
+        ptr::write(events.add(usize::from(copied)), event);
+        copied += 1;
+    }
+
+    copied
+}
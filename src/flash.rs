@@ -0,0 +1,72 @@
+//! In-application flash programming
+//!
+//! While the flash controller of a flash based device writes or erases a segment, the flash can't
+//! be read, so the CPU can't fetch instructions from it. The routine that drives the flash
+//! controller must therefore run from RAM, and so must everything it calls: interrupt handlers
+//! included.
+//!
+//! Functions placed in the `.ramfunc` section are linked to run from RAM. Their code is stored in
+//! `ROM`, next to the `.data` initializers (in the information memory with the `data-in-info`
+//! feature), and copied to RAM by the reset handler.
+//!
+//! ``` ignore
+//! #[link_section = ".ramfunc"]
+//! #[inline(never)]
+//! fn write_word((addr, value): (*mut u16, u16)) -> bool {
+//!     // unlock the flash controller, write the word, wait for completion and lock it again
+//! }
+//!
+//! let written = msp430_rt::flash::program(0x1000..0x1040, write_word, (0x1000 as *mut u16, 42));
+//! ```
+//!
+//! `program` checks that the routine has been placed in RAM and that the target range doesn't
+//! overlap the running image, and runs the routine with interrupts disabled. The previous
+//! interrupt state is restored afterwards.
+//!
+//! A `.ramfunc` routine doesn't run from RAM if the compiler inlines it into its caller, so mark
+//! it `#[inline(never)]`. It must also avoid calls into functions that live in `ROM`. This
+//! includes the compiler intrinsics used for, e.g., multiplication and `memcpy`; check the
+//! disassembly of the routine. Non-maskable interrupts remain enabled; their handlers must not
+//! be in flash either.
+
+use core::ops::Range;
+
+/// Errors reported by `program`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// The routine is not located in the `.ramfunc` section
+    NotInRam,
+    /// The target range overlaps the `ROM` region of the running image
+    RunningImage,
+}
+
+/// Runs the flash programming `routine` with `arg`, with interrupts disabled
+///
+/// `target` is the address range the routine writes or erases. It must not overlap the `ROM`
+/// region of the running image, given by the `_srom` and `_erom` symbols.
+pub fn program<A, R>(target: Range<usize>, routine: fn(A) -> R, arg: A) -> Result<R, Error> {
+    extern "C" {
+        static _sramfunc: u8;
+        static _eramfunc: u8;
+        static _srom: u8;
+        static _erom: u8;
+    }
+
+    let addr = |symbol: &u8| symbol as *const u8 as usize;
+    let (ramfunc, rom) = unsafe {
+        (
+            addr(&_sramfunc)..addr(&_eramfunc),
+            addr(&_srom)..addr(&_erom),
+        )
+    };
+
+    if !ramfunc.contains(&(routine as usize)) {
+        return Err(Error::NotInRam);
+    }
+    if target.start < rom.end && target.end > rom.start {
+        return Err(Error::RunningImage);
+    }
+
+    // On FRAM devices, the routine may write to memory protected by the `fram` module
+    Ok(crate::fram::unprotected(|| routine(arg)))
+}
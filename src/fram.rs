@@ -0,0 +1,115 @@
+//! FRAM write protection
+//!
+//! On FR2xx/FR4xx devices the `SYSCFG0` register write protects the program FRAM, with the `PFWP`
+//! bit, and the information FRAM, with the `DFWP` bit. A stray write through a bad pointer then
+//! leaves the code and the constants intact instead of silently corrupting them. The functions of
+//! this module use the register whose address is given by the `__syscfg0` symbol, which
+//! `MSP430_MCU` sets for these families; with the default, 0, they do nothing.
+//!
+//! With the `fram-write-protect` feature the reset handler calls `protect` once `.data` has been
+//! initialized and the constructors have run, right before the program entry point. Code that
+//! writes to FRAM afterwards, e.g. to update a `.persistent` variable, must lift the protection
+//! for the duration of the write:
+//!
+//! ``` ignore
+//! #[link_section = ".persistent"]
+//! static mut BOOT_COUNT: u16 = 0;
+//!
+//! msp430_rt::fram::unprotected(|| unsafe { BOOT_COUNT += 1 });
+//! ```
+//!
+//! On FR5xx/FR6xx devices the FRAM is write protected by the MPU instead; see the `mpu` module.
+//!
+//! # Read-only text
+//!
+//! The `readonly-text` feature makes the code non-writable on any FRAM device, at the same point
+//! of the reset handler. On FR2xx/FR4xx devices it does the same as `fram-write-protect`. On
+//! FR5xx/FR6xx devices it configures the MPU with three segments, at 1 KiB borders computed by
+//! the linker:
+//!
+//! - from the start of the main FRAM to the end of `.text`: read and execute;
+//! - from there to `.persistent`: read only, which covers the constants and the `.data`
+//! initializers;
+//! - from `.persistent` to the end of the main FRAM, and the information memory: read and write.
+//!
+//! `.persistent` variables can then be written directly on FR5xx/FR6xx devices, but not on
+//! FR2xx/FR4xx devices, whose whole program FRAM is protected. `unprotected` lifts the protection
+//! in both cases, by also disabling the MPU on FR5xx/FR6xx devices. The writes done by
+//! `journal::Journal`, `flash::program` and `vectors::install` already go through it. As the
+//! borders are rounded to 1 KiB, the last constants before `.persistent` may be writable too.
+
+// FRAM write protection password and bits of `SYSCFG0`
+const FRWPPW: u16 = 0xA5 << 8;
+const PFWP: u16 = 1 << 0;
+const DFWP: u16 = 1 << 1;
+
+/// Returns the `SYSCFG0` register, if the device has one
+fn syscfg0() -> Option<*mut u16> {
+    extern "C" {
+        // Absolute symbol; its address is the address of the `SYSCFG0` register, or 0
+        static __syscfg0: u8;
+    }
+
+    // The compiler assumes that the address of a symbol is never 0; hide it behind a volatile read
+    let syscfg0 = unsafe { core::ptr::read_volatile(&(&__syscfg0 as *const u8 as usize)) };
+    if syscfg0 == 0 {
+        None
+    } else {
+        Some(syscfg0 as *mut u16)
+    }
+}
+
+/// Write protects the program and the information FRAM
+///
+/// With the `readonly-text` feature this also applies its MPU configuration on FR5xx/FR6xx
+/// devices.
+pub fn protect() {
+    #[cfg(all(
+        feature = "readonly-text",
+        any(msp430_family = "fr5", msp430_family = "fr6")
+    ))]
+    unsafe {
+        crate::mpu::init();
+    }
+
+    if let Some(syscfg0) = syscfg0() {
+        unsafe {
+            // The upper byte reads back as a different value than the password
+            let value = core::ptr::read_volatile(syscfg0) & 0xFF;
+            core::ptr::write_volatile(syscfg0, FRWPPW | value | PFWP | DFWP);
+        }
+    }
+}
+
+/// Runs `f` with the write protection of the FRAM lifted and restores it afterwards
+///
+/// `f` runs with interrupts disabled, so that no handler runs while the FRAM is writable. With the
+/// `readonly-text` feature the MPU is disabled as well on FR5xx/FR6xx devices.
+pub fn unprotected<R>(f: impl FnOnce() -> R) -> R {
+    msp430::interrupt::free(|_| unsafe {
+        #[cfg(all(
+            feature = "readonly-text",
+            any(msp430_family = "fr5", msp430_family = "fr6")
+        ))]
+        let mpu = crate::mpu::suspend();
+
+        let protection = syscfg0().map(|syscfg0| {
+            let value = core::ptr::read_volatile(syscfg0) & 0xFF;
+            core::ptr::write_volatile(syscfg0, FRWPPW | (value & !(PFWP | DFWP)));
+            (syscfg0, value)
+        });
+
+        let result = f();
+
+        if let Some((syscfg0, value)) = protection {
+            core::ptr::write_volatile(syscfg0, FRWPPW | value);
+        }
+        #[cfg(all(
+            feature = "readonly-text",
+            any(msp430_family = "fr5", msp430_family = "fr6")
+        ))]
+        crate::mpu::resume(mpu);
+
+        result
+    })
+}
@@ -0,0 +1,88 @@
+//! Boot-time image authentication
+//!
+//! See the `image-signature` feature in the crate documentation.
+
+/// Reserves space for the signature of the image
+///
+/// **IMPORTANT**: This macro can appear at most *once* in the dependency graph.
+///
+/// This places `$len` bytes, initialized to `0xFF`, in the `.image_signature` section, which is
+/// located right after the image in the `ROM` region. The placeholder is meant to be patched with
+/// the actual signature by a host tool after linking.
+///
+/// # Examples
+///
+/// ``` ignore
+/// use msp430_rt::image_signature;
+///
+/// // Ed25519 signature
+/// image_signature!(64);
+/// ```
+#[cfg(feature = "image-signature")]
+#[macro_export]
+macro_rules! image_signature {
+    ($len:expr) => {
+        #[link_section = ".image_signature"]
+        #[no_mangle]
+        #[used]
+        static __IMAGE_SIGNATURE: [u8; $len] = [0xFF; $len];
+    };
+}
+
+/// Reserves space for the signature of the image
+///
+/// This macro requires the `image-signature` feature.
+#[cfg(not(feature = "image-signature"))]
+#[macro_export]
+macro_rules! image_signature {
+    ($($tt:tt)*) => {
+        compile_error!("`image_signature!` requires the `image-signature` feature of msp430-rt");
+    };
+}
+
+/// Checks `signature` against `image` using the user provided `VerifySignature` function
+///
+/// An empty signature is always rejected.
+#[cfg(feature = "image-signature")]
+pub(crate) fn check(image: &[u8], signature: &[u8]) -> bool {
+    extern "Rust" {
+        // Provided by the user; there's no default implementation
+        fn VerifySignature(image: &[u8], signature: &[u8]) -> bool;
+    }
+
+    !signature.is_empty() && unsafe { VerifySignature(image, signature) }
+}
+
+/// Verifies the signature of the image range
+///
+/// The image range is the one covered by the image CRC, `__image_crc_start` .. `__image_crc_end`.
+/// On failure `ImageSignatureInvalid` is called, which never returns.
+#[cfg(feature = "image-signature")]
+pub(crate) unsafe fn verify_image() {
+    extern "C" {
+        static __image_crc_start: u8;
+        static __image_crc_end: u8;
+        static __image_signature_start: u8;
+        static __image_signature_end: u8;
+
+        fn ImageSignatureInvalid() -> !;
+    }
+
+    let slice = |start: &u8, end: &u8| {
+        let start = start as *const u8;
+        core::slice::from_raw_parts(start, end as *const u8 as usize - start as usize)
+    };
+
+    let image = slice(&__image_crc_start, &__image_crc_end);
+    let signature = slice(&__image_signature_start, &__image_signature_end);
+
+    if !check(image, signature) {
+        ImageSignatureInvalid()
+    }
+}
+
+#[cfg(feature = "image-signature")]
+#[no_mangle]
+extern "C" fn ImageSignatureInvalid_() -> ! {
+    crate::park()
+}
@@ -0,0 +1,44 @@
+//! C constructors
+//!
+//! The linker script collects the constructors of C and C++ code linked into the program, e.g.
+//! functions marked with `__attribute__((constructor))`, and the Rust functions marked with
+//! `#[ctor]`, in the `.init_array` output section. The reset handler calls them after `.bss` and
+//! `.data` have been initialized, right before `main`.
+//!
+//! Compilers either register constructors in `.init_array`, which is run from start to end, or in
+//! the older `.ctors`, which is run from end to start. Both are supported; the entries of
+//! `.init_array` run first.
+
+use core::{mem, ptr};
+
+/// A constructor
+type Constructor = unsafe extern "C" fn();
+
+/// Calls the constructors collected in `.init_array`
+pub(crate) unsafe fn run() {
+    extern "C" {
+        static __init_array_start: usize;
+        static __init_array_end: usize;
+        static __ctors_start: usize;
+        static __ctors_end: usize;
+    }
+
+    let mut entry = ptr::addr_of!(__init_array_start);
+    while entry < ptr::addr_of!(__init_array_end) {
+        call(ptr::read(entry));
+        entry = entry.add(1);
+    }
+
+    let mut entry = ptr::addr_of!(__ctors_end);
+    while entry > ptr::addr_of!(__ctors_start) {
+        entry = entry.sub(1);
+        call(ptr::read(entry));
+    }
+}
+
+unsafe fn call(constructor: usize) {
+    // crtbegin.o and crtend.o delimit `.ctors` with -1 and 0 when the GCC startup files are linked
+    if constructor != 0 && constructor != usize::MAX {
+        mem::transmute::<usize, Constructor>(constructor)();
+    }
+}
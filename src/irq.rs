@@ -0,0 +1,167 @@
+//! Masking interrupts by name
+//!
+//! Each MSP430 peripheral has its own interrupt enable bits, at different addresses on different
+//! device families. The `InterruptEnable` trait maps an interrupt to those bits, so that `mask`
+//! and `unmask` can enable and disable any interrupt through the same API. The trait is meant to
+//! be implemented by the device crate for its `Interrupt` enumeration. Implementations are
+//! usually a `match` on the interrupt; when the interrupt is known at compile time the call to
+//! `mask` or `unmask` is reduced to a single read-modify-write of the right register.
+//!
+//! ``` ignore
+//! use msp430_rt::irq::{EnableBits, InterruptEnable, Register};
+//!
+//! unsafe impl InterruptEnable for Interrupt {
+//!     fn enable_bits(self) -> EnableBits {
+//!         match self {
+//!             // P1IE: all the pins of port 1
+//!             Interrupt::PORT1 => EnableBits::new(Register::Byte(0x0025), 0xFF),
+//!             // TA0CCTL0.CCIE
+//!             Interrupt::TIMER0_A0 => EnableBits::new(Register::Word(0x0162), 1 << 4),
+//!             // ..
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! ``` ignore
+//! // P1.3 only
+//! msp430_rt::irq::unmask(Interrupt::PORT1, 1 << 3);
+//! msp430_rt::irq::unmask(Interrupt::TIMER0_A0, !0);
+//! ```
+//!
+//! Masking an interrupt doesn't clear its pending flags, and unmasking it doesn't set `GIE`.
+//!
+//! `MaskGuard` masks a set of interrupts for the duration of a scope. Unlike
+//! `msp430::interrupt::free` it leaves the other interrupts enabled, so it doesn't increase their
+//! latency:
+//!
+//! ``` ignore
+//! use msp430_rt::irq::MaskGuard;
+//!
+//! {
+//!     // The UART and timer handlers share `QUEUE` with this code
+//!     let _guard = MaskGuard::new([Interrupt::USCIAB0RX, Interrupt::TIMER0_A0]);
+//!     QUEUE.push(byte);
+//! } // the previous state of both interrupts is restored here
+//! ```
+
+use core::ptr;
+
+/// An interrupt enable register
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Register {
+    /// 8-bit register at the given address
+    Byte(usize),
+    /// 16-bit register at the given address
+    Word(usize),
+}
+
+/// The interrupt enable bits of an interrupt
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct EnableBits {
+    /// The register that contains the bits
+    pub register: Register,
+    /// The bits, e.g. all the pins of a port for a port interrupt
+    pub mask: u16,
+}
+
+impl EnableBits {
+    /// Creates a new `EnableBits`
+    pub const fn new(register: Register, mask: u16) -> Self {
+        EnableBits { register, mask }
+    }
+
+    /// Sets the enable bits selected by `bits` to `value` and returns the previous value of all the
+    /// enable bits, with interrupts disabled
+    pub(crate) fn modify(self, bits: u16, value: u16) -> u16 {
+        let bits = bits & self.mask;
+        let value = value & bits;
+        msp430::interrupt::free(|_| unsafe {
+            match self.register {
+                Register::Byte(addr) => {
+                    let old = ptr::read_volatile(addr as *const u8);
+                    let new = (old & !(bits as u8)) | value as u8;
+                    ptr::write_volatile(addr as *mut u8, new);
+                    u16::from(old) & self.mask
+                }
+                Register::Word(addr) => {
+                    let old = ptr::read_volatile(addr as *const u16);
+                    ptr::write_volatile(addr as *mut u16, (old & !bits) | value);
+                    old & self.mask
+                }
+            }
+        })
+    }
+
+    /// Returns the current value of the enable bits
+    pub(crate) fn read(self) -> u16 {
+        let value = unsafe {
+            match self.register {
+                Register::Byte(addr) => u16::from(ptr::read_volatile(addr as *const u8)),
+                Register::Word(addr) => ptr::read_volatile(addr as *const u16),
+            }
+        };
+
+        value & self.mask
+    }
+}
+
+/// Maps an interrupt to its interrupt enable bits
+///
+/// # Safety
+///
+/// `enable_bits` must return the interrupt enable bits of the interrupt, and only those.
+pub unsafe trait InterruptEnable: Copy {
+    /// Returns the interrupt enable bits of this interrupt
+    fn enable_bits(self) -> EnableBits;
+}
+
+/// Disables interrupt `irq`
+pub fn mask<I: InterruptEnable>(irq: I) {
+    irq.enable_bits().modify(!0, 0);
+}
+
+/// Enables the sources `bits` of interrupt `irq`
+///
+/// `bits` selects among the enable bits of the interrupt, e.g. `1 << 3` for pin 3 of a port; the
+/// other sources are left as they are. Interrupts with a single enable bit are enabled by any
+/// non-zero `bits`, e.g. `!0`.
+pub fn unmask<I: InterruptEnable>(irq: I, bits: u16) {
+    irq.enable_bits().modify(bits, bits);
+}
+
+/// Returns `true` if any of the enable bits of interrupt `irq` is set
+pub fn is_unmasked<I: InterruptEnable>(irq: I) -> bool {
+    irq.enable_bits().read() != 0
+}
+
+/// Masks a set of interrupts until dropped
+///
+/// Dropping the guard restores the previous state of the enable bits of the interrupts, so guards
+/// can be nested.
+#[must_use = "the interrupts are unmasked again as soon as the guard is dropped"]
+pub struct MaskGuard<I: InterruptEnable, const N: usize> {
+    irqs: [I; N],
+    saved: [u16; N],
+}
+
+impl<I: InterruptEnable, const N: usize> MaskGuard<I, N> {
+    /// Masks the interrupts `irqs`
+    pub fn new(irqs: [I; N]) -> Self {
+        let mut saved = [0; N];
+        for (irq, saved) in irqs.iter().zip(saved.iter_mut()) {
+            *saved = irq.enable_bits().modify(!0, 0);
+        }
+
+        MaskGuard { irqs, saved }
+    }
+}
+
+impl<I: InterruptEnable, const N: usize> Drop for MaskGuard<I, N> {
+    fn drop(&mut self) {
+        // Restore in reverse order in case several interrupts share enable bits
+        for (irq, saved) in self.irqs.iter().zip(self.saved.iter()).rev() {
+            irq.enable_bits().modify(!0, *saved);
+        }
+    }
+}
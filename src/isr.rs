@@ -0,0 +1,45 @@
+//! Interrupt context tracking
+//!
+//! With the `isr-depth` feature every handler defined with `#[interrupt]` increments a nesting
+//! counter on entry and decrements it on exit. Libraries can use `in_isr` to choose between
+//! blocking and non-blocking code paths. Naked handlers (`#[interrupt(naked)]`) are not tracked.
+
+use core::ptr;
+
+static mut DEPTH: u8 = 0;
+
+/// Returns `true` if called from an interrupt handler
+pub fn in_isr() -> bool {
+    isr_depth() != 0
+}
+
+/// Returns the number of interrupt handlers currently running
+///
+/// This is `0` outside of interrupt handlers, `1` inside of an interrupt handler and more than
+/// that when interrupt handlers are nested, i.e. when a handler re-enables interrupts.
+pub fn isr_depth() -> u8 {
+    unsafe { ptr::read_volatile(&DEPTH) }
+}
+
+// Used by the code generated by `#[interrupt]`
+#[doc(hidden)]
+pub struct Guard(());
+
+#[doc(hidden)]
+#[inline(always)]
+pub fn enter() -> Guard {
+    // Interrupts are disabled on entry to a handler, so this can't race with a nested handler
+    unsafe { ptr::write_volatile(&mut DEPTH, DEPTH.wrapping_add(1)) };
+
+    Guard(())
+}
+
+impl Drop for Guard {
+    #[inline(always)]
+    fn drop(&mut self) {
+        // The handler may have re-enabled interrupts
+        msp430::interrupt::free(|_| unsafe {
+            ptr::write_volatile(&mut DEPTH, DEPTH.wrapping_sub(1))
+        });
+    }
+}
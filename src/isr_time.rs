@@ -0,0 +1,171 @@
+//! Interrupt execution time accounting, enabled by the `isr-time` feature
+//!
+//! Every handler defined with `#[interrupt]` reads a free-running timer on entry and on exit, and
+//! accumulates the difference, in timer ticks, per interrupt. `usage` reports the totals, e.g. to
+//! find the handler that eats the CPU or blows the latency budget of the others:
+//!
+//! ``` ignore
+//! use msp430_rt::isr_time;
+//!
+//! // Timer_A0 counting SMCLK cycles
+//! isr_time::start();
+//!
+//! // later
+//! for usage in isr_time::usage() {
+//!     // report `usage.name`, `usage.ticks`, `usage.count` and `usage.max`
+//! }
+//! isr_time::reset();
+//! ```
+//!
+//! The timer is the Timer_A whose `TAxCTL` register is at `__isr_timer`, Timer_A0 (`__timer_a0`)
+//! by default. `start` sets it up in continuous mode on SMCLK; a program that already runs a timer
+//! in continuous mode can point `__isr_timer` at it in `memory.x` instead and not call `start`.
+//! Timer_A0 is also the timer of the `startup-time` feature, which stops and clears it right
+//! before `main`; with that feature call `start` from `main`, not from the `#[pre_init]` function
+//! or a constructor.
+//!
+//! Each measurement wraps after 65536 ticks. The time of a nested handler, i.e. one that runs
+//! while a handler that re-enabled the interrupts is running, is also counted in the outer one.
+//! Naked handlers (`#[interrupt(naked)]`) aren't measured.
+
+use core::cell::UnsafeCell;
+use core::ptr;
+
+use msp430::interrupt;
+
+// Control bits of `TAxCTL`: SMCLK, continuous mode and clear
+const TASSEL_SMCLK: u16 = 2 << 8;
+const MC_CONTINUOUS: u16 = 2 << 4;
+const TACLR: u16 = 1 << 2;
+
+/// Offset of `TAxR` from `TAxCTL`
+const TAR: usize = 0x10;
+
+/// The accumulated execution time of an interrupt handler
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Usage {
+    /// Name of the interrupt
+    pub name: &'static str,
+    /// Timer ticks spent in the handler
+    pub ticks: u32,
+    /// Number of times the handler ran
+    pub count: u16,
+    /// Most ticks spent in a single run of the handler
+    pub max: u16,
+}
+
+// Used by the code generated by `#[interrupt]`
+#[doc(hidden)]
+pub struct Stats {
+    ticks: UnsafeCell<u32>,
+    count: UnsafeCell<u16>,
+    max: UnsafeCell<u16>,
+}
+
+// The counters are only accessed with the interrupts disabled
+unsafe impl Sync for Stats {}
+
+#[doc(hidden)]
+pub struct Entry {
+    name: &'static str,
+    stats: &'static Stats,
+}
+
+#[doc(hidden)]
+pub struct Guard {
+    stats: &'static Stats,
+    start: u16,
+}
+
+/// Returns the address of `TAxCTL`
+fn timer() -> *mut u16 {
+    extern "C" {
+        // Absolute symbol; its address is the address of the `TAxCTL` register
+        static __isr_timer: u8;
+    }
+
+    ptr::addr_of!(__isr_timer) as usize as *mut u16
+}
+
+#[inline(always)]
+fn now() -> u16 {
+    unsafe { ptr::read_volatile((timer() as usize + TAR) as *const u16) }
+}
+
+impl Stats {
+    #[doc(hidden)]
+    pub const fn new() -> Self {
+        Stats {
+            ticks: UnsafeCell::new(0),
+            count: UnsafeCell::new(0),
+            max: UnsafeCell::new(0),
+        }
+    }
+
+    #[doc(hidden)]
+    #[inline(always)]
+    pub fn start(&'static self) -> Guard {
+        Guard {
+            stats: self,
+            start: now(),
+        }
+    }
+}
+
+impl Entry {
+    #[doc(hidden)]
+    pub const fn new(name: &'static str, stats: &'static Stats) -> Self {
+        Entry { name, stats }
+    }
+}
+
+impl Drop for Guard {
+    #[inline(always)]
+    fn drop(&mut self) {
+        let elapsed = now().wrapping_sub(self.start);
+        // The handler may have re-enabled interrupts
+        interrupt::free(|_| unsafe {
+            let stats = self.stats;
+            *stats.ticks.get() = (*stats.ticks.get()).wrapping_add(u32::from(elapsed));
+            *stats.count.get() = (*stats.count.get()).wrapping_add(1);
+            if elapsed > *stats.max.get() {
+                *stats.max.get() = elapsed;
+            }
+        });
+    }
+}
+
+/// Returns the entries that `#[interrupt]` registered, one per handler
+fn entries() -> &'static [Entry] {
+    crate::linker_slice!(".isr_time", Entry)
+}
+
+/// Starts the timer at `__isr_timer` from 0, in continuous mode on SMCLK
+pub fn start() {
+    unsafe { ptr::write_volatile(timer(), TASSEL_SMCLK | MC_CONTINUOUS | TACLR) };
+}
+
+/// Returns the execution time of every handler defined with `#[interrupt]`
+pub fn usage() -> impl Iterator<Item = Usage> {
+    entries().iter().map(|entry| {
+        interrupt::free(|_| unsafe {
+            Usage {
+                name: entry.name,
+                ticks: *entry.stats.ticks.get(),
+                count: *entry.stats.count.get(),
+                max: *entry.stats.max.get(),
+            }
+        })
+    })
+}
+
+/// Clears the accumulated execution times
+pub fn reset() {
+    for entry in entries() {
+        interrupt::free(|_| unsafe {
+            *entry.stats.ticks.get() = 0;
+            *entry.stats.count.get() = 0;
+            *entry.stats.max.get() = 0;
+        });
+    }
+}
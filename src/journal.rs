@@ -0,0 +1,158 @@
+//! Power-loss-safe FRAM updates
+//!
+//! A `Journal` makes a group of word writes to non-volatile memory atomic: after a power failure
+//! either all the writes of a transaction have taken place, or none of them have.
+//!
+//! The journal works like a write-ahead log. The writes of a transaction are first recorded in
+//! the journal. On commit the journal is marked as committed, with a single word write, and the
+//! recorded writes are applied to their destinations. Finally the journal is marked as empty. If
+//! power fails before the commit mark is written the transaction is lost; if it fails after that
+//! the recorded writes are applied again by `recover`, which must be called early during boot.
+//!
+//! The journal, as well as the destinations of the writes, must be located in FRAM. The journal
+//! lifts the write protection of the `fram` module while it writes, but an MPU configured with
+//! `mpu_config!` must let it write to both. Use the `.persistent` section to place `static`
+//! variables in FRAM; unlike `.data` that section is only initialized when the device is
+//! programmed.
+//!
+//! ``` ignore
+//! use msp430_rt::journal::Journal;
+//!
+//! #[link_section = ".persistent"]
+//! static mut JOURNAL: Journal<4> = Journal::new();
+//!
+//! #[link_section = ".persistent"]
+//! static mut ENERGY: [u16; 2] = [0; 2];
+//!
+//! #[entry]
+//! fn main() -> ! {
+//!     let journal = unsafe { &mut JOURNAL };
+//!     journal.recover();
+//!
+//!     // ..
+//!
+//!     // Update both words of the counter, or neither
+//!     let mut tx = journal.begin();
+//!     unsafe {
+//!         tx.write(&mut ENERGY[0], low).unwrap();
+//!         tx.write(&mut ENERGY[1], high).unwrap();
+//!     }
+//!     tx.commit();
+//! }
+//! ```
+
+use core::ptr;
+
+use crate::fram;
+
+/// Value of the journal state while a transaction is being recorded or after it has been applied
+const EMPTY: u16 = 0xFFFF;
+
+/// Value of the journal state once a transaction has been committed
+const COMMITTED: u16 = 0xC0DE;
+
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct Entry {
+    addr: usize,
+    value: u16,
+}
+
+/// A write-ahead journal that holds up to `N` word writes
+#[repr(C)]
+pub struct Journal<const N: usize> {
+    state: u16,
+    len: u16,
+    entries: [Entry; N],
+}
+
+/// The transaction has more writes than the journal can hold
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Full;
+
+impl<const N: usize> Journal<N> {
+    /// Creates an empty journal
+    pub const fn new() -> Self {
+        Journal {
+            state: EMPTY,
+            len: 0,
+            entries: [Entry { addr: 0, value: 0 }; N],
+        }
+    }
+
+    /// Completes a transaction that was interrupted by a power failure after its commit
+    ///
+    /// Call this during boot, before reading any of the data updated through the journal.
+    pub fn recover(&mut self) {
+        if unsafe { ptr::read_volatile(&self.state) } == COMMITTED {
+            self.apply();
+        }
+    }
+
+    /// Starts a new transaction
+    pub fn begin(&mut self) -> Transaction<'_, N> {
+        fram::unprotected(|| unsafe {
+            ptr::write_volatile(&mut self.state, EMPTY);
+            ptr::write_volatile(&mut self.len, 0);
+        });
+
+        Transaction { journal: self }
+    }
+
+    fn apply(&mut self) {
+        let len = usize::from(unsafe { ptr::read_volatile(&self.len) });
+        fram::unprotected(|| {
+            // A corrupted length can't be trusted; don't write through garbage addresses
+            if len <= N {
+                for entry in &self.entries[..len] {
+                    let entry = unsafe { ptr::read_volatile(entry) };
+                    unsafe { ptr::write_volatile(entry.addr as *mut u16, entry.value) };
+                }
+            }
+
+            unsafe { ptr::write_volatile(&mut self.state, EMPTY) };
+        });
+    }
+}
+
+/// A transaction in progress
+///
+/// Dropping the transaction without calling `commit` discards its writes.
+pub struct Transaction<'a, const N: usize> {
+    journal: &'a mut Journal<N>,
+}
+
+impl<'a, const N: usize> Transaction<'a, N> {
+    /// Records a write of `value` to `dest`
+    ///
+    /// `dest` is not written until the transaction is committed.
+    ///
+    /// # Safety
+    ///
+    /// `dest` must stay valid for writes until the transaction has been applied, including by a
+    /// `recover` call after a reset.
+    pub unsafe fn write(&mut self, dest: *mut u16, value: u16) -> Result<(), Full> {
+        let len = ptr::read_volatile(&self.journal.len);
+        let index = usize::from(len);
+        if index == N {
+            return Err(Full);
+        }
+
+        let entry = Entry {
+            addr: dest as usize,
+            value,
+        };
+        fram::unprotected(|| {
+            ptr::write_volatile(&mut self.journal.entries[index], entry);
+            ptr::write_volatile(&mut self.journal.len, len + 1);
+        });
+
+        Ok(())
+    }
+
+    /// Commits the transaction and applies its writes
+    pub fn commit(self) {
+        fram::unprotected(|| unsafe { ptr::write_volatile(&mut self.journal.state, COMMITTED) });
+        self.journal.apply();
+    }
+}
@@ -15,10 +15,14 @@
 //!
 //! - Initializing `static` variables before the program entry point.
 //!
+//! - Calling the constructors of C and C++ code linked into the program.
+//!
 //! This crate also provides the following attributes:
 //!
 //! - `#[entry]` to declare the entry point of the program
 //! - `#[pre_init]` to run code *before* `static` variables are initialized
+//! - `#[ctor]` to run code after `static` variables are initialized, but before the entry point
+//! - `#[uninit]` to declare a `static` variable that isn't initialized at all
 //!
 //! This crate also implements a related attribute called `#[interrupt]`, which allows you
 //! to define interrupt handlers. However, since which interrupts are available depends on the
@@ -29,6 +33,24 @@
 //!
 //! # Requirements
 //!
+//! ## Toolchain
+//!
+//! A nightly toolchain is required. The application entry point is defined with `#[entry]` in a
+//! `#![no_main]` crate and the crate uses no lang items or `linkage`, but interrupt handlers use
+//! the `"msp430-interrupt"` ABI, which is still unstable: the crate itself and any crate that
+//! defines interrupt handlers need `#![feature(abi_msp430_interrupt)]`. The crate itself also
+//! uses `asm!`, in the `clock` and `checkpoint` modules. Handlers defined with
+//! `#[interrupt(naked)]` additionally need `#![feature(naked_functions)]`. The `msp430-none-elf`
+//! target has no pre-built `core` either, so it must be built with `-Z build-std=core` or Xargo.
+//!
+//! The crate also builds for other targets, so that `cargo check`, `cargo doc` and the unit tests
+//! of a workspace that contains firmware work on the host. There the startup code, the vector
+//! table and the default handlers are left out, the `"msp430-interrupt"` ABI becomes `"C"` (see
+//! `vectors::Handler`), and the functions that use inline assembly do nothing, e.g. `breakpoint`
+//! and `clock::delay_cycles`, or run on the current stack, e.g. `switch_stack`. The rest of the
+//! API compiles unchanged, but anything that touches the hardware must not be called on the
+//! host. The documentation on docs.rs is built for `msp430-none-elf`.
+//!
 //! ## `memory.x`
 //!
 //! This crate expects the user, or some other crate, to provide the memory layout of the target
@@ -52,6 +74,11 @@
 //! information on vector table layout and size. _You may be able to get more program space if
 //! your device's datasheet explicitly marks a contiguous set of vectors as unused!_
 //!
+//! `link.x` checks this layout when the program is linked: the three regions must not
+//! overlap, their origins (and the lengths of `RAM` and `VECTORS`) must be even, and `VECTORS`
+//! must end at address `0x10000`. Each violation is reported with its own `ERROR(msp430-rt)`
+//! message.
+//!
 //!
 //! ``` text
 //! /* Linker script for the MSP430G2553 */
@@ -72,9 +99,8 @@
 //! #![no_main]
 //! #![no_std]
 //!
-//! extern crate msp430_rt;
 //! // Simple panic handler that infinitely loops.
-//! extern crate panic_msp430;
+//! use panic_msp430 as _;
 //!
 //! use msp430_rt::entry;
 //!
@@ -113,6 +139,164 @@
 //! app: ELF 32-bit LSB executable, TI msp430, version 1 (embedded), statically linked, not stripped
 //! ```
 //!
+//! When the linker is `msp430-elf-gcc`, the default of the target, the build script runs it with
+//! `--version` and fails if it's missing or older than 8.3. Set `MSP430_RT_SKIP_LINKER_CHECK` to
+//! skip the check, e.g. to run `cargo check` without the toolchain.
+//!
+//! ### Linking without `msp430-elf-gcc`
+//!
+//! `link.x` doesn't depend on the GCC driver: it sets its own entry point (`ResetTrampoline`) and
+//! doesn't use the GCC startup files, which is what `-nostartfiles` tells the driver. The program
+//! can therefore also be linked by `rust-lld` or by `msp430-elf-ld` directly:
+//!
+//! ``` text
+//! $ cargo rustc --target msp430-none-elf -Z build-std=core -- \
+//!       -C linker=rust-lld -C linker-flavor=ld.lld -C link-arg=-Tlink.x
+//!
+//! $ cargo rustc --target msp430-none-elf -Z build-std=core -- \
+//!       -C linker=msp430-elf-ld -C linker-flavor=ld -C link-arg=-Tlink.x
+//! ```
+//!
+//! The GCC driver also links `libgcc` and the hardware multiplier libraries, which aren't searched
+//! for by a plain linker. The compiler intrinsics, e.g. `__mspabi_mpyi` and `memcpy`, must then be
+//! provided by the `compiler_builtins` crate (`-Z build-std-features=compiler-builtins-mem` for
+//! the memory functions), or `libgcc.a` must be passed to the linker explicitly. The linker
+//! script expects these symbols from the final program: `main` (defined by `#[entry]`),
+//! `__INTERRUPTS` and the handlers listed in `device.x` when the `device` feature is enabled, and
+//! the regions declared in `memory.x`.
+//!
+//! ### A/B slots
+//!
+//! Firmware that supports over-the-air updates often has two slots in flash, `A` and `B`, and
+//! runs from one while the other is updated. Both images can be built from the same crate and the
+//! same `memory.x`: instead of a `ROM` region, `memory.x` declares one region per slot, named
+//! `ROM_A` and `ROM_B`. The slot to build is then selected by setting the `MSP430_RT_SLOT`
+//! environment variable to `A` or `B`, which makes `ROM` an alias of the corresponding region.
+//! Slot images are usually started by a bootloader, so they are built with the `proxy-vectors`
+//! feature enabled and keep their vector table inside their slot.
+//!
+//! ``` text
+//! /* Two 7 KiB application slots on a MSP430G2553, after a 2 KiB bootloader */
+//! MEMORY
+//! {
+//!   RAM : ORIGIN = 0x0200, LENGTH = 0x0200
+//!   ROM_A : ORIGIN = 0xC800, LENGTH = 0x1C00
+//!   ROM_B : ORIGIN = 0xE400, LENGTH = 0x1BE0
+//!   /* The bootloader owns the hardware vector table at 0xFFE0 */
+//! }
+//! ```
+//!
+//! The `_slot` symbol is set to `0` when building slot `A`, and to `1` when building slot `B`.
+//!
+//! The `boot` module provides a firmware header for slot images (`firmware_header!`), and the
+//! pieces needed to select, at boot time, which slot to run and to confirm that an update works.
+//!
+//! The build script only reruns when `MSP430_RT_SLOT` changes, so use a different target directory
+//! for each slot to keep both images around:
+//!
+//! ``` text
+//! $ MSP430_RT_SLOT=A cargo build --release --target-dir target/slot-a
+//! $ MSP430_RT_SLOT=B cargo build --release --target-dir target/slot-b
+//! ```
+//!
+//! ### Image size budget
+//!
+//! The image can be given a size budget smaller than the `ROM` region, e.g. when the binary is
+//! distributed over the air to devices whose slots are smaller, by defining the
+//! `__image_size_max` symbol in `memory.x`. Linking fails if the image is larger than that.
+//!
+//! ``` text
+//! /* The image must fit in a 6 KiB update slot */
+//! __image_size_max = 0x1800;
+//! ```
+//!
+//! The image size, available through the `__image_size` symbol, spans from `ORIGIN(ROM)` to the
+//! end of the image signature, so it includes the vector table (when it's placed in `ROM`),
+//! `.text`, `.rodata`, the `.data` initializers, the stored CRC and the signature. The firmware
+//! header is not included, it's always placed at the end of `ROM`.
+//!
+//! ### Stack and heap sizes
+//!
+//! By default the stack grows down from the end of `RAM` without any space being reserved for it,
+//! and the heap has no size. Reserving space with the `_stack_size` and `_heap_size` symbols makes
+//! linking fail when `.bss`, `.data`, the heap and the stack don't fit in `RAM` together. The heap
+//! then spans from `_sheap` to `_eheap`.
+//!
+//! These sizes, as well as `__image_size_max`, can also be set from Rust source with
+//! `runtime_config!`, which keeps the common configuration out of `memory.x`:
+//!
+//! ``` ignore
+//! msp430_rt::runtime_config! {
+//!     stack_size: 256,
+//!     heap_size: 128,
+//!     image_size_max: 0x1800,
+//! }
+//! ```
+//!
+//! With the `auto-heap` feature the heap instead takes all the RAM that `.bss`, `.data` and the
+//! stack reserved with `_stack_size` leave, so its size doesn't have to be re-tuned whenever the
+//! statics grow or shrink; see that feature.
+//!
+//! ### Clock frequency
+//!
+//! The frequency of MCLK, in kHz, is declared with the `mclk_khz` setting of `runtime_config!` or
+//! the `__mclk_khz` symbol, and defaults to 1000, the frequency after a reset. The `clock` module
+//! reads it back with `mclk_khz` and derives `delay_us` and `delay_ms` from it. It's a link-time
+//! value, so it's not available as a `const`; the program still has to configure the clock
+//! system to match.
+//!
+//! # MCU selection
+//!
+//! Setting the `MSP430_MCU` environment variable to the part number of the target device, e.g.
+//! `msp430g2553` or `MSP430FR5969`, configures the defaults of its family at build time:
+//!
+//! - the number of vectors used by the `proxy-vectors` and `ram-vectors` features, unless
+//! `MSP430_RT_PROXY_VECTORS` or `MSP430_RT_RAM_VECTORS` is set. This is the number of vectors
+//! of the whole vector table area of the family (16 on F1xx, F4xx and G2xx devices, 32 on F2xx
+//! devices, 64 on F5xx/F6xx devices, 60 on FR2xx/FR4xx devices and 56 on FR5xx/FR6xx devices),
+//! which must match the size of the vector table linked into the application.
+//!
+//! - the `__info_start`, `__info_end` and `__info_segment_size` symbols, which describe the
+//! information memory (on FRAM devices the whole information memory is one segment).
+//!
+//! - the `__crc16`, `__syscfg0` and `__wdtctl` addresses used by the `crc-hw` feature and the
+//! `fram` and `watchdog` modules.
+//!
+//! All of these are weak defaults that `memory.x` can override. The crate is also compiled with
+//! the `msp430_family` cfg set to the family (`"f1"`, `"f2"`, `"f4"`, `"f5"`, `"f6"`, `"g2"`,
+//! `"fr2"`, `"fr4"`, `"fr5"` or `"fr6"`), plus `msp430x` on devices with the MSP430X CPU and
+//! `msp430_fram` on FRAM devices, which lets it reject features the device doesn't support.
+//!
+//! For the following parts the build script also generates `memory.x`, so it doesn't need to be
+//! written by hand: MSP430G2231, MSP430G2452, MSP430G2553, MSP430F5529, MSP430FR2433, MSP430FR5969
+//! and MSP430FR6989. The `ROM` region covers the program memory below the vector table, up to
+//! 64 KiB. A `memory.x` in the root of the application takes precedence over the generated one,
+//! e.g. to reserve memory for a bootloader. For other parts `memory.x` must still be provided.
+//!
+//! These parts can also be selected with a Cargo feature instead of `MSP430_MCU`, which is
+//! convenient for simple projects that then need no linker configuration at all:
+//!
+//! ``` toml
+//! [dependencies.msp430-rt]
+//! version = "0.2.2"
+//! features = ["mcu-msp430g2553"]
+//! ```
+//!
+//! At most one `mcu-*` feature can be enabled, and `MSP430_MCU`, if set, must name the same part.
+//!
+//! For other parts, the `msp430-rt-build` crate can derive `memory.x` from the linker command file
+//! that TI provides for the device (`lnk_<device>.cmd`, shipped with CCS and MSP430-GCC) in the
+//! build script of the application.
+//!
+//! The `board-msp-exp430g2et`, `board-msp-exp430fr5969` and `board-msp-exp430fr2433` features
+//! select the MCU of the corresponding LaunchPad and enable the `board` module, which gives the
+//! pins of its LEDs and backchannel UART. On the MSP-EXP430FR2433 the `uart-updater` feature then
+//! defaults to the backchannel UART (eUSCI_A0); on the MSP-EXP430FR5969 that's already the case.
+//!
+//! ``` text
+//! $ MSP430_MCU=msp430fr5969 cargo build --release
+//! ```
+//!
 //! # Optional features
 //!
 //! ## `device`
@@ -128,6 +312,494 @@
 //! conjunction with PAC crates generated using `svd2rust`. Those *PAC crates* will populate the
 //! missing part of the vector table when their `"rt"` feature is enabled.
 //!
+//! This feature also enables the `irq` module, which masks and unmasks interrupts by name using
+//! a mapping from the device's interrupts to their enable bits.
+//!
+//! ## `jtag-lock`
+//!
+//! Enables the `jtag_signature!` macro. Because locking the debug interface can't be undone on most
+//! devices, the build script prints a warning on every build that has this feature enabled.
+//!
+//! ## `proxy-vectors`
+//!
+//! Use this feature to build an application that is started by a bootloader. The bootloader owns
+//! the hardware vector table, so the application can live at any offset in flash.
+//!
+//! When this feature is enabled `memory.x` must not declare a `VECTORS` region; instead `VECTORS`
+//! becomes an alias of `ROM`. A *proxy vector table* is placed in the `.proxy_vectors` section at
+//! the very beginning of the `ROM` region, followed by the application's copy of the vector table.
+//! Entry `n` of the proxy vector table is a 4-byte `br &vector` instruction that jumps through
+//! entry `n` of the application's vector table. The last entry corresponds to the reset vector.
+//!
+//! The bootloader dispatches interrupt `n` to the application by setting its hardware vector `n`
+//! to `ORIGIN(ROM) + 4 * n` (using the application's `ROM` region), and starts the application by
+//! jumping to the last proxy vector.
+//!
+//! The number of proxy vectors must be provided through the `MSP430_RT_PROXY_VECTORS` environment
+//! variable at build time. It must be equal to the number of vectors of the device, including the
+//! reset vector.
+//!
+//! ``` text
+//! $ cat memory.x
+//! /* MSP430G2553 application that lives after a 1 KiB bootloader */
+//! MEMORY
+//! {
+//!   RAM : ORIGIN = 0x0200, LENGTH = 0x0200
+//!   ROM : ORIGIN = 0xC400, LENGTH = 0x3BE0
+//! }
+//!
+//! $ MSP430_RT_PROXY_VECTORS=16 cargo build --release
+//! ```
+//!
+//! ## `entry-stub`
+//!
+//! Places a 4-byte `br #ResetTrampoline` instruction, the *entry stub*, in the `.text.start`
+//! section at the very beginning of the `ROM` region. Jumping to `ORIGIN(ROM)` is then equivalent
+//! to a reset, regardless of where the linker places the reset handler, so bootloaders and
+//! factory programmers can rely on a constant entry address across firmware versions. When
+//! building A/B slots each slot starts with its own entry stub.
+//!
+//! This feature can't be combined with `proxy-vectors`; with that feature the last proxy vector
+//! already serves as a position-stable entry point.
+//!
+//! ## `ram-image`
+//!
+//! Builds an application that runs entirely from RAM, e.g. one loaded by a bootloader or a
+//! debugger. This feature implies `ram-vectors`.
+//!
+//! When this feature is enabled `memory.x` only declares the `RAM` region, and both `ROM` and
+//! `VECTORS` become aliases of it. The dispatch stubs of `ram-vectors`, the vector table, `.text`
+//! and `.rodata` are placed at the beginning of `RAM`, followed by `.bss`, the RAM vector table,
+//! `.data` and the heap. The stack grows down from the end of `RAM`, as usual. The loader is
+//! expected to place `.data` at its final address, so the reset handler only zeroes `.bss`.
+//!
+//! Interrupts reach the application through its dispatch stubs, which jump through the RAM vector
+//! table: the loader's vector `n` must jump to the address in the `n`-th word of the
+//! application's `.vector_table`, i.e. `ADDR(.ram_dispatch) + 4 * n`. `MSP430_RT_RAM_VECTORS`
+//! sets the number of vectors, as with `ram-vectors`. The application is started by jumping to
+//! the address in the last word of `.vector_table`, or to the entry point of the ELF file
+//! (`ResetTrampoline`). Handlers can be changed at runtime with `vectors::set_handler`.
+//!
+//! ``` text
+//! $ cat memory.x
+//! /* MSP430FR5969 application that is loaded in RAM */
+//! MEMORY
+//! {
+//!   RAM : ORIGIN = 0x1C00, LENGTH = 0x0800
+//! }
+//!
+//! $ MSP430_RT_RAM_VECTORS=56 cargo build --release --features msp430-rt/ram-image
+//! ```
+//!
+//! ## `image-crc`
+//!
+//! Verifies the integrity of the program image before calling the entry point. The reset handler
+//! computes the CRC-16-CCITT (see the `crc` module) of the bytes in the range `__image_crc_start`
+//! .. `__image_crc_end` and compares it against the word stored in the `.image_crc` section by the
+//! `__IMAGE_CRC` symbol. By default the range spans from `ORIGIN(ROM)` to the end of the `.data`
+//! initializer image, i.e. everything `msp430-rt` places in `ROM`; either symbol can be overridden
+//! in `memory.x`:
+//!
+//! ``` text
+//! /* Only check the first 8 KiB of ROM */
+//! __image_crc_start = 0xC000;
+//! __image_crc_end = 0xE000;
+//! ```
+//!
+//! The stored CRC is initialized to `0xFFFF`; it must be patched in the final binary with the
+//! actual CRC of the image, or every boot ends in `ImageCrcMismatch`. `patch_crcs_in_place` of the
+//! `msp430-rt-build` crate does that to the linked ELF file; run it after every link, e.g. from
+//! the cargo runner, before flashing the image (see the documentation of `msp430-rt-build`).
+//!
+//! On mismatch the function `ImageCrcMismatch` is called with the expected and the computed CRCs.
+//! The default implementation is an infinite loop. It can be overridden to, e.g., stay in a
+//! bootloader or enter a safe mode:
+//!
+//! ``` ignore
+//! #[no_mangle]
+//! extern "C" fn ImageCrcMismatch(expected: u16, computed: u16) -> ! {
+//!     // ..
+//! }
+//! ```
+//!
+//! The check runs after RAM has been initialized, so `ImageCrcMismatch` may access `static`
+//! variables. Verifying a large image takes a while; stop or service the watchdog in the
+//! `#[pre_init]` function if needed, or enable the `crc-hw` feature. This feature can't be
+//! combined with `data-in-info`.
+//!
+//! ## `image-signature`
+//!
+//! Verifies the authenticity of the program image before calling the entry point, using a
+//! signature scheme chosen by the application (e.g. Ed25519 or an HMAC). The image range is the
+//! same as the one of the `image-crc` feature, `__image_crc_start` .. `__image_crc_end`, and
+//! the check runs after the CRC check, if any. Like `image-crc`, this feature can't be combined
+//! with `data-in-info`.
+//!
+//! Space for the signature is reserved with the `image_signature!` macro, which places it in the
+//! `.image_signature` section, right after the image. Like the stored CRC the signature must be
+//! patched in the final binary by a post-build host tool.
+//!
+//! The verification itself is done by a function named `VerifySignature` that the application
+//! must provide; linking fails otherwise. An empty signature, i.e. a missing `image_signature!`,
+//! is always rejected.
+//!
+//! ``` ignore
+//! #[export_name = "VerifySignature"]
+//! fn verify(image: &[u8], signature: &[u8]) -> bool {
+//!     // e.g. check an Ed25519 signature against a public key stored in the bootloader
+//! }
+//! ```
+//!
+//! On failure the function `ImageSignatureInvalid` is called. Like `ImageCrcMismatch` it defaults
+//! to an infinite loop and can be overridden:
+//!
+//! ``` ignore
+//! #[no_mangle]
+//! extern "C" fn ImageSignatureInvalid() -> ! {
+//!     // ..
+//! }
+//! ```
+//!
+//! When the `boot-selector` feature is enabled as well `boot::validate` also checks the
+//! signature of the slot images, which is located through their firmware header.
+//!
+//! ## `vector-crc`
+//!
+//! Verifies the integrity of the vector table before calling the entry point, to catch flash or
+//! FRAM corruption that would otherwise send interrupts into the weeds. The reset handler
+//! computes the CRC-16-CCITT of the `.vector_table` section and compares it against the word
+//! stored in the `.image_crc` section by the `__VECTOR_CRC` symbol. Like the stored image CRC it
+//! is initialized to `0xFFFF` and must be patched in the final binary; `patch_crcs_in_place` of
+//! the `msp430-rt-build` crate stores both. `vectors::install` stores the CRC of the updated
+//! vector table.
+//!
+//! The check can also be run periodically, e.g. from a timer interrupt, by calling
+//! `crc::verify_vector_table`.
+//!
+//! On mismatch the function `VectorTableCorrupted` is called with the expected and the computed
+//! CRCs. It defaults to an infinite loop and can be overridden to enter a safe mode:
+//!
+//! ``` ignore
+//! #[no_mangle]
+//! extern "C" fn VectorTableCorrupted(expected: u16, computed: u16) -> ! {
+//!     // ..
+//! }
+//! ```
+//!
+//! ## `fram-vectors`
+//!
+//! Enables `vectors::install`, which replaces the handler of an interrupt at runtime by rewriting
+//! the vector table, e.g. for in-field patching or to switch between operating modes. Only enable
+//! this feature on FRAM devices. See the `vectors` module for details on write protection.
+//!
+//! ## `fram-write-protect`
+//!
+//! Makes the reset handler write protect the program and the information FRAM of FR2xx/FR4xx
+//! devices, with the `PFWP` and `DFWP` bits of `SYSCFG0`, once `.data` has been initialized and
+//! the constructors have run. Writes to FRAM must then go through `fram::unprotected`; see the
+//! `fram` module.
+//!
+//! ## `fast-boot`
+//!
+//! Strips the reset handler down to the setup of the stack pointer, the initialization of `.bss`
+//! and `.data`, and the features that are enabled explicitly, for intermittently powered devices
+//! that boot thousands of times a day: the `#[pre_init]` function and `_system_pre_init` aren't
+//! called, and the constructors aren't run, which the linker checks. The trampoline that sets the
+//! stack pointer is two instructions long and directly followed by the reset handler.
+//!
+//! The linker also checks that the estimated number of cycles from the reset to `main`,
+//! `__boot_cycles`, is at most `_boot_cycles_max`, 1000 by default, i.e. 1 ms with the default
+//! MCLK of 1 MHz. The estimate assumes 8 cycles per word of `.bss` and 10 per word of `.data`,
+//! plus 50 cycles, and doesn't cover the other features. Variables in `.user_ram` aren't
+//! initialized, so they don't count. Set a budget of your own in `memory.x`:
+//!
+//! ``` text
+//! _boot_cycles_max = 400;
+//! ```
+//!
+//! ## `ram-test`
+//!
+//! Makes the reset handler run a March C- test on `.bss`, the heap and the free stack, right after
+//! the `#[pre_init]` function and `_system_pre_init` and before the RAM is initialized, for
+//! products with power-on self-test requirements. `.user_ram` is not tested, so its contents are
+//! kept. Neither is the rest of the RAM when `_system_pre_init` returns 0 to keep it across the
+//! reset. The function `RamTestFailed` is called with the address of the first faulty word and
+//! never returns; the default loops forever. Stop the watchdog in the `#[pre_init]` function, since
+//! the test can take longer than its default interval.
+//!
+//! ## `ram-vectors`
+//!
+//! Routes every interrupt through a vector table in RAM, so that handlers can be changed at
+//! runtime with `vectors::set_handler` on devices whose vector table can't be rewritten. See the
+//! `vectors` module for details. This feature can't be combined with `proxy-vectors`.
+//!
+//! ## `readonly-text`
+//!
+//! Makes the code non-writable at runtime on FRAM devices, with the FRAM write protection of
+//! FR2xx/FR4xx devices or the MPU of FR5xx/FR6xx devices, right before the program entry point.
+//! `MSP430_MCU` must be set. Writes to FRAM must then go through `fram::unprotected`, which the
+//! `journal` and `flash` modules and `vectors::install` already do; see the `fram` module. This
+//! feature can't be combined with `mpu` or `ram-image`.
+//!
+//! ## `reset-unused-vectors`
+//!
+//! Points the interrupts that have no handler, i.e. those bound to `DefaultHandler`, to a stub that
+//! resets the device (see `reset::software_reset`) instead of the default handler, which loops
+//! forever. A spurious or unexpected interrupt then restarts the application from scratch, with the
+//! peripherals back in their reset state. Defining `DefaultHandler` with `#[interrupt]` takes
+//! precedence over this feature. It's the same as the `reset` policy of
+//! `MSP430_RT_SPURIOUS_INTERRUPTS` (see "Spurious interrupts"), which can't select another policy
+//! when this feature is enabled.
+//!
+//! ## `stack-guard`
+//!
+//! Makes the reset handler paint a guard region below the stack, and enables `check_stack_guard`,
+//! which calls `StackGuardCorrupted` if the stack has overflowed into it. Safety-oriented builds
+//! call it periodically, e.g. from a timer interrupt. The size of the guard is given by the
+//! `_stack_guard_size` symbol, 8 bytes by default; see `check_stack_guard`.
+//!
+//! ## `startup-time`
+//!
+//! Makes the reset handler measure the time it takes to get to `main` with Timer_A0, and enables
+//! `startup_cycles`, which returns it; see that function.
+//!
+//! ## `strict-vectors`
+//!
+//! Makes release builds (`--release`) fail to link if any interrupt is still bound to
+//! `DefaultHandler`, so that shipping firmware can't leave an interrupt without a handler. The
+//! linker then reports an undefined `ERROR_msp430_rt_strict_vectors_interrupt_without_handler`
+//! symbol; the interrupts that don't exist on the device must be marked as reserved (0) by the
+//! device crate. Debug builds are unaffected. Defining `DefaultHandler` with `#[interrupt]`
+//! satisfies this check, as every interrupt then has a deliberate handler. This feature requires
+//! the `device` feature.
+//!
+//! ## `isr-depth`
+//!
+//! Makes the handlers defined with `#[interrupt]` track how deeply interrupt handlers are nested,
+//! and enables the `isr` module, whose `in_isr` and `isr_depth` functions report it. This costs a
+//! few cycles on entry to and exit from every handler. The code generated by `#[interrupt]` then
+//! refers to the `msp430_rt` crate, so crates that define interrupt handlers must depend on it
+//! directly.
+//!
+//! ## `isr-time`
+//!
+//! Makes the handlers defined with `#[interrupt]` accumulate the time they run for, measured with
+//! a free-running Timer_A, and enables the `isr_time` module, whose `usage` reports it per
+//! interrupt. Like `isr-depth`, this costs a few cycles per handler, and crates that define
+//! interrupt handlers must depend on `msp430_rt` directly.
+//!
+//! ## `boot-selector`
+//!
+//! Enables `boot::select`, which boots the preferred valid A/B slot. This feature is meant to be
+//! enabled by the bootloader, whose `memory.x` must then declare the `ROM_A` and `ROM_B` regions
+//! used by the application slots (in addition to its own `ROM` region).
+//!
+//! ## `uart-console`
+//!
+//! Enables the `console` module, a polled, transmit-only driver for the USCI_A0 or eUSCI_A0 UART
+//! configured through `memory.x` symbols, for printing e.g. panic messages without a driver of
+//! one's own. The default `AssertFailed` prints failed assertions to it.
+//!
+//! ## `uart-updater`
+//!
+//! Includes a minimal recovery updater that receives a new image over a UART, using XMODEM, and
+//! writes it into the inactive A/B slot. See the `updater` module for details. This feature
+//! implies `boot-selector`. The updater writes the slot like RAM and drives an eUSCI_A module, so
+//! it only supports FRAM devices; the build fails for flash devices.
+//!
+//! ## `boot-markers`
+//!
+//! Makes the reset handler toggle a pin at three points of the startup: on entry, once `.bss` and
+//! `.data` have been initialized, and right before `main`. With a power analyzer or EnergyTrace
+//! the energy spent in each phase of the boot can then be told apart. The pin is P1.0 by default;
+//! set `__boot_marker_out` and `__boot_marker_dir`, the addresses of its `PxOUT` and `PxDIR`
+//! registers, and `__boot_marker_mask`, its bit, in `memory.x` to use another one. The pin is made
+//! an output on the first toggle. On devices with LPMx.5, e.g. FRAM devices, the pins are locked
+//! after a reset until `LOCKLPM5` is cleared; clear it in the `#[pre_init]` function so that the
+//! last two markers show up.
+//!
+//! ## `c-vectors`
+//!
+//! Lets C interrupt handlers declared with `__attribute__((interrupt(N)))` populate the vector
+//! table next to the Rust ones, e.g. while porting a C program to Rust one driver at a time.
+//! msp430-elf-gcc places a pointer to such a handler in the `__interrupt_vector_N` section, where
+//! `N` is the vector number of the device header (`PORT1_VECTOR`, etc.), starting at 1 for the
+//! first vector of the table. A C handler takes precedence over the Rust handler of the same
+//! vector, which is usually `DefaultHandler`.
+//!
+//! Every hardware vector, except the reset vector, then points to a stub in `ROM` that jumps
+//! through the C vector or the Rust vector, which costs 4 bytes of `ROM` per vector and a few
+//! cycles of latency per interrupt. The number of vectors of the device, including the reset
+//! vector, must be provided through the `MSP430_RT_C_VECTORS` environment variable, or
+//! `MSP430_MCU`. This feature can't be combined with `ram-vectors`.
+//!
+//! ``` text
+//! $ MSP430_RT_C_VECTORS=16 cargo build --release
+//! ```
+//!
+//! ## `crc-hw`
+//!
+//! Computes CRCs (see the `crc` module), including the one of the `image-crc` feature, using the
+//! CRC16 peripheral instead of software. Only enable this feature on devices that have the
+//! peripheral. Its base address is given by the `__crc16` symbol, which defaults to `0x0150`
+//! (F5xx/F6xx and FR5xx/FR6xx families). On FR2xx/FR4xx devices add this line to `memory.x`:
+//!
+//! ``` text
+//! __crc16 = 0x01C0;
+//! ```
+//!
+//! ## `c-api`
+//!
+//! Exports the services of the runtime to C code as `extern "C"` functions, declared in the
+//! generated header `msp430_rt.h`: `msp430_rt_software_reset`, `msp430_rt_heap_start`,
+//! `msp430_rt_heap_end` and `msp430_rt_exit`, plus the functions of the enabled features:
+//!
+//! - `isr-depth`: `msp430_rt_in_isr` and `msp430_rt_isr_depth`.
+//! - `newlib-syscalls`: `msp430_rt_check_heap_collision`.
+//! - `reset-counters`: `msp430_rt_reset_count`, `msp430_rt_reset_last_cause` and
+//! `msp430_rt_reset_clear_counts`, with the causes as `MSP430_RT_RESET_*` constants.
+//! - `blackbox`: `msp430_rt_blackbox_record`, `_seal`, `_is_sealed`, `_clear` and `_events`.
+//!
+//! The build scripts of dependent crates find the header in the directory given by the
+//! `DEP_MSP430_RT_INCLUDE` environment variable. The nesting depth only accounts for the handlers
+//! defined with `#[interrupt]`.
+//!
+//! ## `c-main`
+//!
+//! Makes the reset handler call the C function `int app_main(void)` instead of the Rust entry
+//! point, for programs whose application is still written in C but that use the startup code and
+//! the diagnostics of `msp430-rt`. `#[entry]` isn't needed then. Another function can be called by
+//! defining the `__c_main` symbol in `memory.x`:
+//!
+//! ``` text
+//! __c_main = application_main;
+//! ```
+//!
+//! If the function returns its value is passed to `OnExit` and the program then ends with
+//! `exit`, like an `#[entry]` function that returns (see `Termination`).
+//!
+//! ## `ipe`
+//!
+//! Sets up the IP Encapsulation of FR5xx/FR6xx devices: the functions and `static` variables
+//! marked with `#[ipe]` are placed in a 1 KiB aligned region of `ROM`, together with the IPE init
+//! structure, and the IPE signature at 0xFF88 points the bootcode to that structure. After the
+//! next BOR only the code in the region can access it. See "IP Encapsulation".
+//!
+//! ## `lpm5-io`
+//!
+//! Enables the `lpm5` module, which records the configuration of the I/O ports in FRAM before the
+//! device enters LPM3.5 or LPM4.5, and makes the reset handler restore it and unlock the pins on
+//! the wakeup, before the `#[pre_init]` function.
+//!
+//! ## `debugger-delay`
+//!
+//! Makes the reset handler wait for a debugger to attach, before the `#[pre_init]` function, when
+//! the program requested it before the reset or when a pin is high; see the `debugger` module.
+//!
+//! ## `production-test`
+//!
+//! Makes the reset handler divert to `ProductionTest`, instead of running the constructors and
+//! `main`, when a flag in FRAM or a pin selects the production test mode, so that manufacturing
+//! test firmware can be part of the production image; see the `production` module.
+//!
+//! ## `mem-intrinsics`
+//!
+//! Exports `memcpy`, `memmove`, `memset` and `memcmp` implementations that copy, fill and compare
+//! a word at a time when the operands are aligned, instead of the byte-wise ones of
+//! `compiler_builtins` or `libgcc`. This speeds up the initialization of `.data` at startup as
+//! well as the copies done by the application. The functions of `compiler_builtins` are weak, so
+//! they are replaced even with `-Z build-std-features=compiler-builtins-mem`. `libc` and `libgcc`
+//! are searched last by `msp430-elf-gcc`, so theirs are not linked in either.
+//!
+//! ## `mpu`
+//!
+//! Makes the reset handler configure the memory protection unit of FR5xx/FR6xx devices, first
+//! thing after a reset, with the configuration declared by `mpu_config!`; see the `mpu` module.
+//! Violations that raise an NMI are handled by the runtime's `SYSNMI` handler, which passes them
+//! to `MpuViolation`.
+//!
+//! ## `newlib-syscalls`
+//!
+//! Provides the system calls that newlib, the C library of msp430-elf-gcc, needs: `_write`,
+//! `_read`, `_exit` and `_sbrk`, so that C code that uses e.g. `printf` or `malloc` links without
+//! `libnosys`. `_sbrk` allocates from the heap reserved with `runtime_config!` or `_heap_size`;
+//! `_write` discards its output unless the program defines its own `_write`. The definitions of the
+//! program, or of a library that's linked in, take precedence over these.
+//!
+//! `_sbrk` also checks that the stack hasn't grown into the heap, and enables
+//! `check_heap_collision`, which does the same check from e.g. the idle loop. On a collision it
+//! calls `HeapCollision` instead of letting the stack and `malloc` overwrite each other's data.
+//!
+//! ## `rom-driverlib`
+//!
+//! Enables the `rom` module, which calls the routines of TI's DriverLib located in the ROM of
+//! some devices, e.g. FR2xx/FR4xx parts, instead of a copy linked into the program. The addresses
+//! of the routines that are called must be defined in `memory.x` (see the module documentation).
+//!
+//! ## `blackbox`
+//!
+//! Enables the `blackbox` module, a log of the last events recorded by the program, kept in FRAM
+//! across resets. The default interrupt handler seals the log, so that the events that led to an
+//! unexpected interrupt can be read back after the device has been reset.
+//!
+//! ## `checkpoint`
+//!
+//! Enables the `checkpoint` module, which saves the state of the program to FRAM, e.g. when the
+//! supply of an energy harvesting device is about to fail, and makes the reset handler resume from
+//! the saved state after the next reset. The snapshot is reserved in `ROM`, as a section that
+//! isn't loaded, and takes as much space as `RAM`.
+//!
+//! ## `reset-counters`
+//!
+//! Makes the reset handler count the resets of each cause reported by the `SYSRSTIV` register of
+//! FRAM, F5xx and F6xx devices, e.g. brownouts, watchdog timeouts or software resets, so that the
+//! counts survive power cycles. The reset handler does that right after a reset, before the
+//! `#[pre_init]` function; `reset::count`, `reset::last_cause` and `reset::clear_counts` read and
+//! clear the counters.
+//!
+//! FRAM devices keep the counters in `.persistent`. Flash devices keep them in a segment of the
+//! `INFO` region of `memory.x` of their own, which the generated `memory.x` defines, and erase and
+//! program it at every reset, with the watchdog held. The flash endures a limited number of erase
+//! cycles, typically 100,000, and a power failure during the update loses the counts. The build
+//! fails if the `memory.x` that is linked doesn't declare `INFO`.
+//!
+//! ## `sleep`
+//!
+//! Enables the `sleep` module, which enters the deepest low-power mode that the clocks held by
+//! drivers allow until an interrupt handler calls `sleep::wake`. The handlers defined with
+//! `#[interrupt]` then go through a stub that wakes the device up on return when asked to.
+//!
+//! ## `auto-heap`
+//!
+//! Makes the linker size the heap: `_eheap` becomes `_stack_start - _stack_size`, rounded down to a
+//! word, so that the heap spans all the RAM between the end of `.bss` and `.data` and the stack,
+//! and `_heap_size` is computed from it rather than set. Linking fails if the statics and
+//! `_stack_size` leave no RAM, or if `runtime_config!` also sets `heap_size`. Reserve the stack
+//! with `_stack_size`, as the heap otherwise reaches up to the initial stack pointer.
+//! `memory::HEAP_SIZE` isn't available with this feature.
+//!
+//! ## `data-in-info`
+//!
+//! Stores the initial values of `.data`, including the code of the `flash` module that runs from
+//! RAM, in the information memory instead of `ROM`, which frees that much program memory on the
+//! smallest parts. The reset handler copies them from there as usual. `memory.x` must declare an
+//! `INFO` region:
+//!
+//! ``` text
+//! MEMORY
+//! {
+//!   /* .. */
+//!   /* Segments D to B; INFOA holds the calibration data of the clocks */
+//!   INFO : ORIGIN = 0x1000, LENGTH = 0x00C0
+//! }
+//! ```
+//!
+//! The `memory.x` generated for a known part declares it, without INFOA on flash devices, and the
+//! build fails if the `memory.x` that is linked doesn't. The program must not erase the segments
+//! of `INFO`, e.g. with the `flash` module. This feature can't be combined with `ram-image`, nor
+//! with `image-crc` and `image-signature`, whose single range of `ROM` wouldn't cover the initial
+//! values.
+//!
 //! # Inspection
 //!
 //! This section covers how to inspect a binary that builds on top of `msp430-rt`.
@@ -172,7 +844,8 @@
 //! your application code. Some other times `main` gets inlined into `Reset` and you won't find it.
 //!
 //! - `DefaultHandler`. This is the default interrupt handler. If not overridden using `#[interrupt]
-//! fn DefaultHandler(..` this will be an infinite loop.
+//! fn DefaultHandler(..` this will be an infinite loop, or the handler of the policy selected
+//! with `MSP430_RT_SPURIOUS_INTERRUPTS` (see "Spurious interrupts").
 //!
 //! - `__RESET_VECTOR`. This is the reset vector, a pointer into `ResetTrampoline`. This vector is
 //! located at the end of the `.vector_table` section.
@@ -185,9 +858,42 @@
 //! function is not optimized out by default, but if an empty function is marked with the
 //! `#[pre_init]` attribute then the function call will be optimized out.
 //!
+//! - `_system_pre_init`. The pre-initialization hook of TI's C runtime, called right after
+//! `PreInit`. It defaults to a function that returns 1; see "Startup code written for TI
+//! toolchains".
+//!
+//! The defaults of the other functions that never return, e.g. `ImageCrcMismatch` or
+//! `RamTestFailed`, disable the interrupts and loop forever, like the default `DefaultHandler`.
+//!
 //! If you overrode any interrupt handler you'll find it as an unmangled symbol, e.g. `NMI` or
 //! `WDT`, in the output of `objdump`,
 //!
+//! ## Image range symbols
+//!
+//! The linker script also defines the following absolute symbols, meant for host tools that
+//! compute or patch checksums and signatures after linking:
+//!
+//! - `_simage` and `_eimage`. The start and end (exclusive) of the image range, i.e. exactly the
+//! bytes the `image-crc` and `image-signature` features verify at boot. These are equal to
+//! `__image_crc_start` and `__image_crc_end`, so they follow any override made in `memory.x`.
+//!
+//! - `_eidata`. The end of the `.data` initializers in `ROM`, which is the end of everything
+//! `msp430-rt` places in `ROM` besides the stored CRC, the image signature and the firmware header.
+//! `_eimage` defaults to this address.
+//!
+//! - `__IMAGE_CRC`, `__image_signature_start` and `__image_signature_end`. The location of the
+//! stored CRC and of the image signature, which are placed right after the image.
+//!
+//! - `__VECTOR_CRC`, `__vector_table_start` and `__vector_table_end`. The location of the stored
+//! CRC of the vector table (see the `vector-crc` feature) and the range it covers.
+//!
+//! ``` text
+//! $ msp430-elf-nm app | grep -E ' (_simage|_eimage|__IMAGE_CRC)$'
+//! 0000c19a R __IMAGE_CRC
+//! 0000c19a A _eimage
+//! 0000c000 A _simage
+//! ```
+//!
 //! # Advanced usage
 //!
 //! ## Setting the program entry point
@@ -232,6 +938,9 @@
 //! This `static` variable will be placed at `ORIGIN(VECTORS)`. This address corresponds to the
 //! spot where IRQ0 (IRQ number 0) is located.
 //!
+//! `link.x` refers to `__INTERRUPTS` with `EXTERN` and `KEEP`s its section, so the variable doesn't
+//! need `#[used]` to survive `--gc-sections`. The same holds for `__RESET_VECTOR`.
+//!
 //! To conform to the MSP430 ABI `__INTERRUPTS` must be an array of function pointers; some spots
 //! in this array may need to be set to 0 if they are marked as *reserved* in the data sheet /
 //! reference manual. We recommend using a `union` to set the reserved spots to `0`; `None`
@@ -282,6 +991,23 @@
 //! ];
 //! ```
 //!
+//! `vectors::VectorTable` builds the same array without a macro or a hand written list:
+//!
+//! ``` ignore
+//! use msp430_rt::vectors::{Vector, VectorTable};
+//!
+//! #[link_section = ".vector_table.interrupts"]
+//! #[no_mangle]
+//! static __INTERRUPTS: [Vector; 15] = VectorTable::new()
+//!     .reserved(0)
+//!     .reserved(1)
+//!     .with(2, Foo)
+//!     .with(4, Bar)
+//!     .build();
+//! ```
+//!
+//! Unlike the example above, the vectors that aren't set point to `DefaultHandler` rather than 0.
+//!
 //! ### `device.x`
 //!
 //! Linking in `__INTERRUPTS` creates a bunch of undefined references. If the user doesn't set a
@@ -327,17 +1053,323 @@
 //! }
 //! ```
 //!
+//! ## Extending the linker script
+//!
+//! Two sections are reserved for applications and are empty otherwise:
+//!
+//! - `.user_rom`: data kept in `ROM`, after `.rodata`, even if nothing refers to it, e.g. a table
+//! read by host tools. Use `#[link_section = ".user_rom"]` (or `.user_rom.<name>`).
+//!
+//! - `.user_ram`: uninitialized data in `RAM`, after `.bss`. The reset handler doesn't zero it, so
+//! it keeps its value across a watchdog or software reset, and large buffers placed there don't
+//! add to the boot time. `#[uninit]` places a `static` variable there and makes sure that it's a
+//! `MaybeUninit`.
+//!
+//! Custom output sections can be added without a copy of `link.x`, with an overlay script that
+//! uses `INSERT`. `.user_rom`, `.user_ram` and `.text` are the documented anchor points:
+//!
+//! ``` text
+//! /* calib.x */
+//! SECTIONS
+//! {
+//!   .calib : ALIGN(2)
+//!   {
+//!     KEEP(*(.calib));
+//!   } > ROM
+//! } INSERT AFTER .user_rom;
+//! ```
+//!
+//! The overlay script is passed to the linker after `link.x`: `-C link-arg=-Tlink.x -C
+//! link-arg=-Tcalib.x`.
+//!
+//! ## C constructors
+//!
+//! The reset handler calls the constructors of C and C++ code linked into the program, e.g.
+//! functions marked with `__attribute__((constructor))` and the initializers of C++ globals, after
+//! `static` variables have been initialized and before `main`. The linker script collects them
+//! from the `.init_array` and `.ctors` input sections, ordered by priority
+//! (`__attribute__((constructor(101)))` runs before `__attribute__((constructor(102)))`, which
+//! runs before constructors without a priority).
+//!
+//! Rust functions are registered the same way with the `#[ctor]` attribute, which accepts the same
+//! priorities, so C and Rust subsystems can be initialized in a single, controlled order:
+//!
+//! ``` ignore
+//! use msp430_rt::ctor;
+//!
+//! // the clocks are set up before the drivers
+//! #[ctor(priority = 101)]
+//! fn init_clocks() { /* .. */ }
+//!
+//! #[ctor(priority = 200)]
+//! fn init_drivers() { /* .. */ }
+//! ```
+//!
+//! Compilers emit these sections as writable, which `rust-lld` rejects in `ROM` unless it's given
+//! `-C link-arg=-znorelro`.
+//!
+//! ## Startup code written for TI toolchains
+//!
+//! Like TI's C runtime, the reset handler calls `int _system_pre_init(void)` before `static`
+//! variables are initialized, and skips their initialization if it returns 0, e.g. to keep the
+//! contents of RAM across a reset or to speed up the boot of programs with large buffers. An
+//! implementation written for TI toolchains can be linked in as is; otherwise a default that
+//! returns 1 is used. From Rust:
+//!
+//! ``` ignore
+//! #[no_mangle]
+//! extern "C" fn _system_pre_init() -> i16 {
+//!     // e.g. stop the watchdog
+//!     0 // skip the initialization of `static` variables
+//! }
+//! ```
+//!
+//! `_system_pre_init` runs after the `#[pre_init]` function. Returning 0 also skips the RAM test of
+//! the `ram-test` feature.
+//!
+//! The linker script also defines the symbols that the startup files and libraries of
+//! msp430-elf-gcc and mspgcc refer to, as aliases of its own: `__stack` (the initial stack
+//! pointer), `__bss_start`, `__bss_end`, `__bssstart` and `__bsssize` (`.bss`), `__data_start`,
+//! `__data_end`, `__datastart`, `__data_load_start`, `__romdatastart` and `__romdatacopysize`
+//! (`.data` and its initial values), and `__heap_start__`, `__heap_end__`, `end` and `_end` (the
+//! heap, used by newlib's `sbrk`). Each of them can still be defined differently in `memory.x`.
+//!
+//! ## Sharing functions between images
+//!
+//! A bootloader can expose services, like writing to flash, to the application it boots so the
+//! application doesn't need its own copy of that code. The bootloader exports a jump table of
+//! `extern "C"` functions with `api_export!`; the table is placed at the beginning of `ROM`, so its
+//! address is stable. The application declares the same functions with `api_import!`, which
+//! generates typed wrappers that call through the table located at `__api_table`:
+//!
+//! ``` text
+//! /* memory.x of the application: the bootloader's ROM starts at 0xC000 */
+//! __api_table = 0xC000;
+//! ```
+//!
+//! ## Workspaces with several images
+//!
+//! A project with several firmware images, e.g. a bootloader, an application and a factory test
+//! program, can keep them in one workspace, one package per image. Each image needs its own
+//! `memory.x` and usually its own features of this crate, but Cargo runs the linker from the root
+//! of the workspace, where a single `memory.x` would apply to every image, and unifies the features
+//! of the packages that are built together.
+//!
+//! Give each image its own `.cargo/config.toml` instead, and build it from its own directory. The
+//! `MSP430_RT_MEMORY_X` variable names the image's `memory.x`, which the build script then puts in
+//! the search path of the linker, in place of the layout generated for `MSP430_MCU`; it also feeds
+//! the constants of the `memory` module. A target directory per image keeps the configurations,
+//! as well as `MSP430_RT_SLOT`, from triggering rebuilds of each other:
+//!
+//! ``` toml
+//! # bootloader/.cargo/config.toml
+//! [build]
+//! target = "msp430-none-elf"
+//! target-dir = "../target/bootloader"
+//!
+//! [env]
+//! MSP430_MCU = "msp430fr5969"
+//! MSP430_RT_MEMORY_X = { value = "memory.x", relative = true }
+//! ```
+//!
+//! ``` console
+//! $ cd bootloader && cargo build --release
+//! $ cd ../application && cargo build --release
+//! ```
+//!
+//! `relative = true` resolves the path against the directory that holds `.cargo/config.toml`. The
+//! build script rejects a relative path without it, which it would resolve against the directory
+//! of this crate instead.
+//!
+//! Features then follow the dependencies of each image, e.g. `boot-selector` for the bootloader
+//! and `proxy-vectors` for the application, as long as the workspace uses `resolver = "2"` and the
+//! images aren't built together with `cargo build --workspace`. Don't leave a `memory.x` in the
+//! root of the workspace: the linker finds it before the one of the image.
+//!
+//! ## Sharing an interrupt between handlers
+//!
+//! Independent drivers sometimes need the same interrupt, e.g. when they use different pins of the
+//! same port. Instead of writing a dispatcher by hand, each driver registers its handler with
+//! `shared_handler!` and the application turns the interrupt into a dispatcher that calls all of
+//! them with `shared_interrupt!`. The handlers are collected by the linker in the
+//! `.interrupt_chain.*` sections, so no registration code runs at startup.
+//!
+//! ## Spurious interrupts
+//!
+//! What the default interrupt handler, which handles the interrupts that have no handler, does is
+//! a policy chosen per build with the `MSP430_RT_SPURIOUS_INTERRUPTS` environment variable:
+//!
+//! - `loop`, the default: disables the interrupts and loops forever, so that the fault is noticed.
+//! - `ignore`: counts the interrupt and returns; `spurious::count` reads the count. The interrupt
+//! fires again right away if its flag isn't cleared by hardware, so this suits the sources that
+//! clear their flag when they're serviced.
+//! - `reset`: resets the device with `reset::software_reset`, like the `reset-unused-vectors`
+//! feature.
+//! - `hook`: calls the function `SpuriousInterrupt` and returns. The default disables the
+//! interrupts and loops forever, like `loop`; define it to, e.g., log the interrupt.
+//! - `halt-in-debug`: stops under a debugger (see `breakpoint`) and then behaves like `loop` in
+//! debug builds, and behaves like `ignore` in release builds.
+//!
+//! ``` text
+//! $ MSP430_RT_SPURIOUS_INTERRUPTS=halt-in-debug cargo build
+//! ```
+//!
+//! ``` ignore
+//! #[no_mangle]
+//! extern "C" fn SpuriousInterrupt() {
+//!     // ..
+//! }
+//! ```
+//!
+//! Defining `DefaultHandler` with `#[interrupt]` takes precedence over the policy, and so do the
+//! checks of `strict-vectors` in release builds.
+//!
+//! ## Device signatures
+//!
+//! FRAM devices read some configuration words from fixed addresses right below the vector table.
+//! `msp430-rt` provides macros that place these words in dedicated linker sections; the linker
+//! script checks that the sections end up at the addresses required by the hardware.
+//!
+//! - `bsl_signature!` populates the two BSL signature words at `0xFF84`. Setting both words to
+//! `signature::BSL_DISABLE` disables the bootloader.
+//!
+//! - `jtag_signature!` populates the two JTAG signature words at `0xFF80`. Setting both words to
+//! `signature::JTAG_LOCK` *permanently* locks the JTAG/SBW debug interface. This macro is only
+//! available when the `jtag-lock` feature is enabled; see below.
+//!
+//! ## IP Encapsulation
+//!
+//! With the `ipe` feature the `.ipe` output section, placed right after `.text`, holds the IPE
+//! init structure followed by the code and the data marked with `#[ipe]`:
+//!
+//! ``` ignore
+//! use msp430_rt::ipe;
+//!
+//! #[ipe]
+//! static COEFFICIENTS: [i16; 8] = [/* .. */];
+//!
+//! #[ipe]
+//! pub fn filter(sample: i16) -> i16 {
+//!     // reads `COEFFICIENTS`
+//! }
+//! ```
+//!
+//! The linker computes the borders of the region, `__ipe_start` and `__ipe_end`, and the check
+//! code of the structure. The value of the `MPUIPC0` register in the structure is given by the
+//! `__ipe_mpuipc0` symbol. It defaults to `0x00C0`, which enables and locks the protection; set it
+//! in `memory.x` to, e.g., `0x00E0` to also reset the device on violations. The code outside of
+//! the region can still call the functions in it, which `#[ipe]` keeps from being inlined, but it
+//! can't read the region: don't pass references to protected data out of it.
+//!
 //! [attr-entry]: attr.entry.html
 //! [attr-exception]: attr.exception.html
 //! [attr-pre_init]: attr.pre_init.html
 
 #![deny(missing_docs)]
-#![feature(abi_msp430_interrupt)]
+// Other targets get the API but not the startup code, which uses most of the internals
+#![cfg_attr(not(target_arch = "msp430"), allow(dead_code, unused_imports))]
+#![cfg_attr(target_arch = "msp430", feature(abi_msp430_interrupt))]
+#![cfg_attr(target_arch = "msp430", feature(asm_experimental_arch))]
 #![no_std]
 
+pub use breakpoint::breakpoint;
+pub use exit::{exit, Termination};
 use msp430::asm;
 pub use msp430_rt_macros::interrupt;
-pub use msp430_rt_macros::{entry, pre_init};
+#[cfg(feature = "ipe")]
+pub use msp430_rt_macros::ipe;
+pub use msp430_rt_macros::{ctor, entry, pre_init, uninit};
+#[cfg(feature = "stack-guard")]
+pub use stack::check_stack_guard;
+pub use stack_switch::switch_stack;
+#[cfg(feature = "startup-time")]
+pub use startup::startup_cycles;
+#[cfg(feature = "newlib-syscalls")]
+pub use syscalls::check_heap_collision;
+
+#[doc(hidden)]
+pub use exit::main_returned;
+#[doc(hidden)]
+pub use msp430 as __msp430;
+
+mod api;
+pub mod assert;
+#[cfg(feature = "blackbox")]
+pub mod blackbox;
+#[cfg(any(
+    feature = "board-msp-exp430fr2433",
+    feature = "board-msp-exp430fr5969",
+    feature = "board-msp-exp430g2et"
+))]
+pub mod board;
+pub mod boot;
+mod breakpoint;
+mod chain;
+#[cfg(feature = "checkpoint")]
+pub mod checkpoint;
+pub mod clock;
+mod config;
+#[cfg(feature = "uart-console")]
+pub mod console;
+pub mod crc;
+#[cfg(feature = "debugger-delay")]
+pub mod debugger;
+mod exit;
+#[cfg(feature = "c-api")]
+mod ffi;
+pub mod flash;
+pub mod fram;
+mod image;
+#[cfg(not(feature = "fast-boot"))]
+mod init;
+#[cfg(feature = "device")]
+pub mod irq;
+#[cfg(feature = "isr-depth")]
+pub mod isr;
+#[cfg(feature = "isr-time")]
+pub mod isr_time;
+pub mod journal;
+mod linker_slice;
+#[cfg(feature = "lpm5-io")]
+pub mod lpm5;
+#[cfg(feature = "boot-markers")]
+mod marker;
+#[cfg(feature = "mem-intrinsics")]
+mod mem;
+pub mod memory;
+pub mod message;
+#[cfg(any(
+    feature = "mpu",
+    all(
+        feature = "readonly-text",
+        any(msp430_family = "fr5", msp430_family = "fr6")
+    )
+))]
+pub mod mpu;
+pub mod power;
+#[cfg(feature = "production-test")]
+pub mod production;
+#[cfg(feature = "ram-test")]
+mod ram_test;
+pub mod reset;
+#[cfg(feature = "rom-driverlib")]
+pub mod rom;
+pub mod signature;
+mod singleton;
+#[cfg(feature = "sleep")]
+pub mod sleep;
+pub mod spurious;
+#[cfg(feature = "stack-guard")]
+mod stack;
+mod stack_switch;
+#[cfg(feature = "startup-time")]
+mod startup;
+#[cfg(feature = "newlib-syscalls")]
+mod syscalls;
+#[cfg(feature = "uart-updater")]
+pub mod updater;
+pub mod vectors;
+pub mod watchdog;
 
 /// Returns a pointer to the start of the heap
 ///
@@ -351,15 +1383,199 @@ pub fn heap_start() -> *mut u32 {
     unsafe { &mut __sheap }
 }
 
+#[cfg(target_arch = "msp430")]
 extern "msp430-interrupt" {
     fn ResetTrampoline() -> !;
 }
 
+#[cfg(target_arch = "msp430")]
 #[link_section = ".__RESET_VECTOR"]
 #[no_mangle]
 static __RESET_VECTOR: unsafe extern "msp430-interrupt" fn() -> ! = ResetTrampoline;
 
+#[cfg(all(feature = "c-vectors", feature = "ram-vectors"))]
+compile_error!("the `c-vectors` and `ram-vectors` features of msp430-rt can't be combined");
+
+#[cfg(all(feature = "ram-vectors", feature = "proxy-vectors"))]
+compile_error!("the `ram-vectors` and `proxy-vectors` features of msp430-rt can't be combined");
+
+#[cfg(all(
+    feature = "blackbox",
+    any(
+        msp430_family = "f1",
+        msp430_family = "f2",
+        msp430_family = "f4",
+        msp430_family = "f5",
+        msp430_family = "f6",
+        msp430_family = "g2"
+    )
+))]
+compile_error!("the `blackbox` feature of msp430-rt requires a FRAM device; see `MSP430_MCU`");
+
+#[cfg(all(
+    feature = "lpm5-io",
+    any(
+        msp430_family = "f1",
+        msp430_family = "f2",
+        msp430_family = "f4",
+        msp430_family = "f5",
+        msp430_family = "f6",
+        msp430_family = "g2"
+    )
+))]
+compile_error!("the `lpm5-io` feature of msp430-rt requires a FRAM device; see `MSP430_MCU`");
+
+#[cfg(all(
+    feature = "checkpoint",
+    any(
+        msp430_family = "f1",
+        msp430_family = "f2",
+        msp430_family = "f4",
+        msp430_family = "f5",
+        msp430_family = "f6",
+        msp430_family = "g2"
+    )
+))]
+compile_error!("the `checkpoint` feature of msp430-rt requires a FRAM device; see `MSP430_MCU`");
+
+#[cfg(all(feature = "checkpoint", feature = "ram-image"))]
+compile_error!("the `checkpoint` and `ram-image` features of msp430-rt can't be combined");
+
+#[cfg(all(
+    feature = "reset-counters",
+    any(
+        msp430_family = "f1",
+        msp430_family = "f2",
+        msp430_family = "f4",
+        msp430_family = "g2"
+    )
+))]
+compile_error!(
+    "the `reset-counters` feature of msp430-rt requires a device with the `SYSRSTIV` register; \
+     see `MSP430_MCU`"
+);
+
+#[cfg(all(
+    feature = "fram-vectors",
+    any(
+        msp430_family = "f1",
+        msp430_family = "f2",
+        msp430_family = "f4",
+        msp430_family = "f5",
+        msp430_family = "f6",
+        msp430_family = "g2"
+    )
+))]
+compile_error!("the `fram-vectors` feature of msp430-rt requires a FRAM device; see `MSP430_MCU`");
+
+#[cfg(all(
+    feature = "crc-hw",
+    any(
+        msp430_family = "f1",
+        msp430_family = "f2",
+        msp430_family = "f4",
+        msp430_family = "g2"
+    )
+))]
+compile_error!("the `crc-hw` feature of msp430-rt requires a device with a CRC16 peripheral");
+
+#[cfg(all(
+    feature = "fram-write-protect",
+    any(
+        msp430_family = "f1",
+        msp430_family = "f2",
+        msp430_family = "f4",
+        msp430_family = "f5",
+        msp430_family = "f6",
+        msp430_family = "g2",
+        msp430_family = "fr5",
+        msp430_family = "fr6"
+    )
+))]
+compile_error!(
+    "the `fram-write-protect` feature of msp430-rt requires a FR2xx/FR4xx device; see `MSP430_MCU`"
+);
+
+#[cfg(all(
+    feature = "mpu",
+    any(
+        msp430_family = "f1",
+        msp430_family = "f2",
+        msp430_family = "f4",
+        msp430_family = "f5",
+        msp430_family = "f6",
+        msp430_family = "g2",
+        msp430_family = "fr2",
+        msp430_family = "fr4"
+    )
+))]
+compile_error!("the `mpu` feature of msp430-rt requires a FR5xx/FR6xx device; see `MSP430_MCU`");
+
+#[cfg(all(feature = "readonly-text", not(msp430_fram)))]
+compile_error!("the `readonly-text` feature of msp430-rt requires a FRAM device; see `MSP430_MCU`");
+
+#[cfg(all(feature = "readonly-text", feature = "mpu"))]
+compile_error!("the `readonly-text` and `mpu` features of msp430-rt can't be combined");
+
+#[cfg(all(feature = "readonly-text", feature = "ram-image"))]
+compile_error!("the `readonly-text` and `ram-image` features of msp430-rt can't be combined");
+
+#[cfg(all(
+    feature = "ipe",
+    any(
+        msp430_family = "f1",
+        msp430_family = "f2",
+        msp430_family = "f4",
+        msp430_family = "f5",
+        msp430_family = "f6",
+        msp430_family = "g2",
+        msp430_family = "fr2",
+        msp430_family = "fr4"
+    )
+))]
+compile_error!("the `ipe` feature of msp430-rt requires a FR5xx/FR6xx device; see `MSP430_MCU`");
+
+#[cfg(all(feature = "ipe", feature = "ram-image"))]
+compile_error!("the `ipe` and `ram-image` features of msp430-rt can't be combined");
+
+#[cfg(all(feature = "strict-vectors", not(feature = "device")))]
+compile_error!("the `strict-vectors` feature of msp430-rt requires the `device` feature");
+
+#[cfg(all(feature = "entry-stub", feature = "proxy-vectors"))]
+compile_error!("the `entry-stub` and `proxy-vectors` features of msp430-rt can't be combined");
+
+#[cfg(all(
+    feature = "uart-updater",
+    any(
+        msp430_family = "f1",
+        msp430_family = "f2",
+        msp430_family = "f4",
+        msp430_family = "f5",
+        msp430_family = "f6",
+        msp430_family = "g2"
+    )
+))]
+compile_error!("the `uart-updater` feature of msp430-rt requires a FRAM device; see `MSP430_MCU`");
+
+// `br #ResetTrampoline`
+#[cfg(all(feature = "entry-stub", target_arch = "msp430"))]
+#[repr(C)]
+struct EntryStub {
+    opcode: u16,
+    target: unsafe extern "msp430-interrupt" fn() -> !,
+}
+
+#[cfg(all(feature = "entry-stub", target_arch = "msp430"))]
+#[link_section = ".text.start"]
+#[no_mangle]
+#[used]
+static __ENTRY_STUB: EntryStub = EntryStub {
+    opcode: 0x4030,
+    target: ResetTrampoline,
+};
+
 // The reset handler
+#[cfg(target_arch = "msp430")]
 #[no_mangle]
 #[link_section = ".Reset"]
 unsafe extern "C" fn Reset() -> ! {
@@ -377,32 +1593,149 @@ unsafe extern "C" fn Reset() -> ! {
     }
 
     extern "Rust" {
+        #[cfg(not(feature = "fast-boot"))]
         fn PreInit();
+        #[cfg(not(feature = "c-main"))]
         fn main() -> !;
     }
 
+    extern "C" {
+        // `int _system_pre_init(void)` of TI's C runtime; `int` is 16 bits wide
+        fn _system_pre_init() -> i16;
+    }
+
+    #[cfg(feature = "startup-time")]
+    startup::start();
+
+    #[cfg(feature = "boot-markers")]
+    marker::toggle();
+
+    #[cfg(feature = "mpu")]
+    mpu::init();
+
+    // Before `count_causes`: reading `SYSRSTIV` clears the LPMx.5 wakeup flag that `restore`
+    // checks
+    #[cfg(feature = "lpm5-io")]
+    lpm5::restore();
+
+    // After `lpm5::restore`, see above
+    #[cfg(feature = "reset-counters")]
+    reset::count_causes();
+
+    #[cfg(feature = "debugger-delay")]
+    debugger::wait();
+
+    // Resume from the checkpoint instead of booting, if there's one
+    #[cfg(feature = "checkpoint")]
+    if checkpoint::pending() {
+        fram::unprotected(|| checkpoint::restore());
+    }
+
+    #[cfg(not(feature = "fast-boot"))]
     PreInit();
 
-    r0::zero_bss(&mut _sbss, &mut _ebss);
-    r0::init_data(&mut _sdata, &mut _edata, &_sidata);
+    // Returning 0 skips the initialization of `static` variables, as with TI's toolchains
+    if cfg!(feature = "fast-boot") || _system_pre_init() != 0 {
+        // Runs after `PreInit`, which usually stops the watchdog; the test takes a while. It
+        // overwrites the RAM, so it's skipped along with the initialization
+        #[cfg(feature = "ram-test")]
+        ram_test::run();
+
+        r0::zero_bss(&mut _sbss, &mut _ebss);
+        // The loader of a RAM image places .data at its final address
+        #[cfg(not(feature = "ram-image"))]
+        r0::init_data(&mut _sdata, &mut _edata, &_sidata);
+    }
+
+    #[cfg(feature = "boot-markers")]
+    marker::toggle();
+
+    #[cfg(feature = "stack-guard")]
+    stack::paint();
+
+    #[cfg(feature = "ram-vectors")]
+    vectors::init_ram_vectors();
 
-    main()
+    #[cfg(feature = "image-crc")]
+    crc::verify_image();
+
+    #[cfg(feature = "image-signature")]
+    image::verify_image();
+
+    #[cfg(feature = "vector-crc")]
+    crc::verify_vector_table();
+
+    #[cfg(feature = "production-test")]
+    production::check();
+
+    #[cfg(not(feature = "fast-boot"))]
+    init::run();
+
+    #[cfg(feature = "uart-updater")]
+    {
+        extern "C" {
+            fn UpdateRequested() -> bool;
+        }
+
+        if UpdateRequested() {
+            updater::enter();
+        }
+    }
+
+    // Past this point the code and the constants are only ever read
+    #[cfg(any(feature = "fram-write-protect", feature = "readonly-text"))]
+    fram::protect();
+
+    #[cfg(feature = "boot-markers")]
+    marker::toggle();
+
+    #[cfg(feature = "startup-time")]
+    startup::stop();
+
+    #[cfg(feature = "c-main")]
+    {
+        extern "C" {
+            fn __c_main() -> i16;
+        }
+
+        // Like an `#[entry]` function that returns
+        exit::main_returned(__c_main())
+    }
+
+    #[cfg(not(feature = "c-main"))]
+    main();
 }
 
 #[no_mangle]
 unsafe extern "C" fn PreInit_() {}
 
 #[no_mangle]
-extern "msp430-interrupt" fn DefaultHandler_() -> ! {
-    // The interrupts are already disabled here.
+extern "C" fn _system_pre_init_() -> i16 {
+    1
+}
+
+/// Stops the program: disables the interrupts and loops forever
+///
+/// The defaults of the hooks that never return end here.
+pub(crate) fn park() -> ! {
+    msp430::interrupt::disable();
     loop {
         // Prevent optimizations that can remove this loop.
         asm::barrier();
     }
 }
 
+#[cfg(target_arch = "msp430")]
+#[no_mangle]
+extern "msp430-interrupt" fn DefaultHandler_() -> ! {
+    #[cfg(feature = "blackbox")]
+    blackbox::seal();
+
+    park()
+}
+
 // Interrupts for generic application
-#[cfg(not(feature = "device"))]
+#[cfg(all(not(feature = "device"), target_arch = "msp430"))]
 #[no_mangle]
 #[link_section = ".vector_table.interrupts"]
 static __INTERRUPTS: [unsafe extern "msp430-interrupt" fn(); 15] = [{
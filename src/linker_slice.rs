@@ -0,0 +1,33 @@
+//! Arrays collected by the linker
+
+/// Returns the `$ty`s that the linker collects from the sections `$section.1*`, as a
+/// `&'static [$ty]`
+///
+/// The linker sorts the sections by name, so their contents end up between two markers, in the
+/// sections `$section.0` and `$section.2`. Entries are added with, e.g.:
+///
+/// ``` ignore
+/// #[link_section = ".power_fail.1.0"]
+/// #[used]
+/// static CALLBACK: fn() = save_measurements;
+/// ```
+#[doc(hidden)]
+#[macro_export]
+macro_rules! linker_slice {
+    ($section:expr, $ty:ty) => {{
+        #[link_section = concat!($section, ".0")]
+        #[used]
+        static START: [$ty; 0] = [];
+
+        #[link_section = concat!($section, ".2")]
+        #[used]
+        static END: [$ty; 0] = [];
+
+        let start = START.as_ptr();
+        let len = (END.as_ptr() as usize - start as usize) / ::core::mem::size_of::<$ty>();
+        #[allow(unsafe_code)]
+        unsafe {
+            ::core::slice::from_raw_parts(start, len)
+        }
+    }};
+}
@@ -0,0 +1,130 @@
+//! I/O state across LPMx.5
+//!
+//! In LPM3.5 and LPM4.5 the core is powered off; only the pins keep their state, locked by the
+//! `LOCKLPM5` bit of `PM5CTL0`. A wakeup, e.g. from a pin interrupt, goes through a BOR, which
+//! resets the configuration registers of the ports; the program must configure them again exactly
+//! as they were, pin interrupts included, before clearing `LOCKLPM5`, or the pins glitch and the
+//! interrupt that woke the device is lost.
+//!
+//! With the `lpm5-io` feature, `save` records the configuration of the ports in `.persistent`
+//! FRAM before the program enters LPMx.5. On the wakeup the reset handler writes it back and
+//! clears `LOCKLPM5`, before anything else that might touch the pins, i.e. before the
+//! `#[pre_init]` function. The flag of the interrupt that woke the device is left set, so its
+//! handler runs once the program enables interrupts:
+//!
+//! ``` ignore
+//! use msp430_rt::lpm5;
+//!
+//! // Ports 1 to 4
+//! lpm5::save(0b1111);
+//! // set PMMREGOFF and enter LPM4
+//!
+//! #[entry]
+//! fn main() -> ! {
+//!     if lpm5::woke_up() {
+//!         // the pins are already configured; set up the rest again
+//!     }
+//!     // ..
+//! }
+//! ```
+//!
+//! Only the digital I/O ports 1 to 10 are covered, not port J. Resets that aren't a wakeup from
+//! LPMx.5 leave the ports alone.
+
+use core::ptr;
+
+use crate::fram;
+
+// Offsets of the registers of an odd port from the base of its pair: `PxOUT`, `PxDIR`, `PxREN`,
+// `PxSEL0`, `PxSEL1`, `PxIES` and `PxIE`. `PxIFG` isn't restored, as it holds the wakeup source
+const REGISTERS: [usize; 7] = [0x02, 0x04, 0x06, 0x0A, 0x0C, 0x18, 0x1A];
+
+/// Number of ports covered
+const PORTS: usize = 10;
+
+// `PMMIFG` and its LPMx.5 wakeup flag, and `PM5CTL0` and its lock bit
+const PMMIFG: usize = 0x012A;
+const PMMLPM5IFG: u16 = 1 << 15;
+const PM5CTL0: usize = 0x0130;
+const LOCKLPM5: u16 = 1 << 0;
+
+/// Value of `SYSRSTIV` for a wakeup from LPMx.5
+#[cfg(feature = "reset-counters")]
+const SYSRSTIV_LPM5WU: u16 = 0x08;
+
+#[repr(C)]
+struct Saved {
+    ports: u16,
+    registers: [[u8; REGISTERS.len()]; PORTS],
+}
+
+#[link_section = ".persistent"]
+static mut SAVED: Saved = Saved {
+    ports: 0,
+    registers: [[0; REGISTERS.len()]; PORTS],
+};
+
+/// Returns the address of a register of port `port`, from 1
+fn register(port: usize, offset: usize) -> *mut u8 {
+    // Ports come in pairs, 0x20 bytes apart; the registers of the even port follow by a byte
+    (0x0200 + (port - 1) / 2 * 0x20 + (port - 1) % 2 + offset) as *mut u8
+}
+
+/// Records the configuration of the ports whose bit is set in `ports`, bit 0 for port 1, so that
+/// the reset handler restores it after a wakeup from LPMx.5
+///
+/// Call this right before entering LPMx.5, once the pins, including the interrupts that are to
+/// wake the device, are configured.
+pub fn save(ports: u16) {
+    fram::unprotected(|| unsafe {
+        for port in 1..=PORTS {
+            if ports & 1 << (port - 1) != 0 {
+                for (i, &offset) in REGISTERS.iter().enumerate() {
+                    let value = ptr::read_volatile(register(port, offset));
+                    ptr::write_volatile(ptr::addr_of_mut!(SAVED.registers[port - 1][i]), value);
+                }
+            }
+        }
+        ptr::write_volatile(ptr::addr_of_mut!(SAVED.ports), ports);
+    });
+}
+
+/// Returns `true` if `PMMLPM5IFG` is set
+fn flagged() -> bool {
+    unsafe { ptr::read_volatile(PMMIFG as *const u16) & PMMLPM5IFG != 0 }
+}
+
+/// Returns `true` if the last reset was a wakeup from LPMx.5
+pub fn woke_up() -> bool {
+    // With `reset-counters` the reset handler has read `SYSRSTIV`, which clears the flag, and
+    // recorded the cause
+    #[cfg(feature = "reset-counters")]
+    if crate::reset::last_sysrstiv() == SYSRSTIV_LPM5WU {
+        return true;
+    }
+
+    flagged()
+}
+
+/// Restores the configuration recorded by `save` and unlocks the pins, after a wakeup from LPMx.5
+///
+/// Called by the reset handler before `reset::count_causes`, whose reads of `SYSRSTIV` clear
+/// `PMMLPM5IFG`.
+pub(crate) unsafe fn restore() {
+    if !flagged() {
+        return;
+    }
+
+    let ports = ptr::read_volatile(ptr::addr_of!(SAVED.ports));
+    for port in 1..=PORTS {
+        if ports & 1 << (port - 1) != 0 {
+            for (i, &offset) in REGISTERS.iter().enumerate() {
+                let value = ptr::read_volatile(ptr::addr_of!(SAVED.registers[port - 1][i]));
+                ptr::write_volatile(register(port, offset), value);
+            }
+        }
+    }
+
+    let pm5ctl0 = PM5CTL0 as *mut u16;
+    ptr::write_volatile(pm5ctl0, ptr::read_volatile(pm5ctl0) & !LOCKLPM5);
+}
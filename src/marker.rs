@@ -0,0 +1,19 @@
+//! Boot phase markers for energy profiling, enabled by the `boot-markers` feature
+
+/// Toggles the marker pin, making it an output first
+#[inline(always)]
+pub(crate) unsafe fn toggle() {
+    extern "C" {
+        // Absolute symbols; the addresses of `PxOUT` and `PxDIR`, and the mask of the pin
+        static __boot_marker_out: u8;
+        static __boot_marker_dir: u8;
+        static __boot_marker_mask: u8;
+    }
+
+    let out = core::ptr::addr_of!(__boot_marker_out) as usize as *mut u8;
+    let dir = core::ptr::addr_of!(__boot_marker_dir) as usize as *mut u8;
+    let mask = core::ptr::addr_of!(__boot_marker_mask) as usize as u8;
+
+    core::ptr::write_volatile(out, core::ptr::read_volatile(out) ^ mask);
+    core::ptr::write_volatile(dir, core::ptr::read_volatile(dir) | mask);
+}
@@ -0,0 +1,185 @@
+//! Memory functions tuned for the MSP430
+//!
+//! The generic `memcpy`, `memmove`, `memset` and `memcmp` of `compiler_builtins` and `libgcc` work
+//! a byte at a time. These versions move a word at a time, four words per loop iteration, when the
+//! alignment of the operands allows it, which needs about half as many instructions per byte for
+//! aligned data, e.g. when the reset handler initializes `.data`.
+//!
+//! All the memory accesses are volatile: this keeps the compiler from turning the loops back into
+//! calls to the functions being defined, and costs nothing since every access is an instruction
+//! of its own on the MSP430 anyway.
+
+use core::{
+    ffi::c_int,
+    ptr::{read_volatile as read, write_volatile as write},
+};
+
+/// Copies `n` bytes from `src` to `dest`, which must not overlap
+#[no_mangle]
+pub unsafe extern "C" fn memcpy(dest: *mut u8, src: *const u8, n: usize) -> *mut u8 {
+    copy_forward(dest, src, n);
+    dest
+}
+
+/// Copies `n` bytes from `src` to `dest`, which may overlap
+#[no_mangle]
+pub unsafe extern "C" fn memmove(dest: *mut u8, src: *const u8, n: usize) -> *mut u8 {
+    // A forward copy is only wrong if `dest` lies within `src..src + n`
+    if (dest as usize).wrapping_sub(src as usize) >= n {
+        copy_forward(dest, src, n);
+    } else {
+        copy_backward(dest, src, n);
+    }
+    dest
+}
+
+/// Sets `n` bytes starting at `dest` to `c`
+#[no_mangle]
+pub unsafe extern "C" fn memset(dest: *mut u8, c: c_int, n: usize) -> *mut u8 {
+    let byte = c as u8;
+    let mut d = dest;
+    let mut n = n;
+
+    if d as usize & 1 != 0 && n != 0 {
+        write(d, byte);
+        d = d.add(1);
+        n -= 1;
+    }
+
+    let word = u16::from(byte) * 0x0101;
+    let mut dw = d as *mut u16;
+    while n >= 8 {
+        write(dw, word);
+        write(dw.add(1), word);
+        write(dw.add(2), word);
+        write(dw.add(3), word);
+        dw = dw.add(4);
+        n -= 8;
+    }
+    while n >= 2 {
+        write(dw, word);
+        dw = dw.add(1);
+        n -= 2;
+    }
+
+    if n != 0 {
+        write(dw as *mut u8, byte);
+    }
+    dest
+}
+
+/// Compares `n` bytes at `a` and `b`
+#[no_mangle]
+pub unsafe extern "C" fn memcmp(a: *const u8, b: *const u8, n: usize) -> c_int {
+    let mut a = a;
+    let mut b = b;
+    let mut n = n;
+
+    // Skip the words that are equal; the first difference is then located byte by byte
+    if (a as usize | b as usize) & 1 == 0 {
+        while n >= 2 && read(a as *const u16) == read(b as *const u16) {
+            a = a.add(2);
+            b = b.add(2);
+            n -= 2;
+        }
+    }
+
+    while n != 0 {
+        let (x, y) = (read(a), read(b));
+        if x != y {
+            return c_int::from(x) - c_int::from(y);
+        }
+        a = a.add(1);
+        b = b.add(1);
+        n -= 1;
+    }
+    0
+}
+
+unsafe fn copy_forward(dest: *mut u8, src: *const u8, n: usize) {
+    let mut d = dest;
+    let mut s = src;
+    let mut n = n;
+
+    // Word accesses need both pointers to be even, which an odd first byte can fix if they are
+    // both odd
+    if (d as usize ^ s as usize) & 1 == 0 {
+        if d as usize & 1 != 0 && n != 0 {
+            write(d, read(s));
+            d = d.add(1);
+            s = s.add(1);
+            n -= 1;
+        }
+
+        let mut dw = d as *mut u16;
+        let mut sw = s as *const u16;
+        while n >= 8 {
+            write(dw, read(sw));
+            write(dw.add(1), read(sw.add(1)));
+            write(dw.add(2), read(sw.add(2)));
+            write(dw.add(3), read(sw.add(3)));
+            dw = dw.add(4);
+            sw = sw.add(4);
+            n -= 8;
+        }
+        while n >= 2 {
+            write(dw, read(sw));
+            dw = dw.add(1);
+            sw = sw.add(1);
+            n -= 2;
+        }
+
+        d = dw as *mut u8;
+        s = sw as *const u8;
+    }
+
+    while n != 0 {
+        write(d, read(s));
+        d = d.add(1);
+        s = s.add(1);
+        n -= 1;
+    }
+}
+
+unsafe fn copy_backward(dest: *mut u8, src: *const u8, n: usize) {
+    let mut d = dest.add(n);
+    let mut s = src.add(n);
+    let mut n = n;
+
+    if (d as usize ^ s as usize) & 1 == 0 {
+        if d as usize & 1 != 0 && n != 0 {
+            d = d.sub(1);
+            s = s.sub(1);
+            write(d, read(s));
+            n -= 1;
+        }
+
+        let mut dw = d as *mut u16;
+        let mut sw = s as *const u16;
+        while n >= 8 {
+            dw = dw.sub(4);
+            sw = sw.sub(4);
+            write(dw.add(3), read(sw.add(3)));
+            write(dw.add(2), read(sw.add(2)));
+            write(dw.add(1), read(sw.add(1)));
+            write(dw, read(sw));
+            n -= 8;
+        }
+        while n >= 2 {
+            dw = dw.sub(1);
+            sw = sw.sub(1);
+            write(dw, read(sw));
+            n -= 2;
+        }
+
+        d = dw as *mut u8;
+        s = sw as *const u8;
+    }
+
+    while n != 0 {
+        d = d.sub(1);
+        s = s.sub(1);
+        write(d, read(s));
+        n -= 1;
+    }
+}
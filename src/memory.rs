@@ -0,0 +1,108 @@
+//! The memory map of `memory.x`, as constants
+//!
+//! For every region of the `MEMORY` command of `memory.x`, e.g. `RAM`, this module has a
+//! `<REGION>_ORIGIN` and a `<REGION>_LENGTH` constant, and a `<REGION>` `Region`, so that the
+//! program can reason about the memory map without repeating its numbers. `STACK_START` is the
+//! initial stack pointer, the end of `RAM`, and `STACK_SIZE` and `HEAP_SIZE` are the values of
+//! `_stack_size` and `_heap_size` when `memory.x` assigns them:
+//!
+//! ``` ignore
+//! use msp430_rt::memory;
+//!
+//! let free = memory::STACK_START - memory::RAM_ORIGIN - memory::STACK_SIZE;
+//! ```
+//!
+//! The build script can't see the `memory.x` of the application, which the linker finds in the
+//! root of the application, so the path of that file must be given by the `MSP430_RT_MEMORY_X`
+//! environment variable, e.g. in `.cargo/config.toml`, which also makes it the `memory.x` that's
+//! linked (see "Workspaces with several images" in the crate documentation):
+//!
+//! ``` toml
+//! [env]
+//! MSP430_RT_MEMORY_X = { value = "memory.x", relative = true }
+//! ```
+//!
+//! Otherwise the constants come from the `memory.x` generated for a known part (see "MCU
+//! selection"); the build script warns when it finds a `memory.x` in the root of the application,
+//! which would be linked instead. Without either this module is empty. Only values that are plain
+//! numbers, optionally with a `K` or `M` suffix, or sums of them are turned into constants; a
+//! region whose origin is e.g. `ORIGIN(RAM) + 0x100` is left out, and so are the sizes set with
+//! `runtime_config!`.
+
+include!(concat!(env!("OUT_DIR"), "/memory.rs"));
+
+/// A region of `memory.x`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Region {
+    /// Start address
+    pub origin: u32,
+    /// Size in bytes
+    pub length: u32,
+}
+
+#[doc(hidden)]
+pub const fn size_of_pointee<T>(_: *const T) -> usize {
+    core::mem::size_of::<T>()
+}
+
+/// Makes linking fail if the `static` `$object` isn't entirely within the region `$region`
+///
+/// The region is one of the regions of the `memory` module, e.g. `INFOB` for a region of that
+/// name in `memory.x`, which catches placement regressions, e.g. calibration data that outgrows
+/// its segment and spills into the next one, or a `#[link_section]` that ends up in the wrong
+/// region:
+///
+/// ``` ignore
+/// #[link_section = ".infob"]
+/// #[no_mangle]
+/// static CALIBRATION: [u16; 32] = [0; 32];
+///
+/// msp430_rt::assert_in_region!(CALIBRATION, INFOB);
+/// ```
+///
+/// The check, placed in the `.region_check` section, which isn't loaded into the device, is a pair
+/// of 16-bit relocations against `$object` whose values only fit in 16 bits if the object is
+/// within the region. When it isn't `rust-lld` fails with an error like:
+///
+/// ``` text
+/// rust-lld: error: app.o:(.region_check.CALIBRATION_not_in_INFOB+0x2): relocation
+/// R_MSP430_16_BYTE out of range: 65600 is not in [-32768, 65535]; references 'CALIBRATION'
+/// ```
+///
+/// GNU ld doesn't check the range of these relocations, so the check only has an effect when
+/// linking with `rust-lld`. It keeps `$object` in the image even if the program doesn't use it.
+/// The region must be within the first 64 KiB of the address space. The check is skipped on other
+/// targets than MSP430.
+#[macro_export]
+macro_rules! assert_in_region {
+    ($object:ident, $region:ident) => {
+        const _: () = {
+            const REGION: $crate::memory::Region = $crate::memory::$region;
+            assert!(
+                REGION.origin + REGION.length <= 0x10000,
+                "`assert_in_region!` only supports regions below 0x10000"
+            );
+
+            // The first value is in range if the object starts at or after the origin, the
+            // second one if it ends at or before the end of the region
+            #[cfg(target_arch = "msp430")]
+            ::core::arch::global_asm!(
+                concat!(
+                    ".pushsection .region_check.",
+                    stringify!($object),
+                    "_not_in_",
+                    stringify!($region),
+                    ",\"a\",@progbits"
+                ),
+                ".short {object} + ({start})",
+                ".short {object} + ({end})",
+                ".popsection",
+                object = sym $object,
+                start = const -32768 - REGION.origin as i32,
+                end = const $crate::memory::size_of_pointee(::core::ptr::addr_of!($object)) as i32
+                    + 0xFFFF
+                    - (REGION.origin + REGION.length) as i32,
+            );
+        };
+    };
+}
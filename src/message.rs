@@ -0,0 +1,160 @@
+//! Crash-safe message capture
+//!
+//! A `MessageBuffer` is a fixed-size buffer that implements `core::fmt::Write`, for capturing a
+//! message, e.g. that of a panic, without a heap or a large stack. Messages that don't fit are
+//! truncated, at a character boundary, and end with `...`. The buffer is meant to live in
+//! `.user_ram`, which the reset handler doesn't zero, so that the message of a crash survives the
+//! reset that follows it and can be reported then:
+//!
+//! ``` ignore
+//! use core::fmt::Write;
+//! use msp430_rt::message::MessageBuffer;
+//!
+//! #[link_section = ".user_ram.panic"]
+//! static mut PANIC: MessageBuffer<64> = MessageBuffer::new();
+//!
+//! #[panic_handler]
+//! fn panic(info: &core::panic::PanicInfo) -> ! {
+//!     let buffer = unsafe { &mut PANIC };
+//!     buffer.clear();
+//!     let _ = write!(buffer, "{}", info);
+//!     msp430_rt::reset::software_reset()
+//! }
+//!
+//! #[entry]
+//! fn main() -> ! {
+//!     let buffer = unsafe { &mut PANIC };
+//!     if let Some(message) = buffer.message() {
+//!         // report `message`
+//!     }
+//!     buffer.invalidate();
+//!     // ..
+//! }
+//! ```
+//!
+//! The contents of `.user_ram` are undefined after a power-up, so a buffer only holds a message
+//! once `clear` has been called on it; `message` returns `None` before that, and the initializer
+//! of the `static` doesn't matter: all the fields of the buffer are integers, so any contents are
+//! valid. A buffer placed in `.persistent` FRAM instead keeps its message
+//! across power cycles too, but must be written within `fram::unprotected`.
+
+use core::{fmt, str};
+
+/// Ends a truncated message
+pub const TRUNCATION_MARKER: &str = "...";
+
+/// Value of `MessageBuffer.state` once `clear` has been called
+const VALID: u16 = 0x3E55;
+
+/// A buffer of `N` bytes that holds a message, truncated if needed
+///
+/// `N` must be at least the length of `TRUNCATION_MARKER`.
+#[repr(C)]
+pub struct MessageBuffer<const N: usize> {
+    state: u16,
+    len: u16,
+    // Not a `bool`, since the buffer may start out with any contents
+    truncated: u16,
+    bytes: [u8; N],
+}
+
+impl<const N: usize> MessageBuffer<N> {
+    const FITS_MARKER: () = assert!(N >= TRUNCATION_MARKER.len(), "the buffer is too small");
+
+    /// Creates a buffer that holds no message
+    pub const fn new() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::FITS_MARKER;
+
+        MessageBuffer {
+            state: 0,
+            len: 0,
+            truncated: 0,
+            bytes: [0; N],
+        }
+    }
+
+    /// Empties the buffer; it then holds an empty message
+    pub fn clear(&mut self) {
+        self.len = 0;
+        self.truncated = 0;
+        self.state = VALID;
+    }
+
+    /// Marks the buffer as holding no message, e.g. once the message has been reported
+    pub fn invalidate(&mut self) {
+        self.state = 0;
+    }
+
+    /// Returns the message, or `None` if `clear` hasn't been called since `invalidate` or since
+    /// the contents of the buffer were lost
+    pub fn message(&self) -> Option<&str> {
+        if self.state != VALID || usize::from(self.len) > N {
+            return None;
+        }
+
+        // The bytes may have been corrupted while the buffer wasn't written; keep what's valid
+        let bytes = &self.bytes[..usize::from(self.len)];
+        Some(match str::from_utf8(bytes) {
+            Ok(message) => message,
+            Err(error) => unsafe { str::from_utf8_unchecked(&bytes[..error.valid_up_to()]) },
+        })
+    }
+
+    /// Returns `true` if the message has been truncated
+    pub fn is_truncated(&self) -> bool {
+        self.state == VALID && self.truncated != 0
+    }
+
+    fn push(&mut self, s: &str) {
+        let len = usize::from(self.len);
+        self.bytes[len..len + s.len()].copy_from_slice(s.as_bytes());
+        self.len = (len + s.len()) as u16;
+    }
+}
+
+impl<const N: usize> Default for MessageBuffer<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> fmt::Write for MessageBuffer<N> {
+    /// Appends `s`, or as much of it as fits followed by `TRUNCATION_MARKER`
+    ///
+    /// This never fails, so that the formatting of a message carries on once it's been truncated.
+    /// Once truncated the message doesn't grow anymore.
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.state != VALID {
+            self.clear();
+        }
+        if self.truncated != 0 {
+            return Ok(());
+        }
+
+        let len = usize::from(self.len);
+        if len + s.len() <= N {
+            self.push(s);
+            return Ok(());
+        }
+
+        // Make room for the marker, backing off to a character boundary
+        let room = N - TRUNCATION_MARKER.len();
+        if len > room {
+            let mut end = room;
+            while end > 0 && self.bytes[end] & 0xC0 == 0x80 {
+                end -= 1;
+            }
+            self.len = end as u16;
+        } else {
+            let mut end = room - len;
+            while !s.is_char_boundary(end) {
+                end -= 1;
+            }
+            self.push(&s[..end]);
+        }
+        self.push(TRUNCATION_MARKER);
+        self.truncated = 1;
+        Ok(())
+    }
+}
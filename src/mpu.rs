@@ -0,0 +1,382 @@
+//! Memory protection unit of FR5xx/FR6xx devices
+//!
+//! The MPU splits the main FRAM into three segments, at two configurable borders, and controls
+//! the read, write and execute permissions of each of them and of the information memory. A
+//! violation either raises a system NMI, with one interrupt flag per segment in `MPUCTL1`, or
+//! resets the device.
+//!
+//! With the `mpu` feature the reset handler configures the MPU before anything else, even before
+//! the `#[pre_init]` function, so the protection is in place before any application code runs.
+//! The configuration is declared once, as a constant, with `mpu_config!`:
+//!
+//! ``` ignore
+//! use msp430_rt::mpu::{Access, Config, Violation};
+//!
+//! msp430_rt::mpu_config!(
+//!     // Segment 1: 0x4400..0xA000, code; segment 2: 0xA000..0xC000, constants;
+//!     // segment 3: the rest
+//!     Config::new(0xA000, 0xC000)
+//!         .segment1(Access::RX)
+//!         .segment2(Access::R)
+//!         .segment3(Access::RW)
+//!         .info(Access::RW)
+//!         .on_violation(Violation::Nmi)
+//! );
+//! ```
+//!
+//! The code, which starts at the beginning of `ROM` with the reset handler, must stay executable
+//! and the vector table, at the end of the FRAM, readable.
+//!
+//! The `readonly-text` feature uses a configuration of its own instead, so it can't be combined
+//! with the `mpu` feature.
+//!
+//! # Violations
+//!
+//! With `Violation::Nmi` the runtime handles the system NMI (`SYSNMI`) itself: each violation
+//! flagged by the MPU is cleared and passed, as a `Fault`, to the function `MpuViolation`. The
+//! default loops forever, like the default interrupt handler, after recording the code `0xFA00`
+//! plus the number of the segment (1 to 3, 4 for the information memory) in the black box and
+//! sealing it when the `blackbox` feature is enabled. The program can define its own, e.g. to
+//! record the fault and reset the device:
+//!
+//! ``` ignore
+//! use msp430_rt::mpu::Fault;
+//!
+//! #[no_mangle]
+//! fn MpuViolation(fault: Fault) {
+//!     // ..
+//!     msp430_rt::reset::software_reset();
+//! }
+//! ```
+//!
+//! If `MpuViolation` returns, execution resumes after the access that failed; the MPU blocks
+//! writes, and reads and instruction fetches return `0x3FFF`. A program that defines its own
+//! `SYSNMI` handler, e.g. to handle the other system NMIs, can call `take_fault` from it instead.
+
+/// Permissions of a segment
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Access {
+    /// The segment can be read
+    pub read: bool,
+    /// The segment can be written
+    pub write: bool,
+    /// Code can run from the segment
+    pub execute: bool,
+}
+
+impl Access {
+    /// No access at all
+    pub const NONE: Access = Access::new(false, false, false);
+    /// Read only
+    pub const R: Access = Access::new(true, false, false);
+    /// Read and write
+    pub const RW: Access = Access::new(true, true, false);
+    /// Read and execute
+    pub const RX: Access = Access::new(true, false, true);
+    /// Read, write and execute, like with the MPU disabled
+    pub const RWX: Access = Access::new(true, true, true);
+
+    /// Creates a new `Access`
+    pub const fn new(read: bool, write: bool, execute: bool) -> Self {
+        Access {
+            read,
+            write,
+            execute,
+        }
+    }
+
+    /// The bits of a segment in `MPUSAM`, without the violation select bit
+    const fn bits(self) -> u16 {
+        self.read as u16 | (self.write as u16) << 1 | (self.execute as u16) << 2
+    }
+}
+
+/// What a violation of the permissions does
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Violation {
+    /// Raises a system NMI
+    Nmi,
+    /// Resets the device (PUC)
+    Reset,
+}
+
+/// A memory region with permissions of its own
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Segment {
+    /// Segment 1 of the main FRAM
+    One,
+    /// Segment 2 of the main FRAM
+    Two,
+    /// Segment 3 of the main FRAM
+    Three,
+    /// The information memory
+    Info,
+}
+
+/// An access that violated the permissions of a segment
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Fault {
+    /// The segment that was accessed
+    pub segment: Segment,
+    /// The permissions of the segment
+    ///
+    /// The MPU doesn't record the kind of the access, which is one of those that aren't
+    /// permitted.
+    pub permitted: Access,
+}
+
+/// Configuration of the MPU
+#[derive(Clone, Copy, Debug)]
+pub struct Config {
+    border1: u32,
+    border2: u32,
+    segments: [Access; 3],
+    info: Access,
+    violation: Violation,
+    lock: bool,
+}
+
+impl Config {
+    /// Creates a configuration with segment borders at `border1` and `border2`
+    ///
+    /// The borders are addresses in the main FRAM; they must be multiples of 1024 and `border1`
+    /// can't be above `border2`. All the segments start out with full access, violations raise an
+    /// NMI and the configuration isn't locked.
+    pub const fn new(border1: u32, border2: u32) -> Self {
+        assert!(
+            border1 % 1024 == 0 && border2 % 1024 == 0,
+            "MPU borders must be 1 KiB aligned"
+        );
+        assert!(
+            border1 <= border2,
+            "the first MPU border must be below the second one"
+        );
+
+        Config {
+            border1,
+            border2,
+            segments: [Access::RWX; 3],
+            info: Access::RWX,
+            violation: Violation::Nmi,
+            lock: false,
+        }
+    }
+
+    /// Sets the permissions of segment 1, from the start of the main FRAM to `border1`
+    pub const fn segment1(mut self, access: Access) -> Self {
+        self.segments[0] = access;
+        self
+    }
+
+    /// Sets the permissions of segment 2, from `border1` to `border2`
+    pub const fn segment2(mut self, access: Access) -> Self {
+        self.segments[1] = access;
+        self
+    }
+
+    /// Sets the permissions of segment 3, from `border2` to the end of the main FRAM
+    pub const fn segment3(mut self, access: Access) -> Self {
+        self.segments[2] = access;
+        self
+    }
+
+    /// Sets the permissions of the information memory
+    pub const fn info(mut self, access: Access) -> Self {
+        self.info = access;
+        self
+    }
+
+    /// Selects what a violation does
+    pub const fn on_violation(mut self, violation: Violation) -> Self {
+        self.violation = violation;
+        self
+    }
+
+    /// Locks the configuration until the next BOR, so that it can't be changed at runtime
+    pub const fn lock(mut self) -> Self {
+        self.lock = true;
+        self
+    }
+}
+
+// Registers of the MPU
+const MPUCTL0: usize = 0x05A0;
+const MPUCTL1: usize = 0x05A2;
+const MPUSEGB2: usize = 0x05A4;
+const MPUSEGB1: usize = 0x05A6;
+const MPUSAM: usize = 0x05A8;
+
+const MPUPW: u16 = 0xA5 << 8;
+const MPUENA: u16 = 1 << 0;
+const MPULOCK: u16 = 1 << 1;
+const MPUSEGIE: u16 = 1 << 4;
+
+/// Violation select bit of a segment in `MPUSAM`: PUC instead of NMI
+const MPUSEGVS: u16 = 1 << 3;
+
+/// Interrupt flags of the segments in `MPUCTL1`, in the order of `SEGMENTS`
+const MPUSEGIFG: [u16; 4] = [1 << 0, 1 << 1, 1 << 2, 1 << 3];
+const SEGMENTS: [Segment; 4] = [Segment::One, Segment::Two, Segment::Three, Segment::Info];
+
+/// The configuration declared with `mpu_config!`, or the one of the `readonly-text` feature
+fn config() -> Config {
+    #[cfg(feature = "readonly-text")]
+    {
+        extern "C" {
+            // Absolute symbols; see `link.x`
+            static __readonly_text_border1: u8;
+            static __readonly_text_border2: u8;
+        }
+
+        let addr = |symbol: &u8| symbol as *const u8 as usize as u32;
+        unsafe {
+            Config::new(
+                addr(&__readonly_text_border1),
+                addr(&__readonly_text_border2),
+            )
+            .segment1(Access::RX)
+            .segment2(Access::R)
+            .segment3(Access::RW)
+            .info(Access::RW)
+        }
+    }
+
+    #[cfg(not(feature = "readonly-text"))]
+    {
+        extern "Rust" {
+            static __MPU_CONFIG: Config;
+        }
+
+        unsafe { __MPU_CONFIG }
+    }
+}
+
+/// Applies the configuration declared with `mpu_config!`
+pub(crate) unsafe fn init() {
+    let config = &config();
+    let vs = match config.violation {
+        Violation::Nmi => 0,
+        Violation::Reset => MPUSEGVS,
+    };
+    let mut sam = (config.info.bits() | vs) << 12;
+    for (i, segment) in config.segments.iter().enumerate() {
+        sam |= (segment.bits() | vs) << (4 * i);
+    }
+
+    let mut ctl0 = MPUPW | MPUENA;
+    if config.violation == Violation::Nmi {
+        ctl0 |= MPUSEGIE;
+    }
+    if config.lock {
+        ctl0 |= MPULOCK;
+    }
+
+    write(MPUCTL0, MPUPW);
+    // The borders hold bits 19..4 of the addresses
+    write(MPUSEGB1, (config.border1 >> 4) as u16);
+    write(MPUSEGB2, (config.border2 >> 4) as u16);
+    write(MPUSAM, sam);
+    write(MPUCTL0, ctl0);
+    close();
+}
+
+/// Disables the MPU, unless its configuration is locked, and returns the previous `MPUCTL0`
+#[cfg(feature = "readonly-text")]
+pub(crate) unsafe fn suspend() -> u16 {
+    let ctl0 = read(MPUCTL0) & 0xFF;
+    write(MPUCTL0, MPUPW | (ctl0 & !MPUENA));
+    close();
+    ctl0
+}
+
+/// Restores the `MPUCTL0` returned by `suspend`
+#[cfg(feature = "readonly-text")]
+pub(crate) unsafe fn resume(ctl0: u16) {
+    write(MPUCTL0, MPUPW | ctl0);
+    close();
+}
+
+/// Returns the first violation flagged by the MPU, if any, and clears its flag
+pub fn take_fault() -> Option<Fault> {
+    unsafe {
+        let flags = read(MPUCTL1);
+        let i = MPUSEGIFG.iter().position(|ifg| flags & ifg != 0)?;
+
+        // Open the registers, keeping the configuration in the lower byte, and close them again
+        write(MPUCTL0, MPUPW | read(MPUCTL0) & 0xFF);
+        write(MPUCTL1, flags & !MPUSEGIFG[i]);
+        close();
+
+        let config = config();
+        let segment = SEGMENTS[i];
+        let permitted = match segment {
+            Segment::Info => config.info,
+            _ => config.segments[i],
+        };
+        Some(Fault { segment, permitted })
+    }
+}
+
+// Handler of the system NMI, bound to `SYSNMI` when the `mpu` or `readonly-text` feature is
+// enabled
+#[cfg(target_arch = "msp430")]
+#[no_mangle]
+extern "msp430-interrupt" fn MpuNmi() {
+    extern "Rust" {
+        fn MpuViolation(fault: Fault);
+    }
+
+    while let Some(fault) = take_fault() {
+        unsafe { MpuViolation(fault) };
+    }
+}
+
+/// Code recorded in the black box by the default `MpuViolation`, plus the number of the segment
+#[cfg(feature = "blackbox")]
+const BLACKBOX_MPU_VIOLATION: u16 = 0xFA00;
+
+#[allow(non_snake_case)]
+#[no_mangle]
+fn MpuViolation_(_fault: Fault) {
+    #[cfg(feature = "blackbox")]
+    {
+        let segment = match _fault.segment {
+            Segment::One => 1,
+            Segment::Two => 2,
+            Segment::Three => 3,
+            Segment::Info => 4,
+        };
+        crate::blackbox::record(BLACKBOX_MPU_VIOLATION + segment);
+        crate::blackbox::seal();
+    }
+
+    crate::park()
+}
+
+/// Disables the access to the registers until the password is written again
+unsafe fn close() {
+    // Writing anything but the password to the upper byte of `MPUCTL0` does that
+    core::ptr::write_volatile((MPUCTL0 + 1) as *mut u8, 0);
+}
+
+unsafe fn read(register: usize) -> u16 {
+    core::ptr::read_volatile(register as *const u16)
+}
+
+unsafe fn write(register: usize, value: u16) {
+    core::ptr::write_volatile(register as *mut u16, value);
+}
+
+/// Declares the configuration of the MPU, applied by the reset handler
+///
+/// **IMPORTANT**: This macro must appear exactly *once* in the dependency graph when the `mpu`
+/// feature is enabled.
+///
+/// The argument is a constant expression of type `mpu::Config`.
+#[macro_export]
+macro_rules! mpu_config {
+    ($config:expr) => {
+        #[no_mangle]
+        static __MPU_CONFIG: $crate::mpu::Config = $config;
+    };
+}
@@ -0,0 +1,89 @@
+//! Power failure handling
+//!
+//! When the supply is about to fail, e.g. as signalled by a comparator or by the supply voltage
+//! supervisor, the program has the hold-up time of its capacitors left to save what matters.
+//! `power_fail_interrupt!` designates the interrupt that signals it; its handler runs every
+//! callback registered with `power_fail_flush!`, from the highest priority (0) to the lowest (9),
+//! so that the most important work is done first should the time run out:
+//!
+//! ``` ignore
+//! // In the application
+//! msp430_rt::power_fail_interrupt!(COMP_E);
+//!
+//! // Save the measurements first
+//! fn save_measurements() {
+//!     // ..
+//! }
+//!
+//! msp430_rt::power_fail_flush!(0, save_measurements);
+//!
+//! // In a motor driver, then park the outputs
+//! msp430_rt::power_fail_flush!(1, motor::park);
+//! ```
+//!
+//! Once all the callbacks have run the handler calls the function `PowerFailed`, which never
+//! returns. The default loops forever with the interrupts disabled until the supply runs out or
+//! the watchdog resets the device; define it to, e.g., reset the device if the supply recovers.
+//! Callbacks of the same priority run in link order and may only assume that those of the higher
+//! priorities have run. A program that handles the interrupt itself, e.g. the system NMI of the
+//! SVS, can call `flush` from its handler instead.
+
+/// Runs the callbacks registered with `power_fail_flush!`, by priority
+pub fn flush() {
+    // Sorted by section name, i.e. by priority
+    for callback in crate::linker_slice!(".power_fail", fn()) {
+        callback();
+    }
+}
+
+#[doc(hidden)]
+pub fn failed() -> ! {
+    extern "C" {
+        fn PowerFailed() -> !;
+    }
+
+    unsafe { PowerFailed() }
+}
+
+#[no_mangle]
+extern "C" fn PowerFailed_() -> ! {
+    crate::park()
+}
+
+/// Defines the handler of interrupt `$vector` as the power failure handler, which runs the
+/// callbacks registered with `power_fail_flush!` and then calls `PowerFailed`
+///
+/// **IMPORTANT**: This macro can appear at most *once* in the dependency graph, and the interrupt
+/// must not also be handled with `#[interrupt]`.
+///
+/// Like `#[interrupt]` this macro defines an `extern "msp430-interrupt"` function, so the crate
+/// that uses it needs `#![feature(abi_msp430_interrupt)]`. On other targets than MSP430 it
+/// doesn't define anything. See the `power` module for an example.
+#[macro_export]
+macro_rules! power_fail_interrupt {
+    ($vector:ident) => {
+        #[cfg(target_arch = "msp430")]
+        #[allow(non_snake_case)]
+        #[no_mangle]
+        extern "msp430-interrupt" fn $vector() {
+            $crate::power::flush();
+            $crate::power::failed()
+        }
+    };
+}
+
+/// Registers `$callback`, a plain `fn()`, to run when power fails, with priority `$priority`
+///
+/// The priority is a literal from 0, the highest, to 9. See the `power` module for an example.
+#[macro_export]
+macro_rules! power_fail_flush {
+    ($priority:literal, $callback:expr) => {
+        const _: () = {
+            assert!($priority <= 9, "power failure priorities go from 0 to 9");
+
+            #[link_section = concat!(".power_fail.1.", $priority)]
+            #[used]
+            static CALLBACK: fn() = $callback;
+        };
+    };
+}
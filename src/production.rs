@@ -0,0 +1,87 @@
+//! Production test mode
+//!
+//! Manufacturing test firmware can share the image of the product: with the `production-test`
+//! feature the reset handler calls the function `ProductionTest`, which never returns, instead of
+//! going on to the constructors and `main` when either:
+//!
+//! - on FRAM devices, the flag set by `request` is set. It's kept in `.persistent` FRAM, so it
+//! survives resets until `clear` is called, e.g. by `ProductionTest` once the device has passed;
+//! or
+//! - the pin given by the `__production_test_in` symbol, the address of its `PxIN` register, and by
+//! the `__production_test_mask` symbol, its bit, reads `__production_test_level`: 0 for low, 1 for
+//! high. The default mask, 0, selects no pin.
+//!
+//! `.bss` and `.data` have been initialized by then, but the `#[ctor]` functions haven't run. The
+//! default `ProductionTest` loops forever; the program defines its own:
+//!
+//! ``` ignore
+//! #[no_mangle]
+//! extern "C" fn ProductionTest() -> ! {
+//!     // drive the test jig
+//!     msp430_rt::production::clear();
+//!     msp430_rt::reset::software_reset()
+//! }
+//! ```
+//!
+//! ``` text
+//! /* memory.x: the test jig pulls P2.3 (P2IN = 0x0201 on FR5xx devices) low */
+//! __production_test_in = 0x0201;
+//! __production_test_mask = 0x08;
+//! __production_test_level = 0;
+//! ```
+//!
+//! On devices with LPMx.5, e.g. FRAM devices, the pins are locked after a reset until `LOCKLPM5`
+//! is cleared, which the `#[pre_init]` function can do.
+
+use core::ptr;
+
+use crate::fram;
+
+/// Value of the flag when the test mode is requested
+const REQUESTED: u16 = 0x7E57;
+
+#[link_section = ".persistent"]
+static mut FLAG: u16 = 0;
+
+/// Makes the following resets enter the production test mode, until `clear` is called
+pub fn request() {
+    fram::unprotected(|| unsafe { ptr::write_volatile(ptr::addr_of_mut!(FLAG), REQUESTED) });
+}
+
+/// Clears the flag set by `request`
+pub fn clear() {
+    fram::unprotected(|| unsafe { ptr::write_volatile(ptr::addr_of_mut!(FLAG), 0) });
+}
+
+/// Returns `true` if the flag set by `request` is set
+pub fn requested() -> bool {
+    unsafe { ptr::read_volatile(ptr::addr_of!(FLAG)) == REQUESTED }
+}
+
+/// Enters the test mode if the flag or the pin select it
+pub(crate) unsafe fn check() {
+    extern "C" {
+        // Absolute symbols; see the module documentation
+        static __production_test_in: u8;
+        static __production_test_mask: u8;
+        static __production_test_level: u8;
+
+        fn ProductionTest() -> !;
+    }
+
+    let mask = ptr::addr_of!(__production_test_mask) as usize as u8;
+    let pin = mask != 0 && {
+        let input = ptr::addr_of!(__production_test_in) as usize as *const u8;
+        let high = ptr::read_volatile(input) & mask != 0;
+        high == (ptr::addr_of!(__production_test_level) as usize != 0)
+    };
+
+    if pin || requested() {
+        ProductionTest()
+    }
+}
+
+#[no_mangle]
+extern "C" fn ProductionTest_() -> ! {
+    crate::park()
+}
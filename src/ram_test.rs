@@ -0,0 +1,93 @@
+//! Power-on RAM self-test
+//!
+//! A March C- test, run word by word on `.bss` and on the RAM between the end of `.got` (the start
+//! of the heap) and the stack of the reset handler:
+//!
+//! ``` text
+//! any(w0); up(r0, w1); up(r1, w0); down(r0, w1); down(r1, w0); any(r0)
+//! ```
+//!
+//! where 0 and 1 are all-zeros and all-ones words. This detects stuck-at, transition and most
+//! coupling faults between words. `.user_ram` is skipped, so that its contents survive resets, and
+//! so is `.data`.
+
+use core::ptr::{read_volatile, write_volatile};
+
+/// Bytes of the stack, below the frame of `run`, that are left untested because the test itself
+/// and the failure hook use them
+const STACK_MARGIN: usize = 64;
+
+/// Tests the RAM and calls `RamTestFailed` on the first faulty word
+pub(crate) unsafe fn run() {
+    extern "C" {
+        static mut _sbss: u16;
+        static mut _ebss: u16;
+        static mut _sheap: u16;
+
+        fn RamTestFailed(address: usize) -> !;
+    }
+
+    // The address of a local variable approximates the stack pointer
+    let top = 0u16;
+    let top = (&top as *const u16 as usize - STACK_MARGIN) & !1;
+
+    let ranges = [
+        (
+            core::ptr::addr_of_mut!(_sbss),
+            core::ptr::addr_of_mut!(_ebss),
+        ),
+        (core::ptr::addr_of_mut!(_sheap), top as *mut u16),
+    ];
+    for (start, end) in ranges {
+        if start < end {
+            if let Err(address) = march(start, end) {
+                RamTestFailed(address as usize)
+            }
+        }
+    }
+}
+
+/// Runs the March C- test on the words from `start` to `end`; returns the first faulty word
+#[inline(never)]
+unsafe fn march(start: *mut u16, end: *mut u16) -> Result<(), *mut u16> {
+    const ZERO: u16 = 0x0000;
+    const ONE: u16 = 0xFFFF;
+
+    // Reads `expected` from every word and then writes `value` to it, going up or down
+    let element = |up: bool, expected: u16, value: u16| -> Result<(), *mut u16> {
+        let len = end.offset_from(start) as usize;
+        for i in 0..len {
+            let word = if up { start.add(i) } else { end.sub(i + 1) };
+            if read_volatile(word) != expected {
+                return Err(word);
+            }
+            write_volatile(word, value);
+        }
+        Ok(())
+    };
+
+    let mut word = start;
+    while word < end {
+        write_volatile(word, ZERO);
+        word = word.add(1);
+    }
+
+    element(true, ZERO, ONE)?;
+    element(true, ONE, ZERO)?;
+    element(false, ZERO, ONE)?;
+    element(false, ONE, ZERO)?;
+
+    let mut word = start;
+    while word < end {
+        if read_volatile(word) != ZERO {
+            return Err(word);
+        }
+        word = word.add(1);
+    }
+    Ok(())
+}
+
+#[no_mangle]
+extern "C" fn RamTestFailed_(_address: usize) -> ! {
+    crate::park()
+}
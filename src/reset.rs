@@ -0,0 +1,195 @@
+//! Device resets
+
+/// Resets the device
+///
+/// This writes `0xA504` to address `0x0120`. On devices with a PMM module (F5xx/F6xx and FRAM
+/// families) that register is `PMMCTL0` and the value requests a software brownout reset. On older
+/// families (e.g. G2xx) that register is `WDTCTL` and the value is a watchdog password violation,
+/// which triggers a power-up clear.
+pub fn software_reset() -> ! {
+    unsafe { core::ptr::write_volatile(0x0120 as *mut u16, 0xA504) };
+
+    // The reset takes effect immediately; this loop is never reached
+    loop {
+        msp430::asm::barrier();
+    }
+}
+
+/// Cause of a reset, as reported by the `SYSRSTIV` register
+///
+/// Enabled by the `reset-counters` feature, which makes the reset handler count the resets of each
+/// cause in `.persistent` FRAM, or in the information memory of flash devices, so that the counts
+/// survive power cycles.
+#[cfg(feature = "reset-counters")]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Cause {
+    /// Brownout, including power-up
+    Brownout,
+    /// The `RST` pin
+    Pin,
+    /// Supply voltage supervisor
+    Svs,
+    /// Watchdog timeout or password violation
+    Watchdog,
+    /// Software BOR or POR, e.g. `software_reset`
+    Software,
+    /// Security violation
+    SecurityViolation,
+    /// Any other cause, e.g. a wakeup from LPMx.5 or a password violation of another module
+    Other,
+}
+
+#[cfg(feature = "reset-counters")]
+impl Cause {
+    /// Number of causes
+    const COUNT: usize = 7;
+
+    fn of(sysrstiv: u16) -> Self {
+        match sysrstiv {
+            0x02 => Cause::Brownout,
+            0x04 => Cause::Pin,
+            0x06 | 0x14 => Cause::Software,
+            0x0A => Cause::SecurityViolation,
+            0x0C | 0x0E => Cause::Svs,
+            0x16 | 0x18 => Cause::Watchdog,
+            _ => Cause::Other,
+        }
+    }
+}
+
+/// Resets counted per cause and the value of `SYSRSTIV` of the last reset
+#[cfg(feature = "reset-counters")]
+type Counts = ([u16; Cause::COUNT], u16);
+
+/// The counters, in `.persistent` FRAM, or in a segment of the information memory of their own on
+/// flash devices
+#[cfg(feature = "reset-counters")]
+#[cfg_attr(
+    not(any(msp430_family = "f5", msp430_family = "f6")),
+    link_section = ".persistent"
+)]
+#[cfg_attr(
+    any(msp430_family = "f5", msp430_family = "f6"),
+    link_section = ".reset_counts"
+)]
+static mut COUNTS: Counts = ([0; Cause::COUNT], 0);
+
+/// Returns the number of resets due to `cause`, saturated at `u16::MAX`
+#[cfg(feature = "reset-counters")]
+pub fn count(cause: Cause) -> u16 {
+    unsafe { core::ptr::read_volatile(core::ptr::addr_of!(COUNTS.0[cause as usize])) }
+}
+
+/// Returns the main cause of the last reset, if it was recorded
+///
+/// The reset handler reads, and thereby clears, the `SYSRSTIV` register; use this instead.
+#[cfg(feature = "reset-counters")]
+pub fn last_cause() -> Option<Cause> {
+    match last_sysrstiv() {
+        0 => None,
+        sysrstiv => Some(Cause::of(sysrstiv)),
+    }
+}
+
+/// Returns the value of `SYSRSTIV` recorded for the last reset, 0 if none
+#[cfg(feature = "reset-counters")]
+pub(crate) fn last_sysrstiv() -> u16 {
+    unsafe { core::ptr::read_volatile(core::ptr::addr_of!(COUNTS.1)) }
+}
+
+/// Sets the counters of all the causes back to 0
+#[cfg(feature = "reset-counters")]
+pub fn clear_counts() {
+    let last = unsafe { core::ptr::read_volatile(core::ptr::addr_of!(COUNTS.1)) };
+    msp430::interrupt::free(|_| unsafe { store(&([0; Cause::COUNT], last)) });
+}
+
+/// Counts the causes of the reset flagged in `SYSRSTIV`; called by the reset handler
+///
+/// Reading `SYSRSTIV` clears the flags of the causes, `PMMLPM5IFG` included, so this runs after
+/// `lpm5::restore`.
+#[cfg(feature = "reset-counters")]
+pub(crate) unsafe fn count_causes() {
+    extern "C" {
+        // Absolute symbol; its address is the address of the `SYSRSTIV` register
+        static __sysrstiv: u8;
+    }
+
+    let sysrstiv = core::ptr::addr_of!(__sysrstiv) as usize as *const u16;
+    let mut counts = core::ptr::read_volatile(core::ptr::addr_of!(COUNTS));
+    // Each read returns the pending cause of highest priority and clears it
+    let mut last = 0;
+    loop {
+        let value = core::ptr::read_volatile(sysrstiv);
+        if value == 0 {
+            break;
+        }
+        if last == 0 {
+            last = value;
+        }
+
+        let count = &mut counts.0[Cause::of(value) as usize];
+        *count = count.saturating_add(1);
+    }
+    counts.1 = last;
+    store(&counts);
+}
+
+/// Writes `counts` to the FRAM of the counters
+#[cfg(all(
+    feature = "reset-counters",
+    not(any(msp430_family = "f5", msp430_family = "f6"))
+))]
+unsafe fn store(counts: &Counts) {
+    crate::fram::unprotected(|| {
+        core::ptr::write_volatile(core::ptr::addr_of_mut!(COUNTS), *counts)
+    });
+}
+
+/// Erases the flash segment of the counters and programs `counts` into it
+///
+/// The flash controller holds the CPU until each operation completes, so this can run from flash.
+/// The interrupts must be disabled.
+#[cfg(all(
+    feature = "reset-counters",
+    any(msp430_family = "f5", msp430_family = "f6")
+))]
+unsafe fn store(counts: &Counts) {
+    use core::ptr::{addr_of, addr_of_mut, write_volatile};
+
+    extern "C" {
+        // Absolute symbol; its address is the address of the `WDTCTL` register
+        static __wdtctl: u8;
+    }
+
+    // Registers of the flash controller and their password, erase, write and lock bits
+    const FCTL1: *mut u16 = 0x0140 as *mut u16;
+    const FCTL3: *mut u16 = 0x0144 as *mut u16;
+    const FWKEY: u16 = 0xA5 << 8;
+    const ERASE: u16 = 1 << 1;
+    const WRT: u16 = 1 << 6;
+    const LOCK: u16 = 1 << 4;
+    // Password, hold and counter clear bits of `WDTCTL`
+    const WDTPW: u16 = 0x5A << 8;
+    const WDTHOLD: u16 = 1 << 7;
+    const WDTCNTCL: u16 = 1 << 3;
+
+    // The erase takes longer than the shortest watchdog interval
+    let wdtctl = addr_of!(__wdtctl) as usize as *mut u16;
+    let watchdog = core::ptr::read_volatile(wdtctl) & 0xFF;
+    write_volatile(wdtctl, WDTPW | watchdog | WDTHOLD);
+
+    let words = addr_of_mut!(COUNTS) as *mut u16;
+    write_volatile(FCTL3, FWKEY);
+    // A dummy write erases the segment
+    write_volatile(FCTL1, FWKEY | ERASE);
+    write_volatile(words, 0);
+    write_volatile(FCTL1, FWKEY | WRT);
+    for (i, &word) in counts.0.iter().chain(Some(&counts.1)).enumerate() {
+        write_volatile(words.add(i), word);
+    }
+    write_volatile(FCTL1, FWKEY);
+    write_volatile(FCTL3, FWKEY | LOCK);
+
+    write_volatile(wdtctl, WDTPW | watchdog | WDTCNTCL);
+}
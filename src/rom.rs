@@ -0,0 +1,108 @@
+//! Routines of the driver library in ROM
+//!
+//! Some devices, e.g. parts of the FR2xx/FR4xx families, contain TI's MSP430 DriverLib in ROM.
+//! Calling the routines in ROM instead of linking a copy of the library saves program memory.
+//!
+//! The routines are located at fixed addresses that depend on the device and on the version of
+//! the library. Each routine is reached through a `__rom_<routine>` symbol whose value, copied from
+//! the ROM DriverLib package of the device, must be defined in `memory.x`; only the routines that
+//! are called need one:
+//!
+//! ``` text
+//! /* memory.x */
+//! __rom_GPIO_setAsOutputPin = 0x....;
+//! __rom_GPIO_toggleOutputOnPin = 0x....;
+//! ```
+//!
+//! This module provides typed, safe wrappers around common routines. Other routines can be
+//! declared with `rom_import!`.
+//!
+//! ``` ignore
+//! use msp430_rt::rom::{self, GPIO_PIN0, GPIO_PORT_P1};
+//!
+//! rom::pmm_unlock_lpm5();
+//! rom::gpio_set_as_output_pin(GPIO_PORT_P1, GPIO_PIN0);
+//! loop {
+//!     rom::gpio_toggle_output_on_pin(GPIO_PORT_P1, GPIO_PIN0);
+//! }
+//! ```
+
+/// Declares typed wrappers around routines located in ROM
+///
+/// Each routine is given as `fn name(args) -> ret = symbol;`, where `symbol` is the linker symbol
+/// that holds the address of the routine, usually defined in `memory.x`. The routines must use
+/// the C calling convention, which is the case for the routines of TI's libraries.
+///
+/// The wrappers are safe functions, so only declare routines that can't break memory safety
+/// whatever their arguments are.
+///
+/// # Examples
+///
+/// ``` ignore
+/// msp430_rt::rom_import! {
+///     /// Disables the watchdog timer
+///     pub fn wdt_a_hold(base_address: u16) = __rom_WDT_A_hold;
+/// }
+/// ```
+#[macro_export]
+macro_rules! rom_import {
+    ($($(#[$attr:meta])* $vis:vis fn $name:ident($($arg:ident: $ty:ty),*) $(-> $ret:ty)? =
+       $symbol:ident;)*) => {
+        $(
+            $(#[$attr])*
+            $vis fn $name($($arg: $ty),*) $(-> $ret)? {
+                extern "C" {
+                    // Provided by `memory.x`; the routine lives in ROM
+                    #[allow(non_snake_case)]
+                    fn $symbol($($arg: $ty),*) $(-> $ret)?;
+                }
+
+                unsafe { $symbol($($arg),*) }
+            }
+        )*
+    };
+}
+
+/// Port 1, as numbered by DriverLib
+pub const GPIO_PORT_P1: u8 = 1;
+/// Port 2
+pub const GPIO_PORT_P2: u8 = 2;
+/// Port 3
+pub const GPIO_PORT_P3: u8 = 3;
+/// Port 4
+pub const GPIO_PORT_P4: u8 = 4;
+
+/// Pin 0 of a port
+pub const GPIO_PIN0: u16 = 1 << 0;
+/// Pin 1
+pub const GPIO_PIN1: u16 = 1 << 1;
+/// Pin 2
+pub const GPIO_PIN2: u16 = 1 << 2;
+/// Pin 3
+pub const GPIO_PIN3: u16 = 1 << 3;
+/// Pin 4
+pub const GPIO_PIN4: u16 = 1 << 4;
+/// Pin 5
+pub const GPIO_PIN5: u16 = 1 << 5;
+/// Pin 6
+pub const GPIO_PIN6: u16 = 1 << 6;
+/// Pin 7
+pub const GPIO_PIN7: u16 = 1 << 7;
+
+crate::rom_import! {
+    /// Configures `pins` of `port` as outputs (`GPIO_setAsOutputPin`)
+    pub fn gpio_set_as_output_pin(port: u8, pins: u16) = __rom_GPIO_setAsOutputPin;
+    /// Configures `pins` of `port` as inputs (`GPIO_setAsInputPin`)
+    pub fn gpio_set_as_input_pin(port: u8, pins: u16) = __rom_GPIO_setAsInputPin;
+    /// Drives `pins` of `port` high (`GPIO_setOutputHighOnPin`)
+    pub fn gpio_set_output_high_on_pin(port: u8, pins: u16) = __rom_GPIO_setOutputHighOnPin;
+    /// Drives `pins` of `port` low (`GPIO_setOutputLowOnPin`)
+    pub fn gpio_set_output_low_on_pin(port: u8, pins: u16) = __rom_GPIO_setOutputLowOnPin;
+    /// Toggles `pins` of `port` (`GPIO_toggleOutputOnPin`)
+    pub fn gpio_toggle_output_on_pin(port: u8, pins: u16) = __rom_GPIO_toggleOutputOnPin;
+    /// Returns 1 if the input `pin` of `port` is high, 0 otherwise (`GPIO_getInputPinValue`)
+    pub fn gpio_get_input_pin_value(port: u8, pin: u16) -> u8 = __rom_GPIO_getInputPinValue;
+    /// Clears `LOCKLPM5`, which activates the configuration of the pins after a reset
+    /// (`PMM_unlockLPM5`)
+    pub fn pmm_unlock_lpm5() = __rom_PMM_unlockLPM5;
+}
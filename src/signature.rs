@@ -0,0 +1,124 @@
+//! Device signature words
+//!
+//! FRAM (FR2xx, FR4xx, FR5xx and FR6xx) devices read a handful of configuration words located
+//! right below the interrupt vector table during boot. These words control, among other things,
+//! whether the bootloader (BSL) may be invoked and whether the JTAG/SBW debug interface is locked.
+
+/// Value that, written to both JTAG signature words, permanently locks the JTAG/SBW interface
+pub const JTAG_LOCK: u16 = 0x5555;
+
+/// Value of the first JTAG signature word that locks the JTAG/SBW interface with a password
+///
+/// The second signature word then holds the length of the password, in words.
+pub const JTAG_LOCK_PASSWORD: u16 = 0xAAAA;
+
+/// Value of an erased (unprogrammed) signature word; the JTAG/SBW interface stays unlocked
+pub const JTAG_UNLOCK: u16 = 0xFFFF;
+
+/// Address of the first JTAG signature word. The second word follows at
+/// `JTAG_SIGNATURE_ADDRESS + 2`
+pub const JTAG_SIGNATURE_ADDRESS: u16 = 0xFF80;
+
+/// Value that, written to both BSL signature words, disables the BSL
+pub const BSL_DISABLE: u16 = 0x5555;
+
+/// Value of an erased (unprogrammed) signature word; the BSL stays enabled
+pub const BSL_ENABLE: u16 = 0xFFFF;
+
+/// Address of the first BSL signature word. The second word follows at `BSL_SIGNATURE_ADDRESS + 2`
+pub const BSL_SIGNATURE_ADDRESS: u16 = 0xFF84;
+
+/// Value of the first IPE signature word that makes the bootcode set up IP Encapsulation
+///
+/// The second signature word then holds the address of the IPE init structure, shifted right by
+/// 4. With the `ipe` feature both words are filled in by `msp430-rt`.
+pub const IPE_STRUCTURE_VALID: u16 = 0xAAAA;
+
+/// Address of the first IPE signature word. The second word follows at `IPE_SIGNATURE_ADDRESS + 2`
+pub const IPE_SIGNATURE_ADDRESS: u16 = 0xFF88;
+
+#[cfg(feature = "ipe")]
+#[repr(C)]
+struct IpeSignature(u16, *const u8);
+
+#[cfg(feature = "ipe")]
+unsafe impl Sync for IpeSignature {}
+
+// The address of `__ipe_init_shifted` is the second word; see `ipe.x`
+#[cfg(feature = "ipe")]
+#[link_section = ".ipe_signature"]
+#[used]
+static IPE_SIGNATURE: IpeSignature = {
+    extern "C" {
+        static __ipe_init_shifted: u8;
+    }
+
+    IpeSignature(IPE_STRUCTURE_VALID, unsafe { &__ipe_init_shifted })
+};
+
+/// Places the two BSL signature words at `BSL_SIGNATURE_ADDRESS`
+///
+/// **IMPORTANT**: This macro can appear at most *once* in the dependency graph.
+///
+/// The words are placed in the `.bsl_signature` section, which the linker script locates at
+/// `0xFF84`. Linking fails if that section ends up anywhere else or has an unexpected size.
+///
+/// # Examples
+///
+/// ``` ignore
+/// use msp430_rt::{bsl_signature, signature::BSL_DISABLE};
+///
+/// // Make the BSL unreachable, e.g. for production firmware
+/// bsl_signature!(BSL_DISABLE, BSL_DISABLE);
+/// ```
+#[macro_export]
+macro_rules! bsl_signature {
+    ($sig1:expr, $sig2:expr) => {
+        #[link_section = ".bsl_signature"]
+        #[no_mangle]
+        #[used]
+        static __BSL_SIGNATURE: [u16; 2] = [$sig1, $sig2];
+    };
+}
+
+/// Places the two JTAG signature words at `JTAG_SIGNATURE_ADDRESS`
+///
+/// **IMPORTANT**: Locking the JTAG/SBW interface can be *irreversible*. A device locked with
+/// `JTAG_LOCK` can no longer be debugged nor reprogrammed through JTAG/SBW; only the BSL (if not
+/// disabled) or the application itself can erase the signature again.
+///
+/// To prevent accidental use this macro is only available when the `jtag-lock` feature is
+/// enabled. The build script prints a warning every time a crate is built with that feature.
+///
+/// The words are placed in the `.jtag_signature` section, which the linker script locates at
+/// `0xFF80`. Linking fails if that section ends up anywhere else or has an unexpected size.
+///
+/// # Examples
+///
+/// ``` ignore
+/// use msp430_rt::{jtag_signature, signature::JTAG_LOCK};
+///
+/// // Fuse debug access for the production image
+/// jtag_signature!(JTAG_LOCK, JTAG_LOCK);
+/// ```
+#[cfg(feature = "jtag-lock")]
+#[macro_export]
+macro_rules! jtag_signature {
+    ($sig1:expr, $sig2:expr) => {
+        #[link_section = ".jtag_signature"]
+        #[no_mangle]
+        #[used]
+        static __JTAG_SIGNATURE: [u16; 2] = [$sig1, $sig2];
+    };
+}
+
+/// Places the two JTAG signature words at `JTAG_SIGNATURE_ADDRESS`
+///
+/// This macro requires the `jtag-lock` feature.
+#[cfg(not(feature = "jtag-lock"))]
+#[macro_export]
+macro_rules! jtag_signature {
+    ($($tt:tt)*) => {
+        compile_error!("`jtag_signature!` requires the `jtag-lock` feature of msp430-rt");
+    };
+}
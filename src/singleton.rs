@@ -0,0 +1,56 @@
+//! Values that can be borrowed mutably exactly once
+
+/// Creates a `&'static mut` to a value, the first time only
+///
+/// The expansion evaluates to `Some(&'static mut $ty)` holding the value of `$expr` the first
+/// time it runs, and to `None` every time after that, without evaluating `$expr` again. The check
+/// and the initialization run in a critical section (`msp430::interrupt::free`), so the reference
+/// is unique even if the macro runs from an interrupt handler too. The value lives in a `static`,
+/// which makes this useful for buffers, e.g. DMA buffers, that must outlive the driver borrowing
+/// them without taking space on the stack.
+///
+/// The value is written to the `static` once `$expr` has been evaluated, so `$expr` is still built
+/// on the stack first.
+///
+/// # Examples
+///
+/// ``` ignore
+/// use msp430_rt::singleton;
+///
+/// #[entry]
+/// fn main() -> ! {
+///     let buffer: &'static mut [u8; 64] = singleton!(: [u8; 64] = [0; 64]).unwrap();
+///     // `None` from now on
+///     assert!(singleton!(: [u8; 64] = [0; 64]).is_none());
+///
+///     // The `static` can be named, e.g. to tell it apart in the symbol table
+///     let rx = singleton!(RX_BUFFER: [u8; 32] = [0; 32]).unwrap();
+///     // ..
+/// }
+/// ```
+#[macro_export]
+macro_rules! singleton {
+    ($name:ident: $ty:ty = $expr:expr) => {
+        $crate::__msp430::interrupt::free(|_| {
+            static mut $name: (::core::mem::MaybeUninit<$ty>, bool) =
+                (::core::mem::MaybeUninit::uninit(), false);
+
+            #[allow(unsafe_code)]
+            let used = unsafe { $name.1 };
+            if used {
+                None
+            } else {
+                let expr = $expr;
+
+                #[allow(unsafe_code)]
+                unsafe {
+                    $name.1 = true;
+                    Some((*::core::ptr::addr_of_mut!($name.0)).write(expr))
+                }
+            }
+        })
+    };
+    (: $ty:ty = $expr:expr) => {
+        $crate::singleton!(VALUE: $ty = $expr)
+    };
+}
@@ -0,0 +1,187 @@
+//! Low-power sleep, enabled by the `sleep` feature
+//!
+//! `sleep` puts the device in the deepest low-power mode that keeps the clocks that are still in
+//! use running, until an interrupt handler calls `wake`. Drivers and wake sources declare the
+//! clocks they need while the CPU sleeps with `hold`, e.g. a UART that receives on SMCLK or a
+//! timer that runs from ACLK, and give them back with `release`; the program then no longer has
+//! to pick the LPM level by hand at every place it sleeps:
+//!
+//! ``` ignore
+//! use msp430_rt::sleep::{self, Clock};
+//!
+//! // the UART driver, while a reception is in progress
+//! sleep::hold(Clock::Smclk);
+//!
+//! #[interrupt]
+//! fn USCI_A0() {
+//!     // ..
+//!     sleep::wake();
+//! }
+//!
+//! // the idle loop: LPM0 while the UART holds SMCLK, LPM4 otherwise
+//! loop {
+//!     sleep::sleep();
+//!     // ..
+//! }
+//! ```
+//!
+//! A handler normally returns to the low-power mode it interrupted. With this feature every
+//! handler defined with `#[interrupt]` goes through a short stub that clears the low-power mode
+//! bits from the saved status register when the handler has called `wake`. Naked handlers and
+//! handlers that aren't defined with `#[interrupt]` can't wake the device.
+//!
+//! `sleep_for` sleeps for a number of milliseconds, based on a one-shot timer provided by the
+//! program, as the runtime has no timer of its own. The function `SleepTimerStart` must arm it to
+//! fire after the given time; its handler calls `timer_expired`. The timer is assumed to run from
+//! ACLK, which `sleep_for` holds while it sleeps. `SleepTimerStart` has no default, so a program
+//! that uses `sleep_for` without defining it doesn't link:
+//!
+//! ``` ignore
+//! #[no_mangle]
+//! extern "C" fn SleepTimerStart(ms: u16) {
+//!     // e.g. set up Timer_A1 in up mode on ACLK, with CCR0 = ms * 32768 / 1000
+//! }
+//!
+//! #[interrupt]
+//! fn TIMER1_A0() {
+//!     // stop the timer
+//!     msp430_rt::sleep::timer_expired();
+//! }
+//! ```
+
+#[cfg(target_arch = "msp430")]
+use core::arch::asm;
+use core::ptr;
+
+use msp430::interrupt;
+
+/// A clock that must keep running while the CPU sleeps
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Clock {
+    /// The subsystem master clock; limits the device to LPM0
+    Smclk,
+    /// The auxiliary clock; limits the device to LPM3
+    Aclk,
+}
+
+/// A low-power mode
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Mode {
+    /// CPU and MCLK off; SMCLK and ACLK run
+    Lpm0,
+    /// CPU, MCLK, SMCLK and the DCO off; ACLK runs
+    Lpm3,
+    /// All the clocks off
+    Lpm4,
+}
+
+impl Mode {
+    /// Bits of the status register that enter the mode: CPUOFF, OSCOFF, SCG0 and SCG1
+    fn bits(self) -> u16 {
+        match self {
+            Mode::Lpm0 => 0x0010,
+            Mode::Lpm3 => 0x00D0,
+            Mode::Lpm4 => 0x00F0,
+        }
+    }
+}
+
+// `GIE` and all the low-power mode bits of the status register
+const GIE: u16 = 0x0008;
+const LPM_BITS: u16 = 0x00F0;
+
+// Number of holds of each clock, indexed by `Clock`
+static mut HOLDS: [u16; 2] = [0; 2];
+
+// Set by `wake` until `sleep` returns
+static mut PENDING: bool = false;
+static mut EXPIRED: bool = false;
+
+/// Low-power mode bits that the stub of the current handler clears from the saved status register
+#[doc(hidden)]
+pub static mut WAKE: u16 = 0;
+
+/// Keeps `clock` running while the CPU sleeps, until a matching `release`
+///
+/// Holds are counted, so every `hold` needs its own `release`.
+pub fn hold(clock: Clock) {
+    interrupt::free(|_| unsafe {
+        let holds = ptr::addr_of_mut!(HOLDS[clock as usize]);
+        *holds = (*holds).saturating_add(1);
+    });
+}
+
+/// Releases a hold of `clock` taken with `hold`
+pub fn release(clock: Clock) {
+    interrupt::free(|_| unsafe {
+        let holds = ptr::addr_of_mut!(HOLDS[clock as usize]);
+        *holds = (*holds).saturating_sub(1);
+    });
+}
+
+/// Returns the deepest low-power mode that keeps the held clocks running
+pub fn deepest() -> Mode {
+    let held = |clock: Clock| unsafe { ptr::read_volatile(ptr::addr_of!(HOLDS[clock as usize])) };
+
+    if held(Clock::Smclk) != 0 {
+        Mode::Lpm0
+    } else if held(Clock::Aclk) != 0 {
+        Mode::Lpm3
+    } else {
+        Mode::Lpm4
+    }
+}
+
+/// Sleeps in the deepest possible low-power mode until an interrupt handler calls `wake`
+///
+/// Returns right away if `wake` has been called since `sleep` last returned, so a wake-up that
+/// comes before the device goes to sleep isn't lost. The interrupts are enabled when this returns.
+pub fn sleep() {
+    interrupt::disable();
+    unsafe {
+        if !ptr::read_volatile(ptr::addr_of!(PENDING)) {
+            // Enabling the interrupts and entering the mode in a single instruction leaves no
+            // window for a wake-up to slip in between
+            #[cfg(target_arch = "msp430")]
+            asm!("bis r12, sr", "nop", in("r12") deepest().bits() | GIE);
+            interrupt::disable();
+        }
+        ptr::write_volatile(ptr::addr_of_mut!(PENDING), false);
+        interrupt::enable();
+    }
+}
+
+/// Wakes the device up from `sleep` once the current interrupt handler returns
+///
+/// This must be called from a handler defined with `#[interrupt]`.
+pub fn wake() {
+    unsafe {
+        ptr::write_volatile(ptr::addr_of_mut!(PENDING), true);
+        ptr::write_volatile(ptr::addr_of_mut!(WAKE), LPM_BITS);
+    }
+}
+
+/// Sleeps for `ms` milliseconds, as measured by the timer armed by `SleepTimerStart`
+///
+/// Other wake-ups in the meantime put the device back to sleep. See the module documentation.
+pub fn sleep_for(ms: u16) {
+    extern "C" {
+        fn SleepTimerStart(ms: u16);
+    }
+
+    hold(Clock::Aclk);
+    unsafe {
+        ptr::write_volatile(ptr::addr_of_mut!(EXPIRED), false);
+        SleepTimerStart(ms);
+    }
+    while !unsafe { ptr::read_volatile(ptr::addr_of!(EXPIRED)) } {
+        sleep();
+    }
+    release(Clock::Aclk);
+}
+
+/// Ends the `sleep_for` in progress; call it from the handler of the timer
+pub fn timer_expired() {
+    unsafe { ptr::write_volatile(ptr::addr_of_mut!(EXPIRED), true) };
+    wake();
+}
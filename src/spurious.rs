@@ -0,0 +1,75 @@
+//! Spurious interrupts
+//!
+//! An interrupt that has no handler, i.e. whose vector is bound to `DefaultHandler`, is handled
+//! according to the policy selected at build time with the `MSP430_RT_SPURIOUS_INTERRUPTS`
+//! environment variable (see "Spurious interrupts" in the crate documentation). With the `ignore`
+//! policy, and in release builds with `halt-in-debug`, `count` tells how many of them the program
+//! has ignored:
+//!
+//! ``` ignore
+//! if msp430_rt::spurious::count() != 0 {
+//!     // report it
+//!     msp430_rt::spurious::clear();
+//! }
+//! ```
+
+use core::ptr;
+
+static mut COUNT: u16 = 0;
+
+/// Returns the number of spurious interrupts ignored since the last reset, or since `clear`
+///
+/// The count saturates at `u16::MAX`.
+pub fn count() -> u16 {
+    unsafe { ptr::read_volatile(ptr::addr_of!(COUNT)) }
+}
+
+/// Resets the count of spurious interrupts to 0
+pub fn clear() {
+    unsafe { ptr::write_volatile(ptr::addr_of_mut!(COUNT), 0) };
+}
+
+/// `ignore` policy: counts the interrupt and returns
+#[cfg(target_arch = "msp430")]
+#[no_mangle]
+extern "msp430-interrupt" fn DefaultHandlerIgnore() {
+    // The interrupts are disabled here, so the increment can't be interrupted
+    unsafe {
+        let count = ptr::read_volatile(ptr::addr_of!(COUNT));
+        ptr::write_volatile(ptr::addr_of_mut!(COUNT), count.saturating_add(1));
+    }
+}
+
+/// `reset` policy and `reset-unused-vectors`: resets the device
+#[cfg(target_arch = "msp430")]
+#[no_mangle]
+extern "msp430-interrupt" fn DefaultHandlerReset() -> ! {
+    crate::reset::software_reset()
+}
+
+/// `hook` policy: calls `SpuriousInterrupt` and returns
+#[cfg(target_arch = "msp430")]
+#[no_mangle]
+extern "msp430-interrupt" fn DefaultHandlerHook() {
+    extern "C" {
+        fn SpuriousInterrupt();
+    }
+
+    unsafe { SpuriousInterrupt() }
+}
+
+/// `halt-in-debug` policy in debug builds: stops under a debugger, then loops like `loop`
+#[cfg(target_arch = "msp430")]
+#[no_mangle]
+extern "msp430-interrupt" fn DefaultHandlerHalt() -> ! {
+    #[cfg(feature = "blackbox")]
+    crate::blackbox::seal();
+
+    crate::breakpoint();
+    crate::park()
+}
+
+#[no_mangle]
+extern "C" fn SpuriousInterrupt_() {
+    crate::park()
+}
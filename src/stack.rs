@@ -0,0 +1,68 @@
+//! Stack overflow detection, enabled by the `stack-guard` feature
+
+/// Value of every word of the painted guard
+const PATTERN: u16 = 0xA5A5;
+
+/// Returns the bounds of the guard
+fn guard() -> (*mut u16, *mut u16) {
+    extern "C" {
+        static mut __stack_guard_start: u16;
+        static mut __stack_guard_end: u16;
+    }
+
+    (
+        core::ptr::addr_of_mut!(__stack_guard_start),
+        core::ptr::addr_of_mut!(__stack_guard_end),
+    )
+}
+
+/// Fills the guard with the pattern
+pub(crate) unsafe fn paint() {
+    let (mut word, end) = guard();
+    while word < end {
+        core::ptr::write_volatile(word, PATTERN);
+        word = word.add(1);
+    }
+}
+
+/// Checks that the stack hasn't overflowed into the guard region below it
+///
+/// The reset handler paints the guard, the lowest `_stack_guard_size` bytes the stack can grow
+/// into (8 by default), right above the heap, with a known pattern. A stack that overflows into
+/// the guard overwrites the pattern, which this function detects, e.g. when called from a
+/// periodic timer interrupt:
+///
+/// ``` ignore
+/// #[interrupt]
+/// fn TIMER0_A0() {
+///     msp430_rt::check_stack_guard();
+///     // ..
+/// }
+/// ```
+///
+/// On corruption the function `StackGuardCorrupted` is called, which never returns. The default
+/// loops forever with the interrupts disabled; define it to, e.g., record the fault and reset the
+/// device. The stack may already have overwritten other data by then, so `StackGuardCorrupted`
+/// shouldn't rely on `static` variables.
+///
+/// The guard is only checked when this function runs, so an overflow that skips over the guard,
+/// e.g. with a large local array, goes unnoticed. Reserve stack space with `_stack_size` (see
+/// "Stack and heap sizes") so that the linker checks that the guard fits in `RAM`.
+pub fn check_stack_guard() {
+    extern "C" {
+        fn StackGuardCorrupted() -> !;
+    }
+
+    let (mut word, end) = guard();
+    while word < end {
+        if unsafe { core::ptr::read_volatile(word) } != PATTERN {
+            unsafe { StackGuardCorrupted() }
+        }
+        word = unsafe { word.add(1) };
+    }
+}
+
+#[no_mangle]
+extern "C" fn StackGuardCorrupted_() -> ! {
+    crate::park()
+}
@@ -0,0 +1,53 @@
+//! Moving off the boot stack
+
+#[cfg(target_arch = "msp430")]
+use core::arch::asm;
+
+/// Moves the stack pointer to `new_top` and calls `entry` on the new stack
+///
+/// This is meant for RTOS ports and the like, which hand control over to a scheduler or a first
+/// task that runs on a stack of their own, rather than on the stack the reset handler set up. The
+/// stack grows down from `new_top`, which is rounded down to a word; `entry` runs with an empty
+/// stack, apart from the return address of the call, which is never used.
+///
+/// ``` ignore
+/// static mut IDLE_STACK: [u16; 64] = [0; 64];
+///
+/// fn scheduler() -> ! {
+///     // ..
+/// }
+///
+/// #[entry]
+/// fn main() -> ! {
+///     unsafe {
+///         let top = core::ptr::addr_of_mut!(IDLE_STACK).add(1) as *mut u8;
+///         msp430_rt::switch_stack(top, scheduler)
+///     }
+/// }
+/// ```
+///
+/// The frames of the callers are abandoned without being dropped, so nothing that lives on the
+/// old stack may be used afterwards. `check_stack_guard` and the heap checks of `_sbrk` assume that
+/// the stack is the one set up by the reset handler, right above the heap.
+///
+/// # Safety
+///
+/// `new_top` must be the end of a memory area that's large enough for everything that `entry`,
+/// and the interrupt handlers that interrupt it, push on the stack, and that nothing else uses.
+pub unsafe fn switch_stack(new_top: *mut u8, entry: fn() -> !) -> ! {
+    #[cfg(target_arch = "msp430")]
+    asm!(
+        "mov r12, sp",
+        "call r13",
+        in("r12") new_top as usize & !1,
+        in("r13") entry,
+        options(noreturn),
+    );
+
+    // Other targets have no stack to switch to
+    #[cfg(not(target_arch = "msp430"))]
+    {
+        let _ = new_top;
+        entry()
+    }
+}
@@ -0,0 +1,62 @@
+//! Startup time measurement, enabled by the `startup-time` feature
+
+// Control bits of `TA0CTL`: SMCLK, continuous mode and clear
+const TASSEL_SMCLK: u16 = 2 << 8;
+const MC_CONTINUOUS: u16 = 2 << 4;
+const TACLR: u16 = 1 << 2;
+
+/// Offset of `TA0R` from `TA0CTL`
+const TAR: usize = 0x10;
+
+static mut STARTUP_CYCLES: u16 = 0;
+
+/// Returns the address of `TA0CTL`
+fn ta0ctl() -> *mut u16 {
+    extern "C" {
+        // Absolute symbol; its address is the address of the `TA0CTL` register
+        static __timer_a0: u8;
+    }
+
+    core::ptr::addr_of!(__timer_a0) as usize as *mut u16
+}
+
+/// Starts Timer_A0 from 0, counting SMCLK cycles
+#[inline(always)]
+pub(crate) unsafe fn start() {
+    core::ptr::write_volatile(ta0ctl(), TASSEL_SMCLK | MC_CONTINUOUS | TACLR);
+}
+
+/// Records the count of Timer_A0 and puts the timer back in its reset state
+#[inline(always)]
+pub(crate) unsafe fn stop() {
+    let ta0ctl = ta0ctl();
+    let count = core::ptr::read_volatile((ta0ctl as usize + TAR) as *const u16);
+    core::ptr::write_volatile(ta0ctl, TACLR);
+    core::ptr::write_volatile(core::ptr::addr_of_mut!(STARTUP_CYCLES), count);
+}
+
+/// Returns the number of SMCLK cycles the reset handler took to get to `main`
+///
+/// With the `startup-time` feature the reset handler starts Timer_A0 right after the stack pointer
+/// has been set up, counting SMCLK cycles, and reads it back right before calling `main`, or
+/// `app_main` with the `c-main` feature. The timer is then stopped and cleared, so the program
+/// finds it in its reset state. After a reset SMCLK runs from the same clock as MCLK, so the
+/// count is the number of CPU cycles spent in the startup code, unless the `#[pre_init]` function
+/// changes the clocks. The count wraps after 65536 cycles.
+///
+/// This makes regressions of the boot time, e.g. from a growing `.data` section, measurable on
+/// the target:
+///
+/// ``` ignore
+/// #[entry]
+/// fn main() -> ! {
+///     assert!(msp430_rt::startup_cycles() < 2000);
+///     // ..
+/// }
+/// ```
+///
+/// The address of Timer_A0 is given by the `__timer_a0` symbol, which `MSP430_MCU` sets; the
+/// default, `0x0160`, matches the F1xx, F2xx, F4xx and G2xx families.
+pub fn startup_cycles() -> u16 {
+    unsafe { core::ptr::read_volatile(core::ptr::addr_of!(STARTUP_CYCLES)) }
+}
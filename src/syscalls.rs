@@ -0,0 +1,133 @@
+//! System calls of newlib
+//!
+//! C code that uses the standard library of msp430-elf-gcc (newlib, or newlib-nano) depends on a
+//! few system calls that a bare-metal program has to provide. With the `newlib-syscalls` feature
+//! this module provides defaults for them, which the program can still override by defining the
+//! function itself:
+//!
+//! - `_write` discards the output and reports it as written. Define it to send `printf` output
+//! somewhere, e.g. to a UART.
+//! - `_read` reports the end of the input.
+//! - `_exit` calls `Exit`, like `msp430_rt::exit`.
+//! - `_sbrk` hands out the memory between `_sheap` and `_eheap` (see "Stack and heap sizes") to
+//! `malloc`, and returns `-1` once it's exhausted. It calls `HeapCollision` if the stack has
+//! grown into the heap; see `check_heap_collision`.
+//!
+//! ``` ignore
+//! #[no_mangle]
+//! extern "C" fn _write(_fd: i16, buf: *const u8, len: usize) -> i16 {
+//!     let bytes = unsafe { core::slice::from_raw_parts(buf, len) };
+//!     // ..
+//!     len as i16
+//! }
+//! ```
+
+#[cfg(target_arch = "msp430")]
+use core::arch::asm;
+use core::ffi::c_int;
+
+#[no_mangle]
+extern "C" fn _write_(_fd: c_int, _buf: *const u8, len: usize) -> c_int {
+    len as c_int
+}
+
+#[no_mangle]
+extern "C" fn _read_(_fd: c_int, _buf: *mut u8, _len: usize) -> c_int {
+    0
+}
+
+#[no_mangle]
+extern "C" fn _exit_(status: c_int) -> ! {
+    crate::exit(status as i16)
+}
+
+/// Current end of the memory handed out by `_sbrk`, or 0 before the first call
+static mut BREAK: usize = 0;
+
+#[no_mangle]
+extern "C" fn _sbrk_(increment: isize) -> *mut u8 {
+    extern "C" {
+        static mut _sheap: u8;
+        static mut _eheap: u8;
+    }
+
+    msp430::interrupt::free(|_| unsafe {
+        let start = core::ptr::addr_of_mut!(_sheap) as usize;
+        let end = core::ptr::addr_of_mut!(_eheap) as usize;
+        let current = if BREAK == 0 { start } else { BREAK };
+
+        match current.checked_add_signed(increment) {
+            Some(new) if new >= start && new <= end => {
+                // The stack may already have grown below `_eheap`
+                check(new);
+                BREAK = new;
+                current as *mut u8
+            }
+            _ => usize::MAX as *mut u8,
+        }
+    })
+}
+
+/// Checks that the stack hasn't grown into the heap handed out to `malloc`
+///
+/// `_sbrk` only hands out memory up to `_eheap`, but nothing stops the stack from growing below
+/// it, over memory that `malloc` already uses. Every call to `_sbrk` compares the new end of the
+/// heap, plus a margin of `_heap_margin` bytes (32 by default), with the stack pointer; this
+/// function does the same with the current end, e.g. when called from the idle loop:
+///
+/// ``` ignore
+/// loop {
+///     msp430_rt::check_heap_collision();
+///     // ..
+/// }
+/// ```
+///
+/// On a collision the function `HeapCollision` is called with the end of the heap and the stack
+/// pointer, and never returns. The default loops forever with the interrupts disabled; define it
+/// to, e.g., record the fault and reset the device:
+///
+/// ``` ignore
+/// #[no_mangle]
+/// extern "C" fn HeapCollision(heap_end: usize, sp: usize) -> ! {
+///     // ..
+/// }
+/// ```
+///
+/// Like `check_stack_guard`, this only catches a stack that's still below the heap when the check
+/// runs.
+pub fn check_heap_collision() {
+    let current = unsafe { core::ptr::read_volatile(core::ptr::addr_of!(BREAK)) };
+    if current != 0 {
+        check(current);
+    }
+}
+
+/// Calls `HeapCollision` if the stack pointer is within `_heap_margin` bytes of `heap_end`
+fn check(heap_end: usize) {
+    extern "C" {
+        static _heap_margin: u8;
+
+        fn HeapCollision(heap_end: usize, sp: usize) -> !;
+    }
+
+    let sp: usize;
+    #[cfg(target_arch = "msp430")]
+    unsafe {
+        asm!("mov sp, {}", out(reg) sp, options(nomem, nostack, preserves_flags))
+    };
+    // Other targets have no such stack; the address of a local variable is close enough
+    #[cfg(not(target_arch = "msp430"))]
+    {
+        sp = &heap_end as *const usize as usize;
+    }
+
+    let margin = core::ptr::addr_of!(_heap_margin) as usize;
+    if heap_end.saturating_add(margin) > sp {
+        unsafe { HeapCollision(heap_end, sp) }
+    }
+}
+
+#[no_mangle]
+extern "C" fn HeapCollision_(_heap_end: usize, _sp: usize) -> ! {
+    crate::park()
+}
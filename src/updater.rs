@@ -0,0 +1,294 @@
+//! Minimal UART firmware updater
+//!
+//! A recovery mode that receives a new firmware image over a serial port and writes it into the
+//! inactive A/B slot (see `boot::inactive_slot`). Once the transfer completes the slot is marked
+//! as pending (see `boot::request_update`) and the device is reset, so the boot selector gives the
+//! new image a trial boot.
+//!
+//! The image is transferred as a raw binary of the slot, starting at the slot's origin, using the
+//! XMODEM-CRC protocol, which most terminal programs support (e.g. `sx` from `lrzsz`):
+//!
+//! ``` text
+//! $ msp430-elf-objcopy -O binary --gap-fill 0xFF app.elf app.bin
+//! $ sx app.bin < /dev/ttyACM0 > /dev/ttyACM0
+//! ```
+//!
+//! The updater writes the slot with plain memory writes, so the slot must be located in FRAM and
+//! must not be write protected. Flash devices, which need the slot erased and programmed through
+//! the flash controller, and have USCI instead of eUSCI modules, aren't supported; enabling the
+//! `uart-updater` feature for one of them is a compile error.
+//!
+//! The updater is entered by calling `run` with any `Serial` port, or `enter` to use the built-in
+//! eUSCI_A driver. `enter` uses the module whose registers start at the address of the
+//! `__updater_uart` symbol, and the baud rate settings given by the `__updater_brw` and
+//! `__updater_mctlw` symbols. The defaults select eUSCI_A0 of FR5xx/FR6xx devices at 9600 baud
+//! from a 1 MHz SMCLK; override them in `memory.x` if needed:
+//!
+//! ``` text
+//! /* eUSCI_A1 at 115200 baud from an 8 MHz SMCLK */
+//! __updater_uart = 0x05E0;
+//! __updater_brw = 4;
+//! __updater_mctlw = 0x5551;
+//! ```
+//!
+//! `enter` can be called from e.g. `ImageCrcMismatch` or `NoValidImage`. It's also called by the
+//! reset handler, right before calling the entry point, if `UpdateRequested` returns `true`. The
+//! default implementation of `UpdateRequested` returns `false`. Override it to, e.g., check the
+//! state of a boot pin. This is also a good place to select the UART function of the pins used by
+//! the updater:
+//!
+//! ``` ignore
+//! #[no_mangle]
+//! extern "C" fn UpdateRequested() -> bool {
+//!     // Enter the updater if P1.1 is held low
+//!     unsafe { core::ptr::read_volatile(0x0200 as *const u8) & (1 << 1) == 0 }
+//! }
+//! ```
+
+use crate::boot::{self, Slot};
+use crate::crc;
+
+/// A serial port
+pub trait Serial {
+    /// Returns the next received byte, if any. Must not block
+    fn read(&mut self) -> Option<u8>;
+
+    /// Sends a byte, blocking until it can be queued for transmission
+    fn write(&mut self, byte: u8);
+}
+
+/// Polled driver for an eUSCI_A module in UART mode
+///
+/// The driver doesn't configure the I/O pins of the module; select their UART function before
+/// using it.
+pub struct EusciUart {
+    base: usize,
+}
+
+// Register offsets from the base address of an eUSCI_A module
+const UCAXCTLW0: usize = 0x00;
+const UCAXBRW: usize = 0x06;
+const UCAXMCTLW: usize = 0x08;
+const UCAXRXBUF: usize = 0x0C;
+const UCAXTXBUF: usize = 0x0E;
+const UCAXIFG: usize = 0x1C;
+
+const UCSWRST: u16 = 1 << 0;
+const UCSSEL_SMCLK: u16 = 0b10 << 6;
+const UCRXIFG: u16 = 1 << 0;
+const UCTXIFG: u16 = 1 << 1;
+
+impl EusciUart {
+    /// Creates a driver for the eUSCI_A module whose registers start at `base`, e.g. `0x05C0` for
+    /// eUSCI_A0 on FR5xx/FR6xx devices
+    ///
+    /// # Safety
+    ///
+    /// `base` must be the base address of an eUSCI_A module that's not used by anything else.
+    pub const unsafe fn new(base: usize) -> Self {
+        EusciUart { base }
+    }
+
+    /// Configures the module to run from SMCLK with the given baud rate settings, 8 data bits, no
+    /// parity and one stop bit
+    ///
+    /// `brw` and `mctlw` are the values of the `UCAxBRW` and `UCAxMCTLW` registers; refer to the
+    /// baud rate tables in the Family User's Guide.
+    pub fn configure(&mut self, brw: u16, mctlw: u16) {
+        self.write_reg(UCAXCTLW0, UCSWRST);
+        self.write_reg(UCAXCTLW0, UCSWRST | UCSSEL_SMCLK);
+        self.write_reg(UCAXBRW, brw);
+        self.write_reg(UCAXMCTLW, mctlw);
+        self.write_reg(UCAXCTLW0, UCSSEL_SMCLK);
+    }
+
+    fn read_reg(&self, offset: usize) -> u16 {
+        unsafe { core::ptr::read_volatile((self.base + offset) as *const u16) }
+    }
+
+    fn write_reg(&mut self, offset: usize, value: u16) {
+        unsafe { core::ptr::write_volatile((self.base + offset) as *mut u16, value) }
+    }
+}
+
+impl Serial for EusciUart {
+    fn read(&mut self) -> Option<u8> {
+        if self.read_reg(UCAXIFG) & UCRXIFG != 0 {
+            Some(self.read_reg(UCAXRXBUF) as u8)
+        } else {
+            None
+        }
+    }
+
+    fn write(&mut self, byte: u8) {
+        while self.read_reg(UCAXIFG) & UCTXIFG == 0 {}
+        self.write_reg(UCAXTXBUF, u16::from(byte));
+    }
+}
+
+/// Errors that abort a transfer
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// The sender didn't respond in time
+    Timeout,
+    /// The sender cancelled the transfer
+    Cancelled,
+    /// The image doesn't fit in the slot
+    TooLarge,
+    /// Too many consecutive corrupted packets
+    TooManyErrors,
+}
+
+const SOH: u8 = 0x01;
+const EOT: u8 = 0x04;
+const ACK: u8 = 0x06;
+const NAK: u8 = 0x15;
+const CAN: u8 = 0x18;
+const CRC_MODE: u8 = b'C';
+// Padding of the last packet, along with erased flash, `0xFF`
+const PAD: u8 = 0x1A;
+
+const PACKET_SIZE: usize = 128;
+const MAX_ERRORS: u8 = 10;
+
+// Number of polls of the serial port before giving up on a byte. Roughly a few seconds at 8 MHz
+const TIMEOUT_POLLS: u32 = 1_000_000;
+
+fn read_timeout<S: Serial>(serial: &mut S) -> Result<u8, Error> {
+    for _ in 0..TIMEOUT_POLLS {
+        if let Some(byte) = serial.read() {
+            return Ok(byte);
+        }
+    }
+
+    Err(Error::Timeout)
+}
+
+/// Receives an image over XMODEM-CRC and writes it into `dest`
+///
+/// Returns the number of bytes written to `dest`. The packets carry 128 bytes each; the last one
+/// may run past the end of `dest` if the bytes past it are XMODEM padding, `0x1A` or `0xFF`, which
+/// are dropped. On error the transfer is cancelled and `dest` may have been
+/// partially written.
+pub fn receive<S: Serial>(serial: &mut S, dest: &mut [u8]) -> Result<usize, Error> {
+    let mut expected: u8 = 1;
+    let mut offset = 0;
+    let mut errors = 0;
+    let mut packet = [0; PACKET_SIZE];
+
+    // Ask the sender to start a transfer in CRC mode
+    let mut header = loop {
+        serial.write(CRC_MODE);
+        match read_timeout(serial) {
+            Ok(byte) => break byte,
+            Err(_) if errors < MAX_ERRORS => errors += 1,
+            Err(e) => return Err(e),
+        }
+    };
+    errors = 0;
+
+    let result = loop {
+        match header {
+            SOH => {
+                let received = (|| {
+                    let block = read_timeout(serial)?;
+                    let inverse = read_timeout(serial)?;
+                    for byte in packet.iter_mut() {
+                        *byte = read_timeout(serial)?;
+                    }
+                    let crc =
+                        u16::from(read_timeout(serial)?) << 8 | u16::from(read_timeout(serial)?);
+
+                    // XMODEM-CRC uses an initial value of 0
+                    let intact = block == !inverse && crc::crc16_update(0, &packet) == crc;
+                    Ok((block, intact))
+                })();
+
+                match received {
+                    Ok((block, true)) if block == expected => {
+                        // The slot needn't be a multiple of the packet size
+                        let len = PACKET_SIZE.min(dest.len() - offset);
+                        if packet[len..]
+                            .iter()
+                            .any(|&byte| byte != PAD && byte != 0xFF)
+                        {
+                            break Err(Error::TooLarge);
+                        }
+
+                        dest[offset..offset + len].copy_from_slice(&packet[..len]);
+                        offset += len;
+                        expected = expected.wrapping_add(1);
+                        errors = 0;
+                        serial.write(ACK);
+                    }
+                    // Retransmission of the previous packet; our ACK got lost
+                    Ok((block, true)) if block == expected.wrapping_sub(1) => serial.write(ACK),
+                    Ok(_) | Err(Error::Timeout) if errors < MAX_ERRORS => {
+                        errors += 1;
+                        serial.write(NAK);
+                    }
+                    Ok(_) => break Err(Error::TooManyErrors),
+                    Err(e) => break Err(e),
+                }
+            }
+            EOT => {
+                serial.write(ACK);
+                break Ok(offset);
+            }
+            CAN => break Err(Error::Cancelled),
+            // Line noise; wait for the sender to retransmit
+            _ => {}
+        }
+
+        header = match read_timeout(serial) {
+            Ok(byte) => byte,
+            Err(e) => break Err(e),
+        };
+    };
+
+    if result.is_err() {
+        serial.write(CAN);
+        serial.write(CAN);
+    }
+
+    result
+}
+
+/// Receives a firmware image into the inactive slot and resets the device to boot it
+///
+/// Failed transfers are retried indefinitely.
+pub fn run<S: Serial>(serial: &mut S) -> ! {
+    let slot: Slot = boot::inactive_slot();
+    let (start, end) = boot::bounds(slot);
+    let dest = unsafe { core::slice::from_raw_parts_mut(start as *mut u8, end - start) };
+
+    loop {
+        if receive(serial, dest).is_ok() {
+            boot::request_update(slot);
+            crate::reset::software_reset();
+        }
+    }
+}
+
+/// Runs `run` using the built-in eUSCI_A driver configured by the `__updater_uart`,
+/// `__updater_brw` and `__updater_mctlw` symbols
+pub fn enter() -> ! {
+    extern "C" {
+        // Absolute symbols; their addresses are the configuration values
+        static __updater_uart: u8;
+        static __updater_brw: u8;
+        static __updater_mctlw: u8;
+    }
+
+    let value = |symbol: &u8| symbol as *const u8 as usize;
+    unsafe {
+        let mut uart = EusciUart::new(value(&__updater_uart));
+        uart.configure(value(&__updater_brw) as u16, value(&__updater_mctlw) as u16);
+        run(&mut uart)
+    }
+}
+
+#[no_mangle]
+extern "C" fn UpdateRequested_() -> bool {
+    false
+}
@@ -0,0 +1,301 @@
+//! Runtime access to the vector table
+//!
+//! `handler_for` reports which handler is installed for an interrupt, e.g. for self-tests or
+//! debug consoles.
+//!
+//! With the `fram-vectors` feature, `install` replaces the handler of an interrupt at runtime.
+//! This needs a vector table located in FRAM, so it's only available on FRAM devices.
+//!
+//! On FR2xx/FR4xx devices the program FRAM, which contains the vector table, is write protected
+//! by the `PFWP` bit of the `SYSCFG0` register. `install` temporarily lifts the protection (see
+//! `fram::unprotected`) when the address of the register is given by the `__syscfg0` symbol:
+//!
+//! ``` text
+//! /* FR2xx/FR4xx devices */
+//! __syscfg0 = 0x0160;
+//! ```
+//!
+//! On FR5xx/FR6xx devices the vector table is only write protected if the MPU is configured to do
+//! so; in that case the MPU must be reconfigured before calling `install`.
+//!
+//! With the `ram-vectors` feature, `set_handler` does the same on any device, at the cost of a few
+//! cycles of latency per interrupt: every hardware vector, except the reset vector, points to a
+//! stub that jumps through a vector table in RAM, which `set_handler` updates. The reset handler
+//! initializes the RAM vector table with the handlers that were linked into the vector table.
+//!
+//! The number of vectors of the device, including the reset vector, must be provided through the
+//! `MSP430_RT_RAM_VECTORS` environment variable at build time.
+//!
+//! ``` text
+//! $ MSP430_RT_RAM_VECTORS=16 cargo build --release
+//! ```
+//!
+//! In builds for other targets, e.g. of host tests, `#[interrupt]` turns the name of the interrupt
+//! into a `Binding` constant, and `vector_table!` lists the vectors of the device in a
+//! `VECTOR_TABLE` constant, so that tests can check which handler each vector is bound to,
+//! including the ones left to `DefaultHandler`, and call them:
+//!
+//! ``` ignore
+//! #[interrupt]
+//! fn TIMER0_A0() {}
+//!
+//! // in the module that defines the handlers, with the interrupts of the device in order
+//! msp430_rt::vector_table!(PORT1, PORT2, TIMER0_A0, TIMER0_A1);
+//!
+//! #[test]
+//! fn vectors() {
+//!     let bound = VECTOR_TABLE.iter().filter(|vector| vector.handler.is_some());
+//!     assert!(bound.map(|vector| vector.name).eq(["TIMER0_A0"]));
+//!     (TIMER0_A0.handler.unwrap())();
+//! }
+//! ```
+
+/// An interrupt handler
+#[cfg(target_arch = "msp430")]
+pub type Handler = unsafe extern "msp430-interrupt" fn();
+
+/// An interrupt handler
+///
+/// Other targets have no `msp430-interrupt` ABI; handlers are plain C functions there.
+#[cfg(not(target_arch = "msp430"))]
+pub type Handler = unsafe extern "C" fn();
+
+/// The handler bound to an interrupt vector, in builds for targets other than MSP430
+///
+/// See the module documentation.
+#[cfg(not(target_arch = "msp430"))]
+#[derive(Clone, Copy, Debug)]
+pub struct Binding {
+    /// Name of the interrupt
+    pub name: &'static str,
+    /// Runs the handler, `None` if the vector is left to `DefaultHandler`
+    ///
+    /// Naked handlers only run on MSP430; this panics for them.
+    pub handler: Option<fn()>,
+}
+
+/// Lists the bindings of the interrupt vectors `$vector`, in order, in the `VECTOR_TABLE` constant
+/// of builds for targets other than MSP430
+///
+/// The vectors that `#[interrupt]` doesn't bind in the module that invokes this macro are `None`.
+/// See the module documentation.
+#[macro_export]
+macro_rules! vector_table {
+    ($($vector:ident),* $(,)?) => {
+        // Shadowed by the constants of the handlers defined in this module
+        #[cfg(not(target_arch = "msp430"))]
+        #[allow(dead_code, non_upper_case_globals)]
+        mod __msp430_rt_unbound {
+            $(
+                pub const $vector: $crate::vectors::Binding = $crate::vectors::Binding {
+                    name: stringify!($vector),
+                    handler: None,
+                };
+            )*
+        }
+
+        #[cfg(not(target_arch = "msp430"))]
+        #[allow(unused_imports)]
+        use __msp430_rt_unbound::*;
+
+        /// The handlers bound to the interrupt vectors
+        #[cfg(not(target_arch = "msp430"))]
+        #[allow(dead_code)]
+        pub const VECTOR_TABLE: &[$crate::vectors::Binding] = &[$($vector),*];
+    };
+}
+
+/// The vector doesn't exist, or is the reset vector
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct InvalidVector;
+
+/// An entry of the interrupts portion of the vector table
+#[derive(Clone, Copy)]
+#[repr(C)]
+pub union Vector {
+    handler: Handler,
+    reserved: u16,
+}
+
+/// Builder of the interrupts portion of the vector table, `__INTERRUPTS`
+///
+/// This is an alternative to writing the array of `Vector`s by hand. All the `N` vectors start out
+/// pointing to `DefaultHandler`. Out of range indices are rejected at compile time when the table
+/// is built in a `static` initializer.
+///
+/// ``` ignore
+/// use msp430_rt::vectors::{Vector, VectorTable};
+///
+/// extern "msp430-interrupt" {
+///     fn Foo();
+///     fn Bar();
+/// }
+///
+/// #[link_section = ".vector_table.interrupts"]
+/// #[no_mangle]
+/// static __INTERRUPTS: [Vector; 15] = VectorTable::new()
+///     .reserved(0)
+///     .reserved(1)
+///     .with(2, Foo)
+///     .with(4, Bar)
+///     .build();
+/// ```
+pub struct VectorTable<const N: usize> {
+    vectors: [Vector; N],
+}
+
+impl<const N: usize> VectorTable<N> {
+    /// Creates a table where every vector points to `DefaultHandler`
+    pub const fn new() -> Self {
+        #[cfg(target_arch = "msp430")]
+        extern "msp430-interrupt" {
+            fn DefaultHandler();
+        }
+        #[cfg(not(target_arch = "msp430"))]
+        extern "C" {
+            fn DefaultHandler();
+        }
+
+        VectorTable {
+            vectors: [Vector {
+                handler: DefaultHandler,
+            }; N],
+        }
+    }
+
+    /// Sets `handler` as the handler of interrupt `index`
+    pub const fn with(mut self, index: usize, handler: Handler) -> Self {
+        assert!(index < N, "vector index out of range");
+        self.vectors[index] = Vector { handler };
+        self
+    }
+
+    /// Marks vector `index` as reserved, i.e. sets it to 0
+    pub const fn reserved(mut self, index: usize) -> Self {
+        assert!(index < N, "vector index out of range");
+        self.vectors[index] = Vector { reserved: 0 };
+        self
+    }
+
+    /// Returns the vectors, to be placed in the `.vector_table.interrupts` section
+    pub const fn build(self) -> [Vector; N] {
+        self.vectors
+    }
+}
+
+/// Returns the entries of the vector table
+fn table() -> &'static mut [u16] {
+    extern "C" {
+        static mut __vector_table_start: u16;
+        static __vector_table_end: u16;
+    }
+
+    unsafe {
+        let start = &mut __vector_table_start as *mut u16;
+        let len = (&__vector_table_end as *const u16 as usize - start as usize) / 2;
+        core::slice::from_raw_parts_mut(start, len)
+    }
+}
+
+/// Returns the address of the handler of interrupt `vector`
+///
+/// `vector` is the position of the interrupt in the vector table, starting at 0 at the beginning of
+/// the table; the last vector is the reset vector. The vector table is read at its linked address,
+/// so handlers installed at runtime are reported too; with the `ram-vectors` feature the RAM vector
+/// table is read instead, except for the reset vector. Returns 0, like for a reserved vector, if
+/// the vector doesn't exist.
+pub fn handler_for(vector: u8) -> usize {
+    let vector = usize::from(vector);
+    let table = table();
+    if vector >= table.len() {
+        return 0;
+    }
+
+    #[cfg(feature = "ram-vectors")]
+    let table = if vector + 1 < table.len() {
+        ram_table()
+    } else {
+        table
+    };
+
+    usize::from(unsafe { core::ptr::read_volatile(&table[vector]) })
+}
+
+/// Returns the entries of the RAM vector table
+#[cfg(feature = "ram-vectors")]
+fn ram_table() -> &'static mut [u16] {
+    extern "C" {
+        static mut __ram_vectors_start: u16;
+        static __vector_table_start: u16;
+        static __vector_table_end: u16;
+    }
+
+    unsafe {
+        // Both tables have the same size as the vector table
+        let len = (&__vector_table_end as *const u16 as usize
+            - &__vector_table_start as *const u16 as usize)
+            / 2;
+        core::slice::from_raw_parts_mut(&mut __ram_vectors_start, len)
+    }
+}
+
+/// Initializes the RAM vector table with the handlers linked into the vector table
+#[cfg(feature = "ram-vectors")]
+pub(crate) unsafe fn init_ram_vectors() {
+    extern "C" {
+        static __vector_defaults_start: u16;
+    }
+
+    let table = ram_table();
+    let defaults = core::slice::from_raw_parts(&__vector_defaults_start, table.len());
+    table.copy_from_slice(defaults);
+}
+
+/// Sets `handler` as the handler of interrupt `vector` in the RAM vector table and returns the
+/// address of the previous handler
+///
+/// `vector` is the position of the interrupt in the vector table, starting at 0 at the beginning of
+/// the table. The reset vector can't be replaced.
+#[cfg(feature = "ram-vectors")]
+pub fn set_handler(vector: usize, handler: Handler) -> Result<usize, InvalidVector> {
+    let table = ram_table();
+    // The last entry is the reset vector
+    if vector + 1 >= table.len() {
+        return Err(InvalidVector);
+    }
+
+    let entry = &mut table[vector] as *mut u16;
+    // A word write can't be interrupted, but the read-modify-write sequence can
+    let previous = msp430::interrupt::free(|_| unsafe {
+        let previous = core::ptr::read_volatile(entry);
+        core::ptr::write_volatile(entry, handler as usize as u16);
+        previous
+    });
+
+    Ok(usize::from(previous))
+}
+
+/// Installs `handler` as the handler of interrupt `vector` and returns the address of the
+/// previous handler
+///
+/// `vector` is the position of the interrupt in the vector table, starting at 0 at the beginning of
+/// the table. The reset vector can't be replaced. The update is done with interrupts disabled.
+/// With the `vector-crc` feature the stored CRC of the vector table is updated as well.
+#[cfg(feature = "fram-vectors")]
+pub fn install(vector: usize, handler: Handler) -> Result<usize, InvalidVector> {
+    let table = table();
+    // The last entry is the reset vector
+    if vector + 1 >= table.len() {
+        return Err(InvalidVector);
+    }
+
+    crate::fram::unprotected(|| unsafe {
+        let entry = &mut table[vector] as *mut u16;
+        let previous = core::ptr::read_volatile(entry);
+        core::ptr::write_volatile(entry, handler as usize as u16);
+        #[cfg(feature = "vector-crc")]
+        crate::crc::store_vector_table_crc();
+
+        Ok(usize::from(previous))
+    })
+}
@@ -0,0 +1,143 @@
+//! Watchdog supervision of tasks
+//!
+//! A hardware watchdog only proves that *some* code still runs. This module services the watchdog
+//! timer (WDT) on behalf of up to 16 tasks instead: each task registers itself and checks in
+//! regularly, and a central `kick`, e.g. called from a periodic timer interrupt or from the idle
+//! loop, only clears the WDT counter once every registered task has checked in since the previous
+//! time it did. A task that hangs thus leads to a reset even if the rest of the program keeps
+//! running.
+//!
+//! `set_patience` sets how many calls to `kick` may go by without the check-in of every task.
+//! Once that's exceeded `kick` calls the function `WatchdogMissed` with the mask of the tasks that
+//! didn't check in, which never returns. The default loops forever with the interrupts disabled,
+//! so that the WDT, which isn't serviced anymore, resets the device. The program can override it,
+//! e.g. to record which tasks hung before resetting the device:
+//!
+//! ``` ignore
+//! #[no_mangle]
+//! extern "C" fn WatchdogMissed(missing: u16) -> ! {
+//!     // ..
+//!     msp430_rt::reset::software_reset()
+//! }
+//! ```
+//!
+//! The WDT itself must be configured by the program, e.g. in watchdog mode with an interval well
+//! above the period of `kick`; `kick` keeps its configuration and only clears its counter. The
+//! address of the `WDTCTL` register is given by the `__wdtctl` symbol, which `MSP430_MCU` sets; the
+//! default, `0x0120`, matches the F1xx, F2xx, F4xx and G2xx families.
+//!
+//! ``` ignore
+//! use msp430_rt::watchdog::{self, Task};
+//!
+//! const RADIO: Task = Task::new(0);
+//! const SENSORS: Task = Task::new(1);
+//!
+//! watchdog::set_patience(10);
+//! RADIO.register();
+//! SENSORS.register();
+//!
+//! // in the radio task
+//! RADIO.check_in();
+//!
+//! // in the 10 ms tick handler
+//! watchdog::kick();
+//! ```
+
+use msp430::interrupt;
+
+// Tasks that are supervised, tasks that checked in since the WDT was last serviced, number of
+// kicks since then and number of kicks allowed before the crash path is taken
+static mut REGISTERED: u16 = 0;
+static mut CHECKED_IN: u16 = 0;
+static mut MISSED: u16 = 0;
+static mut PATIENCE: u16 = 0;
+
+// Password and counter clear bit of `WDTCTL`
+const WDTPW: u16 = 0x5A << 8;
+const WDTCNTCL: u16 = 1 << 3;
+
+/// A task supervised by the watchdog, identified by a number from 0 to 15
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Task {
+    mask: u16,
+}
+
+impl Task {
+    /// Creates the task with identifier `id`
+    pub const fn new(id: u8) -> Self {
+        assert!(id < 16, "watchdog task identifiers go from 0 to 15");
+
+        Task { mask: 1 << id }
+    }
+
+    /// Starts supervising the task; it counts as checked in until the next service of the WDT
+    pub fn register(self) {
+        interrupt::free(|_| unsafe {
+            REGISTERED |= self.mask;
+            CHECKED_IN |= self.mask;
+        });
+    }
+
+    /// Stops supervising the task
+    pub fn unregister(self) {
+        interrupt::free(|_| unsafe { REGISTERED &= !self.mask });
+    }
+
+    /// Reports that the task is still making progress
+    pub fn check_in(self) {
+        interrupt::free(|_| unsafe { CHECKED_IN |= self.mask });
+    }
+}
+
+/// Sets the number of calls to `kick` allowed between two services of the WDT, 0 by default
+pub fn set_patience(kicks: u16) {
+    interrupt::free(|_| unsafe { PATIENCE = kicks });
+}
+
+/// Services the WDT if every registered task has checked in since it was last serviced
+///
+/// Otherwise, once the patience is exhausted, `WatchdogMissed` is called.
+pub fn kick() {
+    extern "C" {
+        fn WatchdogMissed(missing: u16) -> !;
+    }
+
+    let missing = interrupt::free(|_| unsafe {
+        let missing = REGISTERED & !CHECKED_IN;
+        if missing == 0 {
+            service();
+            CHECKED_IN = 0;
+            MISSED = 0;
+        } else {
+            MISSED = MISSED.saturating_add(1);
+        }
+
+        if MISSED > PATIENCE {
+            missing
+        } else {
+            0
+        }
+    });
+
+    if missing != 0 {
+        unsafe { WatchdogMissed(missing) }
+    }
+}
+
+/// Clears the counter of the WDT, keeping its configuration
+unsafe fn service() {
+    extern "C" {
+        // Absolute symbol; its address is the address of the `WDTCTL` register
+        static __wdtctl: u8;
+    }
+
+    let wdtctl = core::ptr::addr_of!(__wdtctl) as usize as *mut u16;
+    // The upper byte reads back as a different value than the password
+    let value = core::ptr::read_volatile(wdtctl) & 0xFF;
+    core::ptr::write_volatile(wdtctl, WDTPW | value | WDTCNTCL);
+}
+
+#[no_mangle]
+extern "C" fn WatchdogMissed_(_missing: u16) -> ! {
+    crate::park()
+}